@@ -0,0 +1,222 @@
+use serde::Serialize;
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::http::{Handler, Request, Response};
+use crate::ip_interfaces::get_ip_interfaces;
+use crate::machine_main_ip::get_local_ip;
+use crate::route_table::route_table::RouteCollector;
+use crate::route_table::rules::{RouteQuery, parse_fwmark};
+use crate::route_table::{IpVersion, RouteEntry};
+
+/// The endpoints of the API, listed at `/`.
+const ENDPOINTS: [&str; 5] = [
+    "/interfaces?family=ipv4|ipv6|all",
+    "/routes?family=ipv4|ipv6|all&table=main|all|NAME",
+    "/gateway?family=ipv4|ipv6|all",
+    "/main-ip",
+    "/route/lookup?dst=IP&from=IP&fwmark=MARK&iif=IFACE",
+];
+
+/// The default routes per address family, as returned by `/gateway`.
+#[derive(Serialize)]
+struct Gateways<'a> {
+    ipv4: Option<&'a RouteEntry>,
+    ipv6: Option<&'a RouteEntry>,
+}
+
+fn error(status: u16, message: impl std::fmt::Display) -> Response {
+    Response::json(status, &json!({ "error": message.to_string() }))
+}
+
+/// Returns the `family` query parameter, which defaults to "all".
+fn family(request: &Request) -> Result<&str, Response> {
+    match request.param("family").unwrap_or("all") {
+        family @ ("ipv4" | "ipv6" | "all") => Ok(family),
+        other => Err(error(
+            400,
+            format!("Invalid family '{}', expected ipv4, ipv6 or all", other),
+        )),
+    }
+}
+
+/// Parses an optional query parameter, answering 400 if it is malformed.
+fn param<T>(
+    request: &Request,
+    name: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Option<T>, Response> {
+    match request.param(name) {
+        Some(value) => parse(value)
+            .map(Some)
+            .map_err(|e| error(400, format!("Invalid parameter '{}': {}", name, e))),
+        None => Ok(None),
+    }
+}
+
+fn parse_ip(value: &str) -> Result<IpAddr, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not an IP address", value))
+}
+
+fn interfaces(request: &Request) -> Result<Response, Response> {
+    let family = family(request)?;
+    let mut interfaces = get_ip_interfaces().map_err(|e| error(500, e))?;
+    interfaces.retain(|interface| match family {
+        "ipv4" => interface.is_ipv4(),
+        "ipv6" => interface.is_ipv6(),
+        _ => true,
+    });
+    Ok(Response::json(200, &interfaces))
+}
+
+fn routes(request: &Request, collector: &RouteCollector) -> Result<Response, Response> {
+    let family = family(request)?;
+    let table = request.param("table").unwrap_or("main");
+    let route_table = collector.collect().map_err(|e| error(500, e))?;
+    let selected = route_table
+        .select(table, family, &collector.table_names())
+        .map_err(|e| error(404, e))?;
+    Ok(Response::json(200, &selected))
+}
+
+fn gateway(request: &Request, collector: &RouteCollector) -> Result<Response, Response> {
+    let family = family(request)?;
+    let route_table = collector.collect().map_err(|e| error(500, e))?;
+    let gateways = Gateways {
+        ipv4: (family != "ipv6")
+            .then(|| route_table.get_default_gateway(IpVersion::IPv4))
+            .flatten(),
+        ipv6: (family != "ipv4")
+            .then(|| route_table.get_default_gateway(IpVersion::IPv6))
+            .flatten(),
+    };
+    Ok(Response::json(200, &gateways))
+}
+
+fn main_ip() -> Result<Response, Response> {
+    let ip = get_local_ip().map_err(|e| error(503, e))?;
+    Ok(Response::json(200, &json!({ "main_ip": ip })))
+}
+
+fn lookup(request: &Request, collector: &RouteCollector) -> Result<Response, Response> {
+    let dst =
+        param(request, "dst", parse_ip)?.ok_or_else(|| error(400, "Missing parameter 'dst'"))?;
+    let query = RouteQuery {
+        dst,
+        src: param(request, "from", parse_ip)?,
+        fwmark: param(request, "fwmark", parse_fwmark)?,
+        iif: request.param("iif").map(str::to_string),
+        oif: None,
+    };
+    let route_table = collector.collect().map_err(|e| error(500, e))?;
+    Ok(Response::json(200, &route_table.lookup(&query)))
+}
+
+/// Answers an API request with the same JSON the command line prints with
+/// `--format json`. Errors are returned as `{"error": "..."}`.
+fn handle(request: &Request, collector: &RouteCollector) -> Response {
+    let result = match request.path.as_str() {
+        "/" => Ok(Response::json(200, &json!({ "endpoints": ENDPOINTS }))),
+        "/interfaces" => interfaces(request),
+        "/routes" => routes(request, collector),
+        "/gateway" => gateway(request, collector),
+        "/main-ip" => main_ip(),
+        "/route/lookup" => lookup(request, collector),
+        path => Err(error(404, format!("Unknown endpoint '{}'", path))),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+/// Returns the HTTP handler serving the JSON API, with the route table
+/// collected by `collector`.
+pub fn handler(collector: RouteCollector) -> Handler {
+    Arc::new(move |request| handle(request, &collector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::serve_tcp;
+    use serde_json::Value;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// Sends a GET request over `stream` and returns the status code and the
+    /// parsed JSON body.
+    fn get(mut stream: impl Read + Write, target: &str) -> (u16, Value) {
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("Content-Type: application/json"));
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_api_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve_tcp(listener, handler(RouteCollector::default())));
+        let get = |target: &str| get(TcpStream::connect(addr).unwrap(), target);
+
+        let (status, interfaces) = get("/interfaces?family=ipv4");
+        assert_eq!(status, 200);
+        let interfaces = interfaces.as_array().unwrap();
+        assert!(
+            interfaces
+                .iter()
+                .any(|interface| interface["ip"] == "127.0.0.1")
+        );
+        assert!(
+            interfaces
+                .iter()
+                .all(|interface| interface["prefix_len"].is_u64())
+        );
+
+        let (status, routes) = get("/routes?family=ipv6");
+        assert_eq!(status, 200);
+        assert_eq!(routes["ipv4_routes"], Value::Array(Vec::new()));
+        assert!(routes["ipv6_routes"].is_array());
+
+        let (status, gateway) = get("/gateway?family=ipv4");
+        assert_eq!(status, 200);
+        assert!(gateway["ipv6"].is_null());
+
+        let (status, lookup) = get("/route/lookup?dst=127.0.0.1");
+        assert_eq!(status, 200);
+        if cfg!(target_os = "linux") {
+            assert_eq!(lookup["route"]["iface"], "lo");
+        }
+
+        let (status, body) = get("/route/lookup?dst=nope");
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("dst"));
+        assert_eq!(get("/route/lookup").0, 400);
+        assert_eq!(get("/routes?family=ipx").0, 400);
+        assert_eq!(get("/unknown").0, 404);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_api_over_unix_socket() {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        let path = std::env::temp_dir().join(format!("ls_net_api_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            crate::http::serve_unix(listener, handler(RouteCollector::default()))
+        });
+
+        let (status, body) = get(UnixStream::connect(&path).unwrap(), "/");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body["endpoints"].as_array().unwrap().len(), ENDPOINTS.len());
+    }
+}
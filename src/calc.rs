@@ -0,0 +1,407 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::ip_interfaces::{InterfaceAddr, get_ip_interfaces};
+use crate::prefix::{self, Prefix};
+use crate::render::{Element, Output};
+
+/// The most subnets `--split` lists for a single prefix.
+const MAX_SPLIT: u128 = 65536;
+
+/// An interface address whose subnet contains the calculated address.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocalSubnet {
+    pub iface: String,
+    pub address: IpAddr,
+    pub prefix: Prefix,
+}
+
+/// The addresses and masks of a prefix, as printed by `calc`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrefixInfo {
+    /// The address as given, which may have host bits set.
+    pub address: IpAddr,
+    pub prefix: Prefix,
+    pub network: IpAddr,
+    /// Only IPv4 prefixes of `/30` and shorter have a broadcast address.
+    pub broadcast: Option<IpAddr>,
+    pub netmask: IpAddr,
+    pub wildcard: IpAddr,
+    pub first_host: IpAddr,
+    pub last_host: IpAddr,
+    /// The number of usable host addresses, saturating at `u128::MAX` for
+    /// `::/0`.
+    pub host_count: u128,
+    /// The local interface subnets containing `address`.
+    pub local: Vec<LocalSubnet>,
+    /// The subnets from `--split`; empty without it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subnets: Vec<Prefix>,
+}
+
+/// Everything `calc` prints: one entry per prefix and, if requested, the
+/// aggregation of all prefixes and their smallest common supernet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalcReport {
+    pub prefixes: Vec<PrefixInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<Vec<Prefix>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supernet: Option<Prefix>,
+}
+
+/// Parses `addr/len` or a bare address into the address as given and its
+/// prefix.
+///
+/// # Errors
+///
+/// Returns an error if the address or the prefix length is invalid.
+pub fn parse_input(input: &str) -> Result<(IpAddr, Prefix)> {
+    let prefix: Prefix = input.parse()?;
+    let address = input
+        .split('/')
+        .next()
+        .and_then(|address| address.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid IP address in prefix '{}'", input))?;
+    Ok((address, prefix))
+}
+
+/// Parses the prefix length of `--split`, with or without a leading slash.
+///
+/// # Errors
+///
+/// Returns an error if the value is not a number of at most 128.
+pub fn parse_split(value: &str) -> Result<u8, String> {
+    value
+        .trim_start_matches('/')
+        .parse::<u8>()
+        .ok()
+        .filter(|len| *len <= 128)
+        .ok_or_else(|| format!("invalid prefix length '{}'", value))
+}
+
+/// Returns the addresses and masks of `prefix`, and the subnets of
+/// `interfaces` containing `address`.
+///
+/// IPv4 prefixes of `/30` and shorter exclude the network and broadcast
+/// addresses from the host range; `/31` and `/32` (RFC 3021) and all IPv6
+/// prefixes include every address.
+pub fn prefix_info(address: IpAddr, prefix: Prefix, interfaces: &[InterfaceAddr]) -> PrefixInfo {
+    let (network, last) = (prefix.network(), prefix.last());
+    let has_broadcast = address.is_ipv4() && prefix.prefix_len() <= 30;
+    let (first_host, last_host, host_count) = if has_broadcast {
+        (step(network, 1), step(last, -1), prefix.size() - 2)
+    } else {
+        (network, last, prefix.size())
+    };
+
+    let local = interfaces
+        .iter()
+        .filter_map(|interface| {
+            let subnet = Prefix::new(interface.ip, interface.prefix_len);
+            subnet.contains(address).then(|| LocalSubnet {
+                iface: interface.name.clone(),
+                address: interface.ip,
+                prefix: subnet,
+            })
+        })
+        .collect();
+
+    PrefixInfo {
+        address,
+        prefix,
+        network,
+        broadcast: has_broadcast.then_some(last),
+        netmask: prefix.netmask(),
+        wildcard: prefix.hostmask(),
+        first_host,
+        last_host,
+        host_count,
+        local,
+        subnets: Vec::new(),
+    }
+}
+
+/// Returns the address `delta` after `ip`, for stepping inside a prefix.
+fn step(ip: IpAddr, delta: i8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(
+            u32::from(v4).wrapping_add_signed(i32::from(delta)),
+        )),
+        IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(
+            u128::from(v6).wrapping_add_signed(i128::from(delta)),
+        )),
+    }
+}
+
+/// Returns the subnets of length `len` that `prefix` splits into.
+///
+/// # Errors
+///
+/// Returns an error if `len` is shorter than the prefix, too long for its
+/// address family or would give more than 65536 subnets.
+pub fn split(prefix: &Prefix, len: u8) -> Result<Vec<Prefix>> {
+    if len < prefix.prefix_len() || len > prefix.max_len() {
+        return Err(anyhow!("Cannot split {} into /{} subnets", prefix, len));
+    }
+    let count = 1u128
+        .checked_shl(u32::from(len - prefix.prefix_len()))
+        .unwrap_or(u128::MAX);
+    if count > MAX_SPLIT {
+        return Err(anyhow!(
+            "Splitting {} into /{} gives {} subnets, more than {}",
+            prefix,
+            len,
+            count,
+            MAX_SPLIT
+        ));
+    }
+    Ok(prefix.subnets(len).collect())
+}
+
+/// Returns the smallest prefix containing all of `prefixes`.
+///
+/// # Errors
+///
+/// Returns an error if `prefixes` is empty or mixes IPv4 and IPv6.
+pub fn supernet(prefixes: &[Prefix]) -> Result<Prefix> {
+    let (first, rest) = prefixes
+        .split_first()
+        .ok_or_else(|| anyhow!("No prefixes to aggregate"))?;
+    let mut supernet = *first;
+    for prefix in rest {
+        if prefix.max_len() != supernet.max_len() {
+            return Err(anyhow!("Cannot combine IPv4 and IPv6 prefixes"));
+        }
+        while supernet.prefix_len() > prefix.prefix_len() || !supernet.contains(prefix.network()) {
+            let Some(shorter) = supernet.supernet() else {
+                break;
+            };
+            supernet = shorter;
+        }
+    }
+    Ok(supernet)
+}
+
+/// Builds the report for `inputs`.
+///
+/// # Errors
+///
+/// Returns an error if an input is not a valid prefix, or splitting or
+/// aggregating fails.
+pub fn calculate(
+    inputs: &[String],
+    split_len: Option<u8>,
+    aggregate: bool,
+    interfaces: &[InterfaceAddr],
+) -> Result<CalcReport> {
+    let mut prefixes = Vec::new();
+    for input in inputs {
+        let (address, prefix) = parse_input(input)?;
+        let mut info = prefix_info(address, prefix, interfaces);
+        if let Some(len) = split_len {
+            info.subnets = split(&prefix, len)?;
+        }
+        prefixes.push(info);
+    }
+
+    let (aggregated, supernet) = if aggregate {
+        let all: Vec<Prefix> = prefixes.iter().map(|info| info.prefix).collect();
+        (Some(prefix::aggregate(&all)), Some(supernet(&all)?))
+    } else {
+        (None, None)
+    };
+
+    Ok(CalcReport {
+        prefixes,
+        aggregate: aggregated,
+        supernet,
+    })
+}
+
+/// Calculates the addresses, masks and host ranges of the given prefixes
+/// and writes them to `output` as text, or as JSON if its format is "json".
+///
+/// With `split_len`, each prefix is also split into subnets of that length.
+/// With `aggregate`, the prefixes are merged into the fewest covering
+/// prefixes, and their smallest common supernet is shown.
+///
+/// # Errors
+///
+/// Returns an error if an input is not a valid prefix, splitting or
+/// aggregating fails, the interface addresses cannot be read, or the output
+/// cannot be written.
+pub fn run_calc(
+    output: &mut Output,
+    inputs: &[String],
+    split_len: Option<u8>,
+    aggregate: bool,
+) -> Result<()> {
+    let report = calculate(inputs, split_len, aggregate, &get_ip_interfaces()?)?;
+
+    if output.format == "json" {
+        writeln!(output, "{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+
+    for info in &report.prefixes {
+        let title = output.paint(Element::Title, &info.prefix.to_string());
+        writeln!(output, "{}", title)?;
+        let mut lines = vec![
+            ("Address", info.address.to_string()),
+            ("Network", info.network.to_string()),
+        ];
+        if let Some(broadcast) = info.broadcast {
+            lines.push(("Broadcast", broadcast.to_string()));
+        }
+        lines.extend([
+            ("Netmask", info.netmask.to_string()),
+            ("Wildcard", info.wildcard.to_string()),
+            ("Hosts", format!("{} - {}", info.first_host, info.last_host)),
+            ("Host count", info.host_count.to_string()),
+        ]);
+        let local = if info.local.is_empty() {
+            "no".to_string()
+        } else {
+            info.local
+                .iter()
+                .map(|local| {
+                    format!(
+                        "{} ({}/{})",
+                        local.iface,
+                        local.address,
+                        local.prefix.prefix_len()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        lines.push(("Local", local));
+        for (label, value) in lines {
+            let label = output.paint(Element::Header, &format!("{:11}", label));
+            writeln!(output, "  {} {}", label, value)?;
+        }
+        if !info.subnets.is_empty() {
+            let label = output.paint(Element::Header, "Subnets");
+            writeln!(output, "  {}", label)?;
+            for subnet in &info.subnets {
+                writeln!(output, "    {}", subnet)?;
+            }
+        }
+    }
+
+    if let Some(aggregated) = &report.aggregate {
+        let title = output.paint(Element::Title, "Aggregate");
+        writeln!(output, "{}", title)?;
+        for prefix in aggregated {
+            writeln!(output, "  {}", prefix)?;
+        }
+    }
+    if let Some(supernet) = report.supernet {
+        let title = output.paint(Element::Title, "Supernet");
+        writeln!(output, "{} {}", title, supernet)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(input: &str) -> PrefixInfo {
+        let (address, prefix) = parse_input(input).unwrap();
+        prefix_info(address, prefix, &[])
+    }
+
+    #[test]
+    fn test_ipv4_prefix_info() {
+        let info = info("192.168.1.77/26");
+        assert_eq!(info.address.to_string(), "192.168.1.77");
+        assert_eq!(info.prefix.to_string(), "192.168.1.64/26");
+        assert_eq!(info.broadcast.unwrap().to_string(), "192.168.1.127");
+        assert_eq!(info.netmask.to_string(), "255.255.255.192");
+        assert_eq!(info.wildcard.to_string(), "0.0.0.63");
+        assert_eq!(info.first_host.to_string(), "192.168.1.65");
+        assert_eq!(info.last_host.to_string(), "192.168.1.126");
+        assert_eq!(info.host_count, 62);
+
+        // Point-to-point links use both addresses
+        let info = self::info("10.0.0.0/31");
+        assert_eq!(info.broadcast, None);
+        assert_eq!(info.host_count, 2);
+        assert_eq!(self::info("10.0.0.1").host_count, 1);
+    }
+
+    #[test]
+    fn test_ipv6_prefix_info() {
+        let info = info("2001:db8::1/64");
+        assert_eq!(info.prefix.to_string(), "2001:db8::/64");
+        assert_eq!(info.broadcast, None);
+        assert_eq!(info.first_host.to_string(), "2001:db8::");
+        assert_eq!(info.last_host.to_string(), "2001:db8::ffff:ffff:ffff:ffff");
+        assert_eq!(info.host_count, 1 << 64);
+    }
+
+    #[test]
+    fn test_local_subnets() {
+        let interfaces = [InterfaceAddr {
+            name: "eth0".to_string(),
+            ip: "192.168.1.10".parse().unwrap(),
+            netmask: "255.255.255.0".parse().unwrap(),
+            prefix_len: 24,
+        }];
+        let report = calculate(
+            &["192.168.1.200".to_string(), "192.168.2.1".to_string()],
+            None,
+            false,
+            &interfaces,
+        )
+        .unwrap();
+        assert_eq!(
+            report.prefixes[0].local,
+            [LocalSubnet {
+                iface: "eth0".to_string(),
+                address: "192.168.1.10".parse().unwrap(),
+                prefix: "192.168.1.0/24".parse().unwrap(),
+            }]
+        );
+        assert!(report.prefixes[1].local.is_empty());
+    }
+
+    #[test]
+    fn test_split() {
+        let prefix: Prefix = "10.0.0.0/24".parse().unwrap();
+        assert_eq!(split(&prefix, 26).unwrap().len(), 4);
+        assert!(split(&prefix, 23).is_err());
+        assert!(split(&prefix, 33).is_err());
+        assert!(split(&"fd00::/48".parse().unwrap(), 128).is_err());
+
+        assert_eq!(parse_split("/26"), Ok(26));
+        assert_eq!(parse_split("64"), Ok(64));
+        assert!(parse_split("/129").is_err());
+    }
+
+    #[test]
+    fn test_aggregate_and_supernet() {
+        let inputs: Vec<String> = ["10.0.0.0/25", "10.0.0.128/25", "10.0.3.0/24"]
+            .iter()
+            .map(|input| input.to_string())
+            .collect();
+        let report = calculate(&inputs, None, true, &[]).unwrap();
+        assert_eq!(
+            report.aggregate.unwrap(),
+            [
+                "10.0.0.0/24".parse::<Prefix>().unwrap(),
+                "10.0.3.0/24".parse().unwrap()
+            ]
+        );
+        assert_eq!(report.supernet.unwrap().to_string(), "10.0.0.0/22");
+
+        let mixed = ["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()];
+        assert!(supernet(&mixed).is_err());
+        assert!(supernet(&[]).is_err());
+    }
+}
@@ -0,0 +1,661 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::neighbors::{Neighbor, get_neighbors};
+use crate::render::{Element, Output};
+use crate::route_table::RouteTable;
+use crate::table::Table;
+
+/// Pause between two echo requests to the same target.
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Payload of the echo requests.
+const PING_PAYLOAD: &[u8; 16] = b"ls_net gw check\0";
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// A gateway that routes point to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NextHop {
+    pub gateway: IpAddr,
+    pub iface: String,
+    /// The gateway of a default route, rather than only of more specific
+    /// routes.
+    pub default: bool,
+}
+
+/// Returns the distinct gateways of the routes of the given protocol
+/// ("ipv4", "ipv6", or "all"), default gateways first.
+pub fn next_hops(route_table: &RouteTable, protocol: &str) -> Vec<NextHop> {
+    let mut hops: Vec<NextHop> = Vec::new();
+    let mut routes = route_table.clone();
+    routes.retain_protocol(protocol);
+
+    for route in routes.ipv4_routes.iter().chain(&routes.ipv6_routes) {
+        // Strip a scope suffix, e.g. `fe80::1%en0`
+        let gateway = route.gateway.split('%').next().unwrap_or_default();
+        let Ok(gateway) = gateway.parse::<IpAddr>() else {
+            continue;
+        };
+        if gateway.is_unspecified() {
+            continue;
+        }
+        match hops
+            .iter_mut()
+            .find(|hop| hop.gateway == gateway && hop.iface == route.iface)
+        {
+            Some(hop) => hop.default |= route.is_default(),
+            None => hops.push(NextHop {
+                gateway,
+                iface: route.iface.clone(),
+                default: route.is_default(),
+            }),
+        }
+    }
+
+    hops.sort_by_key(|hop| !hop.default);
+    hops
+}
+
+/// Outcome of a series of ICMP echo requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PingStats {
+    pub sent: u32,
+    pub received: u32,
+    pub rtt_min_ms: Option<f64>,
+    pub rtt_avg_ms: Option<f64>,
+    pub rtt_max_ms: Option<f64>,
+}
+
+impl PingStats {
+    fn from_rtts(sent: u32, rtts: &[Duration]) -> Self {
+        let ms: Vec<f64> = rtts.iter().map(|rtt| rtt.as_secs_f64() * 1000.0).collect();
+        Self {
+            sent,
+            received: ms.len() as u32,
+            rtt_min_ms: ms.iter().copied().reduce(f64::min),
+            rtt_avg_ms: (!ms.is_empty()).then(|| ms.iter().sum::<f64>() / ms.len() as f64),
+            rtt_max_ms: ms.iter().copied().reduce(f64::max),
+        }
+    }
+
+    /// Returns the share of echo requests without a reply, in percent.
+    pub fn loss_percent(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        100.0 * f64::from(self.sent - self.received) / f64::from(self.sent)
+    }
+}
+
+/// Computes the Internet checksum (RFC 1071) of `data`.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Builds an ICMP or ICMPv6 echo request. The identifier is left 0, the
+/// kernel sets it for datagram ICMP sockets.
+fn echo_request(ipv6: bool, sequence: u16) -> Vec<u8> {
+    let kind = if ipv6 {
+        ICMPV6_ECHO_REQUEST
+    } else {
+        ICMP_ECHO_REQUEST
+    };
+    let mut packet = vec![kind, 0, 0, 0, 0, 0];
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(PING_PAYLOAD);
+    // The kernel computes the ICMPv6 checksum over the pseudo header
+    if !ipv6 {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// Returns the sequence number of an echo reply, or `None` for other
+/// messages. A leading IPv4 header, as delivered by BSD and macOS, is
+/// skipped.
+fn parse_echo_reply(packet: &[u8], ipv6: bool) -> Option<u16> {
+    let packet = if !ipv6 && packet.first().is_some_and(|byte| byte >> 4 == 4) {
+        packet.get(usize::from(packet[0] & 0x0f) * 4..)?
+    } else {
+        packet
+    };
+    let expected = if ipv6 {
+        ICMPV6_ECHO_REPLY
+    } else {
+        ICMP_ECHO_REPLY
+    };
+    if packet.len() < 8 || packet[0] != expected {
+        return None;
+    }
+    Some(u16::from_be_bytes([packet[6], packet[7]]))
+}
+
+/// Opens a datagram ICMP socket ("ping socket") connected to `target`,
+/// which needs no privileges.
+///
+/// # Errors
+///
+/// Returns an error if the socket cannot be created, e.g. because the group
+/// of the process is not in `net.ipv4.ping_group_range` on Linux.
+#[cfg(unix)]
+fn ping_socket(target: SocketAddr) -> Result<std::net::UdpSocket> {
+    use std::os::fd::FromRawFd;
+
+    let (domain, protocol) = match target {
+        SocketAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+        SocketAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+    };
+    // SAFETY: plain socket syscall without pointer arguments; the returned
+    // descriptor is checked and owned right below.
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM, protocol) };
+    if fd < 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() == std::io::ErrorKind::PermissionDenied {
+            return Err(anyhow!(
+                "ICMP sockets are not permitted, see sysctl net.ipv4.ping_group_range"
+            ));
+        }
+        return Err(error).context("Failed to open ICMP socket");
+    }
+    // A connected datagram ICMP socket behaves like a UDP socket.
+    // SAFETY: `fd` is a valid descriptor freshly returned by `socket` that
+    // nothing else owns or closes; the `UdpSocket` takes sole ownership and
+    // closes it when dropped.
+    let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+#[cfg(not(unix))]
+fn ping_socket(_target: SocketAddr) -> Result<std::net::UdpSocket> {
+    Err(anyhow!("ICMP probes are not supported on this platform"))
+}
+
+/// Sends `count` ICMP echo requests to `target` and waits up to `timeout`
+/// for each reply.
+///
+/// # Errors
+///
+/// Returns an error if no ICMP socket can be opened or sending fails.
+pub fn ping(target: SocketAddr, count: u32, timeout: Duration) -> Result<PingStats> {
+    let socket = ping_socket(target)?;
+    let ipv6 = target.is_ipv6();
+    let mut rtts = Vec::new();
+    let mut buf = [0u8; 1500];
+
+    for sequence in 0..count {
+        if sequence > 0 {
+            thread::sleep(PROBE_INTERVAL);
+        }
+        let sent = Instant::now();
+        socket.send(&echo_request(ipv6, sequence as u16))?;
+
+        while let Some(remaining) = timeout.checked_sub(sent.elapsed())
+            && !remaining.is_zero()
+        {
+            socket.set_read_timeout(Some(remaining))?;
+            match socket.recv(&mut buf) {
+                Ok(len) if parse_echo_reply(&buf[..len], ipv6) == Some(sequence as u16) => {
+                    rtts.push(sent.elapsed());
+                    break;
+                }
+                Ok(_) => continue,
+                // A timeout or an ICMP error such as "host unreachable"
+                Err(_) => break,
+            }
+        }
+    }
+
+    Ok(PingStats::from_rtts(count, &rtts))
+}
+
+/// Connects to `target` over TCP.
+///
+/// A refused connection still proves that the host answered, so it is
+/// reported with its round-trip time and `open` unset.
+pub fn tcp_connect(target: SocketAddr, timeout: Duration) -> TcpCheck {
+    let start = Instant::now();
+    let result = TcpStream::connect_timeout(&target, timeout);
+    let rtt_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+    match result {
+        Ok(_) => TcpCheck {
+            port: target.port(),
+            open: true,
+            rtt_ms,
+            error: None,
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => TcpCheck {
+            port: target.port(),
+            open: false,
+            rtt_ms,
+            error: None,
+        },
+        Err(e) => TcpCheck {
+            port: target.port(),
+            open: false,
+            rtt_ms: None,
+            error: Some(format!("TCP connect to {} failed: {}", target, e)),
+        },
+    }
+}
+
+/// Returns the socket address of `gateway`, with the scope of `iface` for
+/// IPv6 link-local gateways.
+fn target_addr(gateway: IpAddr, iface: &str, port: u16) -> SocketAddr {
+    match gateway {
+        IpAddr::V6(ip) if ip.is_unicast_link_local() => {
+            SocketAddr::V6(SocketAddrV6::new(ip, port, 0, interface_index(iface)))
+        }
+        ip => SocketAddr::new(ip, port),
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(iface: &str) -> u32 {
+    std::ffi::CString::new(iface)
+        // SAFETY: `name` is a NUL-terminated `CString` that outlives the
+        // call, which only reads it.
+        .map(|name| unsafe { libc::if_nametoindex(name.as_ptr()) })
+        .unwrap_or(0)
+}
+
+#[cfg(not(unix))]
+fn interface_index(_iface: &str) -> u32 {
+    0
+}
+
+/// The result of a TCP connect probe.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TcpCheck {
+    pub port: u16,
+    /// The connection was accepted; a refused connection leaves it unset.
+    pub open: bool,
+    /// The time until the connection was accepted or refused.
+    pub rtt_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// The reachability of a gateway.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GatewayCheck {
+    #[serde(flatten)]
+    pub hop: NextHop,
+    pub icmp: Option<PingStats>,
+    pub icmp_error: Option<String>,
+    /// The neighbor table entry of the gateway after probing it.
+    pub neighbor: Option<Neighbor>,
+    pub tcp: Option<TcpCheck>,
+}
+
+impl GatewayCheck {
+    /// Returns `true` if the gateway answered an echo request or a TCP
+    /// connection attempt.
+    pub fn answered(&self) -> bool {
+        self.icmp.as_ref().is_some_and(|icmp| icmp.received > 0)
+            || self.tcp.as_ref().is_some_and(|tcp| tcp.rtt_ms.is_some())
+    }
+
+    /// Returns why probing the gateway failed, such as
+    /// `ICMP: ICMP sockets are not permitted`, or `None` if it did not.
+    pub fn error(&self) -> Option<String> {
+        let errors: Vec<String> = [
+            ("ICMP", self.icmp_error.as_ref()),
+            ("TCP", self.tcp.as_ref().and_then(|tcp| tcp.error.as_ref())),
+        ]
+        .into_iter()
+        .filter_map(|(probe, error)| Some(format!("{}: {}", probe, error?)))
+        .collect();
+        (!errors.is_empty()).then(|| errors.join("; "))
+    }
+}
+
+/// Options of `check gw`.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Number of echo requests per gateway.
+    pub count: u32,
+    /// Time to wait for each reply or TCP handshake.
+    pub timeout: Duration,
+    /// Also try a TCP connection to this port.
+    pub tcp_port: Option<u16>,
+}
+
+/// Probes `hop` with ICMP and optionally TCP, then looks up its neighbor
+/// table entry.
+pub fn check_hop(hop: NextHop, options: &CheckOptions) -> GatewayCheck {
+    let (icmp, icmp_error) = match ping(
+        target_addr(hop.gateway, &hop.iface, 0),
+        options.count,
+        options.timeout,
+    ) {
+        Ok(stats) => (Some(stats), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let tcp = options
+        .tcp_port
+        .map(|port| tcp_connect(target_addr(hop.gateway, &hop.iface, port), options.timeout));
+    // Probing has triggered address resolution, so the entry is current
+    let neighbor = get_neighbors().ok().and_then(|neighbors| {
+        neighbors
+            .into_iter()
+            .find(|neighbor| neighbor.ip == hop.gateway && neighbor.iface == hop.iface)
+    });
+
+    GatewayCheck {
+        hop,
+        icmp,
+        icmp_error,
+        neighbor,
+        tcp,
+    }
+}
+
+fn format_ms(ms: Option<f64>) -> String {
+    ms.map(|ms| format!("{:.3}", ms))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn print_checks(
+    output: &mut Output,
+    checks: &[GatewayCheck],
+    options: &CheckOptions,
+) -> io::Result<()> {
+    let mut headers = vec![
+        "Gateway",
+        "Iface",
+        "Kind",
+        "Replies",
+        "Loss",
+        "RTT min/avg/max ms",
+        "Neighbor",
+    ];
+    if options.tcp_port.is_some() {
+        headers.push("TCP ms");
+    }
+    let errors = checks.iter().any(|check| check.error().is_some());
+    if errors {
+        headers.push("Error");
+    }
+    let mut table = Table::new(headers);
+    for check in checks {
+        let mut row = vec![
+            check.hop.gateway.to_string(),
+            check.hop.iface.clone(),
+            if check.hop.default {
+                "default".to_string()
+            } else {
+                "next hop".to_string()
+            },
+        ];
+        match &check.icmp {
+            Some(icmp) => row.extend([
+                format!("{}/{}", icmp.received, icmp.sent),
+                format!("{:.0}%", icmp.loss_percent()),
+                format!(
+                    "{}/{}/{}",
+                    format_ms(icmp.rtt_min_ms),
+                    format_ms(icmp.rtt_avg_ms),
+                    format_ms(icmp.rtt_max_ms)
+                ),
+            ]),
+            None => row.extend(["error", "-", "-"].map(String::from)),
+        }
+        row.push(match &check.neighbor {
+            Some(neighbor) => format!(
+                "{} {}",
+                neighbor.mac.as_deref().unwrap_or("-"),
+                neighbor.state
+            ),
+            None => "-".to_string(),
+        });
+        if let Some(tcp) = &check.tcp {
+            row.push(match (tcp.rtt_ms, tcp.open) {
+                (Some(_), true) => format_ms(tcp.rtt_ms),
+                (Some(_), false) => format!("{} refused", format_ms(tcp.rtt_ms)),
+                (None, _) => "failed".to_string(),
+            });
+        }
+        if errors {
+            row.push(check.error().unwrap_or_else(|| "-".to_string()));
+        }
+        table.push_row(row);
+    }
+
+    let title = output.paint(Element::Title, "Gateway Reachability");
+    writeln!(output, "{}", title)?;
+    output.table(&table)
+}
+
+/// Probes every default gateway and next hop in `route_table` of the given
+/// protocol ("ipv4", "ipv6", or "all") in parallel and writes the results
+/// to `output` as a table, or as JSON if its format is "json".
+///
+/// # Errors
+///
+/// Returns an error if there are no gateways, a default gateway did not
+/// answer, or the output cannot be written.
+pub fn check_gateways(
+    output: &mut Output,
+    route_table: &RouteTable,
+    protocol: &str,
+    options: &CheckOptions,
+) -> Result<()> {
+    let hops = next_hops(route_table, protocol);
+    if hops.is_empty() {
+        return Err(anyhow!("No gateways found"));
+    }
+
+    let checks: Vec<GatewayCheck> = thread::scope(|scope| {
+        let handles: Vec<_> = hops
+            .into_iter()
+            .map(|hop| scope.spawn(move || check_hop(hop, options)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("probe thread panicked"))
+            .collect()
+    });
+
+    if output.format == "json" {
+        writeln!(output, "{}", serde_json::to_string_pretty(&checks)?)?;
+    } else {
+        print_checks(output, &checks, options)?;
+    }
+
+    let silent: Vec<String> = checks
+        .iter()
+        .filter(|check| check.hop.default && !check.answered())
+        .map(|check| check.hop.gateway.to_string())
+        .collect();
+    if !silent.is_empty() {
+        return Err(anyhow!(
+            "Default gateway did not answer: {}",
+            silent.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Style;
+    use crate::route_table::{IpVersion, RouteEntry};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_next_hops() {
+        let mut route_table = RouteTable::new();
+        for (destination, gateway, iface) in [
+            ("10.0.0.0/8", "192.168.1.254", "eth0"),
+            ("192.168.1.0/24", "0.0.0.0", "eth0"),
+            ("default", "192.168.1.1", "eth0"),
+            ("172.16.0.0/12", "192.168.1.254", "eth0"),
+        ] {
+            route_table.add_route(RouteEntry {
+                destination: destination.to_string(),
+                gateway: gateway.to_string(),
+                iface: iface.to_string(),
+                ..Default::default()
+            });
+        }
+        route_table.add_route(RouteEntry {
+            destination: "default".to_string(),
+            gateway: "fe80::1%en0".to_string(),
+            iface: "en0".to_string(),
+            ip_version: IpVersion::IPv6,
+            ..Default::default()
+        });
+
+        let hops = next_hops(&route_table, "ipv4");
+        assert_eq!(
+            hops,
+            vec![
+                NextHop {
+                    gateway: "192.168.1.1".parse().unwrap(),
+                    iface: "eth0".to_string(),
+                    default: true,
+                },
+                NextHop {
+                    gateway: "192.168.1.254".parse().unwrap(),
+                    iface: "eth0".to_string(),
+                    default: false,
+                },
+            ]
+        );
+
+        let hops = next_hops(&route_table, "all");
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[1].gateway, "fe80::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_echo_packets() {
+        let request = echo_request(false, 7);
+        assert_eq!(request[0], ICMP_ECHO_REQUEST);
+        // A packet including its checksum sums up to zero
+        assert_eq!(checksum(&request), 0);
+        assert_eq!(echo_request(true, 7)[2..4], [0, 0]);
+
+        let mut reply = request.clone();
+        reply[0] = ICMP_ECHO_REPLY;
+        assert_eq!(parse_echo_reply(&reply, false), Some(7));
+        assert_eq!(parse_echo_reply(&request, false), None);
+
+        // BSD delivers the IPv4 header along with the message
+        let mut with_header = vec![0x45];
+        with_header.resize(20, 0);
+        with_header.extend(&reply);
+        assert_eq!(parse_echo_reply(&with_header, false), Some(7));
+
+        let mut reply = echo_request(true, 300);
+        reply[0] = ICMPV6_ECHO_REPLY;
+        assert_eq!(parse_echo_reply(&reply, true), Some(300));
+    }
+
+    #[test]
+    fn test_ping_stats() {
+        let stats = PingStats::from_rtts(4, &[Duration::from_millis(1), Duration::from_millis(3)]);
+        assert_eq!(stats.received, 2);
+        assert_eq!(stats.loss_percent(), 50.0);
+        assert_eq!(stats.rtt_min_ms, Some(1.0));
+        assert_eq!(stats.rtt_avg_ms, Some(2.0));
+        assert_eq!(stats.rtt_max_ms, Some(3.0));
+        assert_eq!(PingStats::from_rtts(3, &[]).rtt_avg_ms, None);
+    }
+
+    #[test]
+    fn test_print_checks_with_errors() {
+        let check = |gateway: &str, icmp_error: Option<&str>| GatewayCheck {
+            hop: NextHop {
+                gateway: gateway.parse().unwrap(),
+                iface: "eth0".to_string(),
+                default: true,
+            },
+            icmp: icmp_error
+                .is_none()
+                .then(|| PingStats::from_rtts(1, &[Duration::from_millis(2)])),
+            icmp_error: icmp_error.map(String::from),
+            neighbor: None,
+            tcp: Some(TcpCheck {
+                port: 443,
+                open: false,
+                rtt_ms: None,
+                error: Some("timed out".to_string()),
+            }),
+        };
+        let checks = [
+            check("192.168.1.1", Some("ICMP sockets are not permitted")),
+            check("192.168.1.2", None),
+        ];
+        let options = CheckOptions {
+            count: 1,
+            timeout: Duration::from_secs(1),
+            tcp_port: Some(443),
+        };
+
+        let mut buffer = Vec::new();
+        let style = Style {
+            color: false,
+            ..Style::default()
+        };
+        print_checks(
+            &mut Output::new(&mut buffer, "text", style),
+            &checks,
+            &options,
+        )
+        .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[1].ends_with("TCP ms  Error"));
+        assert!(lines[2].starts_with("192.168.1.1  eth0   default  error"));
+        assert!(lines[2].ends_with("failed  ICMP: ICMP sockets are not permitted; TCP: timed out"));
+        assert!(lines[3].ends_with("failed  TCP: timed out"));
+        assert_eq!(checks[1].error().unwrap(), "TCP: timed out");
+    }
+
+    #[test]
+    fn test_ping_loopback() {
+        let target = "127.0.0.1:0".parse().unwrap();
+        match ping(target, 2, Duration::from_secs(1)) {
+            Ok(stats) => {
+                assert_eq!(stats.sent, 2);
+                assert_eq!(stats.received, 2);
+            }
+            // Ping sockets can be disabled for the group of the test process
+            Err(e) => assert!(e.to_string().contains("ping_group_range"), "{}", e),
+        }
+    }
+
+    #[test]
+    fn test_tcp_connect_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let check = tcp_connect(open, Duration::from_secs(1));
+        assert!(check.open);
+        assert!(check.rtt_ms.is_some());
+
+        // A refused connection is an answer as well
+        drop(listener);
+        let check = tcp_connect(open, Duration::from_secs(1));
+        assert!(!check.open);
+        assert!(check.rtt_ms.is_some());
+        assert_eq!(check.error, None);
+    }
+}
@@ -0,0 +1,314 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::ip_interfaces::InterfaceAddr;
+use crate::render::{Element, Output};
+use crate::route_table::{IpVersion, RouteEntry};
+use crate::snapshot::Snapshot;
+
+/// A change of a single route between two snapshots.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum RouteChange {
+    Added {
+        route: RouteEntry,
+    },
+    Removed {
+        route: RouteEntry,
+    },
+    Changed {
+        old: Box<RouteEntry>,
+        new: Box<RouteEntry>,
+    },
+}
+
+/// A change of a single interface address between two snapshots.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum AddressChange {
+    Added { address: InterfaceAddr },
+    Removed { address: InterfaceAddr },
+}
+
+/// The differences between two snapshots.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotDiff {
+    pub main_ip: Option<(Option<String>, Option<String>)>,
+    pub routes: Vec<RouteChange>,
+    pub addresses: Vec<AddressChange>,
+}
+
+/// Identifies a route across snapshots: routes with the same destination in
+/// the same table are the same route.
+type RouteKey = (u8, String, String);
+
+fn route_key(route: &RouteEntry) -> RouteKey {
+    let family = match route.ip_version {
+        IpVersion::IPv4 => 4,
+        IpVersion::IPv6 => 6,
+    };
+    (
+        family,
+        route.table_name().to_string(),
+        route.destination.clone(),
+    )
+}
+
+/// Returns `true` if two routes for the same destination differ in a way
+/// that matters. The remaining lifetime (`expire`) and the usage counters
+/// (`refs`, `use`) change all the time and are ignored.
+fn routes_differ(old: &RouteEntry, new: &RouteEntry) -> bool {
+    let mut old = old.clone();
+    old.expire = new.expire.clone();
+    old.refs = new.refs;
+    old.use_count = new.use_count;
+    &old != new
+}
+
+impl SnapshotDiff {
+    /// Compares the snapshot `old` with the snapshot `new`.
+    ///
+    /// Routes are matched on their destination and table. If a destination
+    /// has a single route on both sides, a difference is reported as a
+    /// change; multipath routes are compared as sets and reported as
+    /// additions and removals.
+    pub fn between(old: &Snapshot, new: &Snapshot) -> Self {
+        let mut diff = SnapshotDiff::default();
+
+        if old.main_ip != new.main_ip {
+            diff.main_ip = Some((old.main_ip.clone(), new.main_ip.clone()));
+        }
+
+        let mut grouped: BTreeMap<RouteKey, (Vec<&RouteEntry>, Vec<&RouteEntry>)> = BTreeMap::new();
+        for route in old.routes.ipv4_routes.iter().chain(&old.routes.ipv6_routes) {
+            grouped.entry(route_key(route)).or_default().0.push(route);
+        }
+        for route in new.routes.ipv4_routes.iter().chain(&new.routes.ipv6_routes) {
+            grouped.entry(route_key(route)).or_default().1.push(route);
+        }
+
+        for (old_routes, new_routes) in grouped.values() {
+            if let ([old_route], [new_route]) = (old_routes.as_slice(), new_routes.as_slice()) {
+                if routes_differ(old_route, new_route) {
+                    diff.routes.push(RouteChange::Changed {
+                        old: Box::new((*old_route).clone()),
+                        new: Box::new((*new_route).clone()),
+                    });
+                }
+                continue;
+            }
+
+            for route in old_routes {
+                if !new_routes.iter().any(|new| !routes_differ(route, new)) {
+                    diff.routes.push(RouteChange::Removed {
+                        route: (*route).clone(),
+                    });
+                }
+            }
+            for route in new_routes {
+                if !old_routes.iter().any(|old| !routes_differ(old, route)) {
+                    diff.routes.push(RouteChange::Added {
+                        route: (*route).clone(),
+                    });
+                }
+            }
+        }
+
+        for address in &old.interfaces {
+            if !new.interfaces.contains(address) {
+                diff.addresses.push(AddressChange::Removed {
+                    address: address.clone(),
+                });
+            }
+        }
+        for address in &new.interfaces {
+            if !old.interfaces.contains(address) {
+                diff.addresses.push(AddressChange::Added {
+                    address: address.clone(),
+                });
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.main_ip.is_none() && self.routes.is_empty() && self.addresses.is_empty()
+    }
+
+    /// Writes the differences to `output` with `+`/`-` markers, styled as
+    /// added and removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output cannot be written.
+    pub fn display(&self, output: &mut Output) -> io::Result<()> {
+        let separator = output.paint(
+            Element::Separator,
+            "================ Route Changes ==============",
+        );
+        writeln!(output, "{}", separator)?;
+        for change in &self.routes {
+            match change {
+                RouteChange::Added { route } => {
+                    let line = output.paint(Element::Added, &format!("+ {}", route.describe()));
+                    writeln!(output, "{}", line)?;
+                }
+                RouteChange::Removed { route } => {
+                    let line = output.paint(Element::Removed, &format!("- {}", route.describe()));
+                    writeln!(output, "{}", line)?;
+                }
+                RouteChange::Changed { old, new } => {
+                    let old = output.paint(Element::Removed, &format!("- {}", old.describe()));
+                    let new = output.paint(Element::Added, &format!("+ {}", new.describe()));
+                    writeln!(output, "{}\n{}", old, new)?;
+                }
+            }
+        }
+
+        let separator = output.paint(
+            Element::Separator,
+            "=============== Address Changes =============",
+        );
+        writeln!(output, "{}", separator)?;
+        for change in &self.addresses {
+            let line = match change {
+                AddressChange::Added { address } => output.paint(
+                    Element::Added,
+                    &format!("+ {}: {}/{}", address.name, address.ip, address.prefix_len),
+                ),
+                AddressChange::Removed { address } => output.paint(
+                    Element::Removed,
+                    &format!("- {}: {}/{}", address.name, address.ip, address.prefix_len),
+                ),
+            };
+            writeln!(output, "{}", line)?;
+        }
+
+        if let Some((old, new)) = &self.main_ip {
+            let label = output.paint(Element::Header, "Main IP address changed:");
+            let old = output.paint(Element::Removed, old.as_deref().unwrap_or("none"));
+            let new = output.paint(Element::Added, new.as_deref().unwrap_or("none"));
+            writeln!(output, "{} {} -> {}", label, old, new)?;
+        }
+
+        let count = |kind: fn(&RouteChange) -> bool| self.routes.iter().filter(|c| kind(c)).count();
+        writeln!(
+            output,
+            "{} routes added, {} removed, {} changed; {} addresses changed",
+            count(|c| matches!(c, RouteChange::Added { .. })),
+            count(|c| matches!(c, RouteChange::Removed { .. })),
+            count(|c| matches!(c, RouteChange::Changed { .. })),
+            self.addresses.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::RouteTable;
+
+    fn route(destination: &str, gateway: &str, iface: &str) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            flags: "UG".to_string(),
+            iface: iface.to_string(),
+            table: Some("main".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn address(name: &str, ip: &str, prefix_len: u8) -> InterfaceAddr {
+        let ip = ip.parse().unwrap();
+        InterfaceAddr {
+            name: name.to_string(),
+            ip,
+            netmask: ip,
+            prefix_len,
+        }
+    }
+
+    fn snapshot(routes: Vec<RouteEntry>, interfaces: Vec<InterfaceAddr>) -> Snapshot {
+        let mut table = RouteTable::new();
+        for route in routes {
+            table.add_route(route);
+        }
+        Snapshot {
+            main_ip: Some("192.168.1.10".to_string()),
+            interfaces,
+            routes: table,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_after_vpn_connects() {
+        let before = snapshot(
+            vec![
+                route("default", "192.168.1.1", "eth0"),
+                route("192.168.1.0/24", "0.0.0.0", "eth0"),
+            ],
+            vec![address("eth0", "192.168.1.10", 24)],
+        );
+        let mut after = snapshot(
+            vec![
+                route("default", "10.8.0.1", "wg0"),
+                route("192.168.1.0/24", "0.0.0.0", "eth0"),
+                route("10.8.0.0/24", "0.0.0.0", "wg0"),
+            ],
+            vec![
+                address("eth0", "192.168.1.10", 24),
+                address("wg0", "10.8.0.2", 24),
+            ],
+        );
+        after.main_ip = Some("10.8.0.2".to_string());
+
+        let diff = SnapshotDiff::between(&before, &after);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.routes.len(), 2);
+        assert!(diff.routes.iter().any(|change| matches!(
+            change,
+            RouteChange::Changed { old, new } if old.iface == "eth0" && new.iface == "wg0"
+        )));
+        assert!(diff.routes.iter().any(|change| matches!(
+            change,
+            RouteChange::Added { route } if route.destination == "10.8.0.0/24"
+        )));
+        assert!(matches!(
+            &diff.addresses[..],
+            [AddressChange::Added { address }] if address.name == "wg0"
+        ));
+        assert_eq!(
+            diff.main_ip,
+            Some((
+                Some("192.168.1.10".to_string()),
+                Some("10.8.0.2".to_string())
+            ))
+        );
+
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(json["addresses"][0]["change"], "added");
+    }
+
+    #[test]
+    fn test_diff_ignores_expire_and_matches_multipath() {
+        let mut old_route = route("10.0.0.0/8", "10.0.0.1", "eth0");
+        old_route.expire = Some("100".to_string());
+        let mut new_route = old_route.clone();
+        new_route.expire = Some("40".to_string());
+        let second_hop = route("10.0.0.0/8", "10.0.1.1", "eth1");
+
+        let before = snapshot(vec![old_route], vec![]);
+        let after = snapshot(vec![new_route, second_hop], vec![]);
+
+        let diff = SnapshotDiff::between(&before, &after);
+        assert!(matches!(
+            &diff.routes[..],
+            [RouteChange::Added { route }] if route.gateway == "10.0.1.1"
+        ));
+        assert!(SnapshotDiff::between(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+pub mod rules;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use crate::links::{Link, get_links};
+use crate::render::{Element, Output};
+use crate::route_table::route_table::RouteCollector;
+use crate::snapshot::Snapshot;
+
+/// The resolver configuration listing the DNS servers.
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// Time to wait for a DNS server to answer.
+const DNS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Returns the label of the severity styled for `output`, padded to a
+    /// common width.
+    fn label(&self, output: &Output) -> String {
+        match self {
+            Severity::Info => output.paint(Element::Header, &format!("{:7}", "INFO")),
+            Severity::Warning => output.paint(Element::Warning, "WARNING"),
+            Severity::Error => output.paint(Element::Error, &format!("{:7}", "ERROR")),
+        }
+    }
+}
+
+/// A problem found by a rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A DNS server of the resolver configuration.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DnsServer {
+    pub ip: IpAddr,
+    /// Whether the server answered a query, if it was probed.
+    pub reachable: Option<bool>,
+}
+
+/// Everything the rules look at.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub snapshot: Snapshot,
+    pub links: Vec<Link>,
+    pub dns_servers: Vec<DnsServer>,
+}
+
+impl State {
+    /// Collects the addresses, routes (from `routes`) and links of the host
+    /// and probes the DNS servers of `/etc/resolv.conf`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the addresses, routes or links cannot be
+    /// collected.
+    pub fn collect(routes: &RouteCollector) -> Result<Self> {
+        let servers = fs::read_to_string(RESOLV_CONF)
+            .map(|content| parse_resolv_conf(&content))
+            .unwrap_or_default();
+        let dns_servers = thread::scope(|scope| {
+            let handles: Vec<_> = servers
+                .into_iter()
+                .map(|ip| {
+                    scope.spawn(move || (ip, probe_dns(SocketAddr::new(ip, 53), DNS_TIMEOUT)))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .map(|(ip, reachable)| DnsServer {
+                    ip,
+                    reachable: Some(reachable),
+                })
+                .collect()
+        });
+
+        Ok(Self {
+            snapshot: Snapshot::collect(routes)?,
+            links: get_links()?,
+            dns_servers,
+        })
+    }
+}
+
+/// Returns the `nameserver` addresses of a `resolv.conf` file. Scope
+/// suffixes of IPv6 addresses (`fe80::1%eth0`) are dropped.
+pub fn parse_resolv_conf(content: &str) -> Vec<IpAddr> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next()? != "nameserver" {
+                return None;
+            }
+            parts.next()?.split('%').next()?.parse().ok()
+        })
+        .collect()
+}
+
+/// Builds a DNS query for the NS records of the root zone, which every
+/// resolver can answer.
+fn dns_query(id: u16) -> Vec<u8> {
+    let mut query = id.to_be_bytes().to_vec();
+    // Recursion desired, one question
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    // Root name, type NS, class IN
+    query.extend_from_slice(&[0, 0, 2, 0, 1]);
+    query
+}
+
+/// Returns `true` if the DNS server at `server` answers a query over UDP
+/// within `timeout`. Any answer counts, including errors such as `REFUSED`.
+pub fn probe_dns(server: SocketAddr, timeout: Duration) -> bool {
+    let bind: SocketAddr = match server.ip() {
+        IpAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        IpAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    let query = dns_query(std::process::id() as u16);
+    let probe = || -> std::io::Result<bool> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_read_timeout(Some(timeout))?;
+        socket.connect(server)?;
+        socket.send(&query)?;
+        let mut buf = [0u8; 512];
+        let len = socket.recv(&mut buf)?;
+        Ok(len >= 2 && buf[..2] == query[..2])
+    };
+    probe().unwrap_or(false)
+}
+
+/// Runs every rule over `state` and returns the findings, the most severe
+/// first.
+pub fn diagnose(state: &State) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = rules::RULES
+        .iter()
+        .flat_map(|rule| {
+            (rule.check)(state)
+                .into_iter()
+                .map(|(severity, message)| Finding {
+                    rule: rule.name,
+                    severity,
+                    message,
+                })
+        })
+        .collect();
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    findings
+}
+
+/// Collects the network state, with the route table from `routes`, runs all
+/// rules and writes the findings to `output` as text, or as JSON if its
+/// format is "json".
+///
+/// # Errors
+///
+/// Returns an error if the network state cannot be collected, the output
+/// cannot be written, or a finding has error severity.
+pub fn run_doctor(output: &mut Output, routes: &RouteCollector) -> Result<()> {
+    let findings = diagnose(&State::collect(routes)?);
+
+    if output.format == "json" {
+        writeln!(output, "{}", serde_json::to_string_pretty(&findings)?)?;
+    } else {
+        let title = output.paint(Element::Title, "Network Doctor");
+        writeln!(output, "{}", title)?;
+        let width = findings
+            .iter()
+            .map(|finding| finding.rule.len())
+            .max()
+            .unwrap_or(0);
+        for finding in &findings {
+            let label = finding.severity.label(output);
+            writeln!(
+                output,
+                "{} {:width$}  {}",
+                label,
+                finding.rule,
+                finding.message,
+                width = width
+            )?;
+        }
+        if findings.is_empty() {
+            let none = output.paint(Element::Added, "No problems found");
+            writeln!(output, "{}", none)?;
+        }
+        writeln!(output, "{} rules checked", rules::RULES.len())?;
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!("Found {} errors", errors));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let content = "\
+# Generated by NetworkManager
+search example.com
+nameserver 192.168.1.1
+nameserver fe80::1%eth0
+nameserver not-an-ip
+options edns0
+";
+        assert_eq!(
+            parse_resolv_conf(content),
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_probe_dns_over_loopback() {
+        // A fake server echoing the query back counts as an answer
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            if let Ok((len, peer)) = server.recv_from(&mut buf) {
+                server.send_to(&buf[..len], peer).unwrap();
+            }
+        });
+        assert!(probe_dns(addr, Duration::from_secs(1)));
+
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = silent.local_addr().unwrap();
+        assert!(!probe_dns(addr, Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_diagnose_sorts_by_severity() {
+        let findings = diagnose(&State::default());
+        assert!(!findings.is_empty());
+        assert!(
+            findings
+                .windows(2)
+                .all(|pair| pair[0].severity >= pair[1].severity)
+        );
+        assert_eq!(findings[0].rule, "no-default-route");
+    }
+}
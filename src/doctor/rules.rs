@@ -0,0 +1,521 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use super::{Severity, State};
+use crate::links::Link;
+use crate::route_table::analysis::{
+    connected_subnets, is_local_only, overlapping_subnets as overlapping_subnets_of,
+};
+use crate::route_table::{IpVersion, RouteEntry};
+
+/// What a rule reports: the severity and a message per problem found.
+pub type Findings = Vec<(Severity, String)>;
+
+/// An independent diagnostic check over the collected state.
+pub struct Rule {
+    pub name: &'static str,
+    pub check: fn(&State) -> Findings,
+}
+
+/// All rules `doctor` runs, in the order they are reported within a
+/// severity.
+pub const RULES: [Rule; 9] = [
+    Rule {
+        name: "no-default-route",
+        check: no_default_route,
+    },
+    Rule {
+        name: "equal-metric-defaults",
+        check: equal_metric_defaults,
+    },
+    Rule {
+        name: "default-via-down-link",
+        check: default_via_down_link,
+    },
+    Rule {
+        name: "no-carrier",
+        check: no_carrier,
+    },
+    Rule {
+        name: "duplicate-address",
+        check: duplicate_address,
+    },
+    Rule {
+        name: "overlapping-subnets",
+        check: overlapping_subnets,
+    },
+    Rule {
+        name: "missing-ipv6-default",
+        check: missing_ipv6_default,
+    },
+    Rule {
+        name: "unreachable-dns",
+        check: unreachable_dns,
+    },
+    Rule {
+        name: "bridge-mtu-mismatch",
+        check: bridge_mtu_mismatch,
+    },
+];
+
+fn family(ip_version: &IpVersion) -> &'static str {
+    match ip_version {
+        IpVersion::IPv4 => "IPv4",
+        IpVersion::IPv6 => "IPv6",
+    }
+}
+
+/// Returns the default routes that forward traffic, leaving out reject
+/// routes such as `unreachable default` and interface-scoped macOS routes.
+fn default_routes(state: &State) -> impl Iterator<Item = &RouteEntry> {
+    let routes = &state.snapshot.routes;
+    routes
+        .ipv4_routes
+        .iter()
+        .chain(&routes.ipv6_routes)
+        .filter(|route| {
+            route.is_default()
+                && route
+                    .route_type
+                    .as_deref()
+                    .is_none_or(|route_type| route_type == "unicast")
+                && !route.flags.contains('I')
+        })
+}
+
+fn link<'a>(state: &'a State, name: &str) -> Option<&'a Link> {
+    state.links.iter().find(|link| link.name == name)
+}
+
+fn no_default_route(state: &State) -> Findings {
+    if default_routes(state).next().is_some() {
+        return Vec::new();
+    }
+    vec![(
+        Severity::Error,
+        "No default route, only directly connected networks are reachable".to_string(),
+    )]
+}
+
+fn equal_metric_defaults(state: &State) -> Findings {
+    let mut groups: BTreeMap<(&str, &str, u32), Vec<&RouteEntry>> = BTreeMap::new();
+    for route in default_routes(state) {
+        groups
+            .entry((
+                family(&route.ip_version),
+                route.table_name(),
+                route.metric.unwrap_or(0),
+            ))
+            .or_default()
+            .push(route);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, routes)| routes.len() > 1)
+        .map(|((family, table, metric), routes)| {
+            let via: Vec<String> = routes
+                .iter()
+                .map(|route| format!("{} on {}", route.gateway, route.iface))
+                .collect();
+            (
+                Severity::Warning,
+                format!(
+                    "{} {} default routes in table {} share metric {} ({}), unless this is \
+                     intended multipath the kernel picks one arbitrarily",
+                    routes.len(),
+                    family,
+                    table,
+                    metric,
+                    via.join(", ")
+                ),
+            )
+        })
+        .collect()
+}
+
+fn default_via_down_link(state: &State) -> Findings {
+    default_routes(state)
+        .filter_map(|route| {
+            let link = link(state, &route.iface)?;
+            let problem = if !link.up {
+                "is down"
+            } else if link.carrier == Some(false) {
+                "has no carrier"
+            } else {
+                return None;
+            };
+            Some((
+                Severity::Error,
+                format!(
+                    "{} default route via {} uses {}, which {}",
+                    family(&route.ip_version),
+                    route.gateway,
+                    route.iface,
+                    problem
+                ),
+            ))
+        })
+        .collect()
+}
+
+fn no_carrier(state: &State) -> Findings {
+    let routes = &state.snapshot.routes;
+    state
+        .links
+        .iter()
+        .filter(|link| link.up && link.carrier == Some(false))
+        .map(|link| {
+            // Every address adds routes to the local table, only others matter
+            let routed = routes
+                .ipv4_routes
+                .iter()
+                .chain(&routes.ipv6_routes)
+                .any(|route| route.iface == link.name && route.table_name() != "local");
+            if routed {
+                (
+                    Severity::Warning,
+                    format!("{} is up without a carrier, but routes use it", link.name),
+                )
+            } else {
+                (
+                    Severity::Info,
+                    format!("{} is up without a carrier", link.name),
+                )
+            }
+        })
+        .collect()
+}
+
+fn duplicate_address(state: &State) -> Findings {
+    let mut owners: BTreeMap<IpAddr, Vec<&str>> = BTreeMap::new();
+    for interface in &state.snapshot.interfaces {
+        if is_local_only(&interface.ip) {
+            continue;
+        }
+        let names = owners.entry(interface.ip).or_default();
+        if !names.contains(&interface.name.as_str()) {
+            names.push(&interface.name);
+        }
+    }
+
+    owners
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(ip, names)| {
+            (
+                Severity::Error,
+                format!("{} is assigned to {}", ip, names.join(", ")),
+            )
+        })
+        .collect()
+}
+
+fn overlapping_subnets(state: &State) -> Findings {
+    let subnets = connected_subnets(&state.snapshot.interfaces);
+    overlapping_subnets_of(&subnets)
+        .into_iter()
+        .map(|(subnet, other)| (Severity::Warning, format!("{} overlaps {}", subnet, other)))
+        .collect()
+}
+
+fn missing_ipv6_default(state: &State) -> Findings {
+    let has_default = default_routes(state).any(|route| route.ip_version == IpVersion::IPv6);
+    // Global unicast addresses are allocated from 2000::/3
+    let global = state.snapshot.interfaces.iter().find(
+        |interface| matches!(interface.ip, IpAddr::V6(ip) if ip.segments()[0] & 0xe000 == 0x2000),
+    );
+
+    match global {
+        Some(interface) if !has_default => vec![(
+            Severity::Warning,
+            format!(
+                "{} has the global IPv6 address {}, but there is no IPv6 default route",
+                interface.name, interface.ip
+            ),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+fn unreachable_dns(state: &State) -> Findings {
+    state
+        .dns_servers
+        .iter()
+        .filter(|server| server.reachable == Some(false))
+        .map(|server| {
+            (
+                Severity::Error,
+                format!("DNS server {} does not answer", server.ip),
+            )
+        })
+        .collect()
+}
+
+fn bridge_mtu_mismatch(state: &State) -> Findings {
+    let mut members: BTreeMap<&str, Vec<&Link>> = BTreeMap::new();
+    for link in &state.links {
+        if let Some(master) = &link.master {
+            members.entry(master).or_default().push(link);
+        }
+    }
+
+    members
+        .into_iter()
+        .filter_map(|(master, links)| {
+            let mut mtus: Vec<u32> = links.iter().filter_map(|link| link.mtu).collect();
+            mtus.sort_unstable();
+            mtus.dedup();
+            if mtus.len() < 2 {
+                return None;
+            }
+            let list: Vec<String> = links
+                .iter()
+                .filter_map(|link| Some(format!("{} {}", link.name, link.mtu?)))
+                .collect();
+            Some((
+                Severity::Warning,
+                format!(
+                    "Members of {} have different MTUs: {}",
+                    master,
+                    list.join(", ")
+                ),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::DnsServer;
+    use crate::ip_interfaces::InterfaceAddr;
+    use crate::prefix::Prefix;
+
+    fn route(destination: &str, gateway: &str, iface: &str, metric: u32) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            iface: iface.to_string(),
+            ip_version: if gateway.contains(':') {
+                IpVersion::IPv6
+            } else {
+                IpVersion::IPv4
+            },
+            metric: Some(metric),
+            table: Some("main".to_string()),
+            route_type: Some("unicast".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn address(name: &str, ip: &str, prefix_len: u8) -> InterfaceAddr {
+        let ip: IpAddr = ip.parse().unwrap();
+        InterfaceAddr {
+            name: name.to_string(),
+            ip,
+            netmask: Prefix::new(ip, prefix_len).netmask(),
+            prefix_len,
+        }
+    }
+
+    fn link(name: &str, up: bool, carrier: bool, mtu: u32, master: Option<&str>) -> Link {
+        Link {
+            name: name.to_string(),
+            up,
+            carrier: Some(carrier),
+            mtu: Some(mtu),
+            master: master.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// A healthy host: one uplink with a default route for each family.
+    fn healthy() -> State {
+        let mut state = State::default();
+        state
+            .snapshot
+            .routes
+            .add_route(route("default", "192.168.1.1", "eth0", 100));
+        state
+            .snapshot
+            .routes
+            .add_route(route("default", "fe80::1", "eth0", 1024));
+        state.snapshot.interfaces = vec![
+            address("eth0", "192.168.1.10", 24),
+            address("eth0", "2001:db8::10", 64),
+            address("eth0", "fe80::10", 64),
+            address("lo", "127.0.0.1", 8),
+        ];
+        state.links = vec![link("eth0", true, true, 1500, None)];
+        state.dns_servers = vec![DnsServer {
+            ip: "192.168.1.1".parse().unwrap(),
+            reachable: Some(true),
+        }];
+        state
+    }
+
+    fn run(check: fn(&State) -> Findings, state: &State) -> Vec<String> {
+        check(state)
+            .into_iter()
+            .map(|(severity, message)| format!("{:?}: {}", severity, message))
+            .collect()
+    }
+
+    #[test]
+    fn test_healthy_state_has_no_findings() {
+        let state = healthy();
+        for rule in &RULES {
+            assert_eq!(
+                run(rule.check, &state),
+                Vec::<String>::new(),
+                "{}",
+                rule.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_default_route() {
+        let mut state = healthy();
+        state.snapshot.routes = Default::default();
+        state.snapshot.routes.add_route(RouteEntry {
+            route_type: Some("unreachable".to_string()),
+            ..route("default", "::", "lo", 0)
+        });
+        assert_eq!(
+            run(no_default_route, &state),
+            ["Error: No default route, only directly connected networks are reachable"]
+        );
+    }
+
+    #[test]
+    fn test_equal_metric_defaults() {
+        let mut state = healthy();
+        state
+            .snapshot
+            .routes
+            .add_route(route("default", "10.0.0.1", "wlan0", 100));
+        state
+            .snapshot
+            .routes
+            .add_route(route("default", "172.16.0.1", "eth1", 200));
+        let findings = run(equal_metric_defaults, &state);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].starts_with(
+            "Warning: 2 IPv4 default routes in table main share metric 100 \
+             (192.168.1.1 on eth0, 10.0.0.1 on wlan0)"
+        ));
+    }
+
+    #[test]
+    fn test_default_via_down_link() {
+        let mut state = healthy();
+        state.links[0].carrier = Some(false);
+        assert_eq!(
+            run(default_via_down_link, &state),
+            [
+                "Error: IPv4 default route via 192.168.1.1 uses eth0, which has no carrier",
+                "Error: IPv6 default route via fe80::1 uses eth0, which has no carrier"
+            ]
+        );
+        state.links[0].up = false;
+        assert!(run(default_via_down_link, &state)[0].ends_with("which is down"));
+    }
+
+    #[test]
+    fn test_no_carrier() {
+        let mut state = healthy();
+        state.links[0].carrier = Some(false);
+        state.links.push(link("eth1", true, false, 1500, None));
+        state.links.push(link("eth2", false, false, 1500, None));
+        assert_eq!(
+            run(no_carrier, &state),
+            [
+                "Warning: eth0 is up without a carrier, but routes use it",
+                "Info: eth1 is up without a carrier"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_address() {
+        let mut state = healthy();
+        state.snapshot.interfaces.extend([
+            address("wlan0", "192.168.1.10", 24),
+            address("wlan0", "fe80::10", 64),
+            address("lo0", "127.0.0.1", 8),
+        ]);
+        assert_eq!(
+            run(duplicate_address, &state),
+            ["Error: 192.168.1.10 is assigned to eth0, wlan0"]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_subnets() {
+        let mut state = healthy();
+        state.snapshot.interfaces.extend([
+            address("wg0", "192.168.0.1", 16),
+            address("wg0", "192.168.200.1", 24),
+            address("lo", "192.168.1.53", 32),
+        ]);
+        assert_eq!(
+            run(overlapping_subnets, &state),
+            ["Warning: 192.168.1.0/24 on eth0 overlaps 192.168.0.0/16 on wg0"]
+        );
+    }
+
+    #[test]
+    fn test_missing_ipv6_default() {
+        let mut state = healthy();
+        state.snapshot.routes.ipv6_routes.clear();
+        assert_eq!(
+            run(missing_ipv6_default, &state),
+            [
+                "Warning: eth0 has the global IPv6 address 2001:db8::10, but there is no IPv6 \
+                 default route"
+            ]
+        );
+
+        // Unique local and link-local addresses need no default route
+        state.snapshot.interfaces = vec![
+            address("eth0", "fd00::10", 64),
+            address("eth0", "fe80::10", 64),
+        ];
+        assert!(run(missing_ipv6_default, &state).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_dns() {
+        let mut state = healthy();
+        state.dns_servers.push(DnsServer {
+            ip: "8.8.8.8".parse().unwrap(),
+            reachable: Some(false),
+        });
+        state.dns_servers.push(DnsServer {
+            ip: "1.1.1.1".parse().unwrap(),
+            reachable: None,
+        });
+        assert_eq!(
+            run(unreachable_dns, &state),
+            ["Error: DNS server 8.8.8.8 does not answer"]
+        );
+    }
+
+    #[test]
+    fn test_bridge_mtu_mismatch() {
+        let mut state = healthy();
+        state.links.extend([
+            link("br0", true, true, 1500, None),
+            link("eth1", true, true, 1500, Some("br0")),
+            link("eth2", true, true, 9000, Some("br0")),
+            link("eth3", true, true, 9000, Some("br1")),
+            link("eth4", true, true, 9000, Some("br1")),
+        ]);
+        assert_eq!(
+            run(bridge_mtu_mismatch, &state),
+            ["Warning: Members of br0 have different MTUs: eth1 1500, eth2 9000"]
+        );
+    }
+}
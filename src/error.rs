@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// The kind of a failure, which decides the exit code of ls_net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// The system has no network interfaces.
+    NoInterfaces,
+    /// No route source could read the route table.
+    RouteSourceUnavailable,
+    /// Reading from the kernel or running a command was not permitted.
+    PermissionDenied,
+    /// Part of the output could not be collected, with `--fail-on-partial`.
+    PartialData,
+    /// Any other failure.
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns the exit code of ls_net for a failure of this kind. Usage
+    /// errors exit with 2, as reported by clap.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::NoInterfaces => 3,
+            ErrorKind::RouteSourceUnavailable => 4,
+            ErrorKind::PermissionDenied => 5,
+            ErrorKind::PartialData => 6,
+        }
+    }
+
+    /// Returns the kind of `error`: that of an `Error` in its chain of
+    /// causes, `PermissionDenied` for an `io::Error` of that kind, and
+    /// `Other` for anything else.
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<Error>() {
+                return error.kind;
+            }
+            if let Some(error) = cause.downcast_ref::<io::Error>()
+                && error.kind() == io::ErrorKind::PermissionDenied
+            {
+                return ErrorKind::PermissionDenied;
+            }
+        }
+        ErrorKind::Other
+    }
+}
+
+/// An error of a given kind, carried inside `anyhow::Error` so that `main`
+/// can choose the exit code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The error has been shown to the user already, so `main` only exits
+    /// with its code.
+    pub reported: bool,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            reported: false,
+        }
+    }
+
+    /// Returns the error marked as shown to the user already.
+    pub fn reported(self) -> Self {
+        Self {
+            reported: true,
+            ..self
+        }
+    }
+
+    /// Returns whether `error` has an `Error` in its chain of causes that
+    /// has been shown to the user already.
+    pub fn is_reported(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Error>())
+            .is_some_and(|error| error.reported)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An error as reported in the `errors` array of JSON output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    /// The message, followed by its causes.
+    pub message: String,
+}
+
+impl From<&anyhow::Error> for ErrorReport {
+    fn from(error: &anyhow::Error) -> Self {
+        Self {
+            kind: ErrorKind::of(error),
+            message: format!("{:#}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kind() {
+        let error = anyhow::Error::new(Error::new(
+            ErrorKind::NoInterfaces,
+            "No network interfaces found.",
+        ));
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NoInterfaces);
+        let error = error.context("Failed to collect the snapshot");
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NoInterfaces);
+        assert_eq!(ErrorKind::of(&error).exit_code(), 3);
+
+        let denied: anyhow::Result<()> =
+            Err(io::Error::from(io::ErrorKind::PermissionDenied)).context("Failed to open netlink");
+        let error = denied.unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::PermissionDenied);
+
+        let error = anyhow::anyhow!("No route to 10.0.0.1");
+        assert_eq!(ErrorKind::of(&error).exit_code(), 1);
+        assert!(!Error::is_reported(&error));
+        let error = anyhow::Error::new(Error::new(ErrorKind::PartialData, "Incomplete").reported());
+        assert!(Error::is_reported(&error));
+
+        let report = ErrorReport::from(&anyhow::Error::new(Error::new(
+            ErrorKind::RouteSourceUnavailable,
+            "No route source could read the route table",
+        )));
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"kind":"route-source-unavailable","message":"No route source could read the route table"}"#
+        );
+    }
+}
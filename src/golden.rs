@@ -0,0 +1,62 @@
+//! Golden-file assertions for tests: output is compared with a file below
+//! `tests/golden`. Run the tests with `UPDATE_GOLDEN=1` to write the files
+//! from the current output after an intended change, and review the diff.
+
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// Returns the content of the fixture `name` below `tests/fixtures`, output
+/// recorded on a real system.
+pub fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing fixture {}", path.display()))
+}
+
+/// Asserts that `actual` equals the golden file `name`.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing golden file {}; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "output differs from golden file {}; run with UPDATE_GOLDEN=1 to update it\n\
+         --- expected\n{}\n--- actual\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+/// Asserts that `value` serialized as pretty-printed JSON equals the golden
+/// file `name`.
+pub fn assert_golden_json(name: &str, value: &impl Serialize) {
+    assert_golden(name, &(serde_json::to_string_pretty(value).unwrap() + "\n"));
+}
+
+/// Removes the ANSI color escape codes from `text`.
+pub fn strip_colors(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
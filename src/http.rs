@@ -0,0 +1,330 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Time a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest request line read, in bytes.
+const MAX_LINE_LENGTH: u64 = 8192;
+
+/// Most bytes of a request head read, including the headers.
+const MAX_HEAD_LENGTH: u64 = 64 * 1024;
+
+/// A request handler, shared by all connection threads.
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// The parts of an HTTP request the handlers look at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Parses an HTTP request line such as `GET /routes?family=ipv6 HTTP/1.1`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?;
+        parts
+            .next()
+            .filter(|version| version.starts_with("HTTP/"))?;
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(name), percent_decode(value))
+            })
+            .collect();
+
+        Some(Self {
+            method,
+            path: percent_decode(path),
+            query,
+        })
+    }
+
+    /// Returns the value of the first query parameter with the given name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP response with a complete body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body)
+    }
+
+    /// Creates a JSON response; serialization errors become a 500 response.
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_string_pretty(value) {
+            Ok(body) => Self::new(status, "application/json", body + "\n"),
+            Err(e) => Self::text(500, format!("Failed to encode response: {}\n", e)),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "Not Found\n")
+    }
+
+    /// Writes the response as HTTP/1.1; the connection is closed afterwards.
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            414 => "URI Too Long",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "",
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        writer.flush()
+    }
+}
+
+/// Decodes `%XX` escapes and `+` (as space) in a URL component. Invalid
+/// escapes are kept as they are.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Parses a listen address. A bare port such as `:9100` listens on all
+/// interfaces.
+pub fn parse_listen_addr(value: &str) -> Result<SocketAddr> {
+    let value = if value.starts_with(':') {
+        format!("0.0.0.0{}", value)
+    } else {
+        value.to_string()
+    };
+    value.parse().map_err(|_| {
+        anyhow!(
+            "Invalid listen address '{}', expected HOST:PORT or :PORT",
+            value
+        )
+    })
+}
+
+/// Reads one request from `stream`, answers it with `handler` and closes
+/// the connection. Only `GET` requests are served, with a request line of
+/// at most `MAX_LINE_LENGTH` bytes.
+///
+/// # Errors
+///
+/// Returns an error if reading the request or writing the response fails.
+pub fn handle_connection<S: Read + Write>(stream: S, handler: &Handler) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    (&mut reader)
+        .take(MAX_LINE_LENGTH)
+        .read_line(&mut request_line)?;
+    let too_long = !request_line.ends_with('\n') && request_line.len() as u64 == MAX_LINE_LENGTH;
+    // The handlers only need the request line
+    skip_head(&mut reader)?;
+
+    let response = match Request::parse(&request_line) {
+        _ if too_long => Response::text(414, "URI Too Long\n"),
+        Some(request) if request.method == "GET" => handler(&request),
+        Some(_) => Response::text(405, "Method Not Allowed\n"),
+        None => Response::text(400, "Bad Request\n"),
+    };
+    response.write_to(reader.get_mut())?;
+    Ok(())
+}
+
+/// Skips the rest of the request head up to the empty line that ends it,
+/// reading at most `MAX_HEAD_LENGTH` bytes, so the client is not reset by
+/// closing the connection with unread data.
+fn skip_head(reader: &mut impl BufRead) -> std::io::Result<()> {
+    let mut read = 0;
+    let mut line = Vec::new();
+    while read < MAX_HEAD_LENGTH {
+        line.clear();
+        let n = (&mut *reader)
+            .take(MAX_HEAD_LENGTH - read)
+            .read_until(b'\n', &mut line)?;
+        if n == 0 || line == b"\r\n" || line == b"\n" {
+            break;
+        }
+        read += n as u64;
+    }
+    Ok(())
+}
+
+/// Serves requests on `listener` forever, each connection in its own
+/// thread. A connection that cannot be accepted is logged and skipped.
+pub fn serve_tcp(listener: TcpListener, handler: Handler) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+            eprintln!("Failed to set up connection: {}", e);
+            continue;
+        }
+        let handler = handler.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &handler) {
+                eprintln!("Failed to handle request: {}", e);
+            }
+        });
+    }
+}
+
+/// Serves requests on a Unix domain socket forever, each connection in its
+/// own thread. A connection that cannot be accepted is logged and skipped.
+#[cfg(unix)]
+pub fn serve_unix(listener: std::os::unix::net::UnixListener, handler: Handler) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+            eprintln!("Failed to set up connection: {}", e);
+            continue;
+        }
+        let handler = handler.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &handler) {
+                eprintln!("Failed to handle request: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_parse_request() {
+        let request = Request::parse("GET /route/lookup?dst=fd00%3A%3A1&x HTTP/1.1\r\n").unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/route/lookup");
+        assert_eq!(
+            request.query,
+            vec![
+                ("dst".to_string(), "fd00::1".to_string()),
+                ("x".to_string(), String::new())
+            ]
+        );
+
+        assert!(Request::parse("GET /\r\n").is_none());
+        assert_eq!(percent_decode("a+b%2"), "a b%2");
+    }
+
+    #[test]
+    fn test_parse_listen_addr() {
+        assert_eq!(
+            parse_listen_addr(":9100").unwrap().to_string(),
+            "0.0.0.0:9100"
+        );
+        assert_eq!(
+            parse_listen_addr("127.0.0.1:8080").unwrap().to_string(),
+            "127.0.0.1:8080"
+        );
+        assert!(parse_listen_addr("9100").is_err());
+    }
+
+    #[test]
+    fn test_serve_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler: Handler = Arc::new(|request: &Request| match request.path.as_str() {
+            "/hello" => Response::text(
+                200,
+                format!("hello {}\n", request.param("name").unwrap_or("?")),
+            ),
+            _ => Response::not_found(),
+        });
+        thread::spawn(move || serve_tcp(listener, handler));
+
+        let get = |target: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/hello?name=ls_net");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 13\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello ls_net\n"));
+        assert!(get("/missing").starts_with("HTTP/1.1 404 Not Found"));
+        let long = format!("/hello?name={}", "x".repeat(MAX_LINE_LENGTH as usize));
+        assert!(get(&long).starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+        assert!(get("/hello").starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let mut unavailable = Vec::new();
+        Response::text(503, "").write_to(&mut unavailable).unwrap();
+        assert!(unavailable.starts_with(b"HTTP/1.1 503 Service Unavailable\r\n"));
+    }
+}
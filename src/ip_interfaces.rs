@@ -1,6 +1,58 @@
-use anyhow::{Result, anyhow};
-use colored::Colorize;
-use if_addrs::{IfAddr, Interface, get_if_addrs};
+use anyhow::{Context, Result};
+use if_addrs::{IfAddr, get_if_addrs};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::IpAddr;
+
+use crate::error::{Error, ErrorKind};
+use crate::links::get_links;
+use crate::render::{Element, Output};
+use crate::table::Table;
+
+/// A single IP address assigned to a network interface.
+///
+/// An interface with several addresses appears once per address, in the same
+/// way `get_if_addrs` reports them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceAddr {
+    pub name: String,
+    pub ip: IpAddr,
+    pub netmask: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl InterfaceAddr {
+    pub fn is_ipv4(&self) -> bool {
+        self.ip.is_ipv4()
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        self.ip.is_ipv6()
+    }
+}
+
+impl From<if_addrs::Interface> for InterfaceAddr {
+    fn from(interface: if_addrs::Interface) -> Self {
+        let (ip, netmask, prefix_len) = match interface.addr {
+            IfAddr::V4(addr) => (
+                IpAddr::V4(addr.ip),
+                IpAddr::V4(addr.netmask),
+                addr.prefixlen,
+            ),
+            IfAddr::V6(addr) => (
+                IpAddr::V6(addr.ip),
+                IpAddr::V6(addr.netmask),
+                addr.prefixlen,
+            ),
+        };
+        Self {
+            name: interface.name,
+            ip,
+            netmask,
+            prefix_len,
+        }
+    }
+}
 
 /// Gets all network interfaces and their corresponding IP addresses.
 ///
@@ -9,20 +61,22 @@ use if_addrs::{IfAddr, Interface, get_if_addrs};
 /// found, it returns an error.
 ///
 /// It then sorts the interfaces by name and returns them as a vector of
-/// `Interface` objects.
+/// `InterfaceAddr` objects.
 ///
 /// # Errors
 ///
-/// This function returns an error if no network interfaces are found.
-fn get_ip_interfaces() -> Result<Vec<Interface>> {
+/// This function returns an error of kind `NoInterfaces` if no network
+/// interfaces are found, or an error if they cannot be listed.
+pub fn get_ip_interfaces() -> Result<Vec<InterfaceAddr>> {
     // Get all network interfaces and their corresponding IP addresses
-    let interfaces = get_if_addrs()?;
+    let interfaces = get_if_addrs().context("Failed to list network interfaces")?;
     if interfaces.is_empty() {
-        return Err(anyhow!("No network interfaces found."));
+        return Err(Error::new(ErrorKind::NoInterfaces, "No network interfaces found.").into());
     }
 
     // Sort interfaces by name
-    let mut sorted_interfaces = interfaces.clone();
+    let mut sorted_interfaces: Vec<InterfaceAddr> =
+        interfaces.into_iter().map(InterfaceAddr::from).collect();
     sorted_interfaces.sort_by(|a, b| a.name.cmp(&b.name));
 
     Ok(sorted_interfaces)
@@ -32,84 +86,162 @@ fn get_ip_interfaces() -> Result<Vec<Interface>> {
 ///
 /// This function first gets all network interfaces and their IP addresses using
 /// the `get_ip_interfaces` function. If no interfaces are found, it returns an
-/// error.
-///
-/// It then filters the interfaces by IP protocol if a protocol is specified.
-/// Finally, it displays the filtered interfaces with their IP addresses in a
-/// formatted string.
+/// error. The interfaces are then written to `output` by
+/// `render_ip_interfaces`, with those `get_links` reports down marked.
 ///
 /// # Errors
 ///
-/// This function returns an error if no network interfaces are found.
+/// This function returns an error if no network interfaces are found or the
+/// output cannot be written.
 ///
 /// # Arguments
 ///
 /// * `protocol`: The IP protocol to filter by. If not specified, all
 ///   interfaces are displayed. Supported protocols are "ipv4" and "ipv6".
-pub fn display_ip_interfaces(protocol: &str) -> Result<()> {
-    let sorted_interfaces = get_ip_interfaces()?;
+pub fn display_ip_interfaces(output: &mut Output, protocol: &str) -> Result<()> {
+    let interfaces = get_ip_interfaces()?;
+    // Without link states, no interface is marked down
+    let down: Vec<String> = get_links()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|link| !link.up)
+        .map(|link| link.name)
+        .collect();
+    render_ip_interfaces(output, &interfaces, &down, protocol)
+}
+
+/// Writes `interfaces` with their IP addresses to `output`.
+///
+/// It filters the interfaces by IP protocol if a protocol is specified. As
+/// text, each address is written on a line with the interface name, styled
+/// as `Element::Down` for the interfaces named in `down`, followed by the
+/// number of interfaces found and displayed; as CSV or Markdown, the
+/// addresses are written as a table.
+///
+/// # Errors
+///
+/// This function returns an error if the output cannot be written.
+pub fn render_ip_interfaces(
+    output: &mut Output,
+    interfaces: &[InterfaceAddr],
+    down: &[String],
+    protocol: &str,
+) -> Result<()> {
+    let displayed: Vec<&InterfaceAddr> = interfaces
+        .iter()
+        .filter(|interface| match protocol {
+            "ipv4" => interface.is_ipv4(),
+            "ipv6" => interface.is_ipv6(),
+            _ => true, // Show all interfaces
+        })
+        .collect();
+
+    if !output.is_text() {
+        let mut table = Table::new(["Interface", "Family", "Address", "Netmask"]);
+        for interface in &displayed {
+            let family = if interface.is_ipv4() { "IPv4" } else { "IPv6" };
+            table.push_row(vec![
+                interface.name.clone(),
+                family.to_string(),
+                interface.ip.to_string(),
+                interface.netmask.to_string(),
+            ]);
+        }
+        output.table(&table)?;
+        return Ok(());
+    }
 
     // Calculate the longest interface name for formatting output
-    let max_name_len = sorted_interfaces
+    let max_name_len = interfaces
         .iter()
         .map(|interface| interface.name.len())
         .max()
         .unwrap_or(10);
 
-    // Count displayed interfaces
-    let mut displayed_count = 0;
-    let len_interfaces = sorted_interfaces.len();
-
-    println!(
-        "{}",
-        "=============================================".green()
+    let separator = output.paint(
+        Element::Separator,
+        "=============================================",
     );
+    writeln!(output, "{}", separator)?;
 
     // Display all interfaces and IP addresses
-    for interface in sorted_interfaces {
-        // Filter by protocol if specified
-        match protocol {
-            "ipv4" => {
-                if let IfAddr::V4(_) = interface.addr {
-                    // Continue if it's IPv4
-                } else {
-                    continue;
-                }
-            }
-            "ipv6" => {
-                if let IfAddr::V6(_) = interface.addr {
-                    // Continue if it's IPv6
-                } else {
-                    continue;
-                }
-            }
-            _ => (), // Show all interfaces
-        }
-
-        let ip_info = match interface.addr {
-            IfAddr::V4(addr) => format!("IPv4: {}/{}", addr.ip, addr.netmask),
-            IfAddr::V6(addr) => format!("IPv6: {}/{}", addr.ip, addr.netmask),
+    for interface in &displayed {
+        let ip_info = match interface.ip {
+            IpAddr::V4(ip) => format!("IPv4: {}/{}", ip, interface.netmask),
+            IpAddr::V6(ip) => format!("IPv6: {}/{}", ip, interface.netmask),
         };
 
-        println!(
-            "{}: {}",
-            format!("{:width$}", interface.name, width = max_name_len)
-                .blue()
-                .bold(),
-            ip_info.yellow()
+        let element = if down.contains(&interface.name) {
+            Element::Down
+        } else {
+            Element::Name
+        };
+        let name = output.paint(
+            element,
+            &format!("{:width$}", interface.name, width = max_name_len),
         );
-
-        displayed_count += 1;
+        let ip_info = output.paint(Element::Address, &ip_info);
+        writeln!(output, "{}: {}", name, ip_info)?;
     }
 
-    println!(
-        "{}",
-        "=============================================".green()
-    );
-    println!(
+    writeln!(output, "{}", separator)?;
+    writeln!(
+        output,
         "Found {} network interfaces (displaying {})",
-        len_interfaces, displayed_count
-    );
+        interfaces.len(),
+        displayed.len()
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::{assert_golden, fixture};
+    use crate::render::Style;
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn test_render_ip_interfaces_golden() {
+        let snapshot: Snapshot = serde_json::from_str(&fixture("snapshot/debian12.json")).unwrap();
+        for (format, protocol, extension) in [
+            ("text", "ipv4", "txt"),
+            ("text", "all", "txt"),
+            ("csv", "all", "csv"),
+            ("markdown", "ipv6", "md"),
+        ] {
+            let mut buffer = Vec::new();
+            let mut output = Output::new(
+                &mut buffer,
+                format,
+                Style {
+                    color: false,
+                    ..Style::default()
+                },
+            );
+            render_ip_interfaces(&mut output, &snapshot.interfaces, &[], protocol).unwrap();
+            assert_golden(
+                &format!("interfaces/debian12-{}.{}", protocol, extension),
+                &String::from_utf8(buffer).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_down_interfaces() {
+        let snapshot: Snapshot = serde_json::from_str(&fixture("snapshot/debian12.json")).unwrap();
+        let mut buffer = Vec::new();
+        let mut output = Output::new(&mut buffer, "text", Style::default());
+        render_ip_interfaces(
+            &mut output,
+            &snapshot.interfaces,
+            &["lo".to_string()],
+            "ipv4",
+        )
+        .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("\x1b[1;34meth0\x1b[0m: "), "{}", text);
+        assert!(text.contains("\x1b[31mlo  \x1b[0m: "), "{}", text);
+    }
+}
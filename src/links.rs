@@ -0,0 +1,261 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::probe::{RealProbe, SystemProbe};
+
+/// Directory with one entry per network interface on Linux.
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+const IFF_UP: u32 = 0x1;
+
+/// Traffic counters of a network interface since it was brought up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LinkStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+/// The link-layer state of a network interface.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Link {
+    pub name: String,
+    /// The interface is administratively up.
+    pub up: bool,
+    /// Whether the interface has a carrier, if known.
+    pub carrier: Option<bool>,
+    pub mtu: Option<u32>,
+    /// The bridge or bond this interface is a member of.
+    pub master: Option<String>,
+    pub stats: LinkStats,
+}
+
+impl Link {
+    /// Returns `true` if the interface is up and not known to lack a carrier.
+    pub fn is_running(&self) -> bool {
+        self.up && self.carrier != Some(false)
+    }
+}
+
+/// Gets the state, MTU and traffic counters of all network interfaces,
+/// sorted by name.
+///
+/// On Linux this reads `/sys/class/net`; other platforms use `netstat -ibn`,
+/// which provides no carrier state, bridge membership or drop counters.
+///
+/// # Errors
+///
+/// Returns an error if the interface list cannot be read.
+pub fn get_links() -> Result<Vec<Link>> {
+    get_links_from(&RealProbe)
+}
+
+/// Gets the links of the system behind `probe`, as described for
+/// `get_links`.
+///
+/// # Errors
+///
+/// Returns an error if the interface list cannot be read.
+pub fn get_links_from(probe: &dyn SystemProbe) -> Result<Vec<Link>> {
+    let mut links = if probe.os() == "linux" {
+        read_sys_links(probe, Path::new(SYS_CLASS_NET))?
+    } else {
+        let output = probe.run("netstat", &["-ibn"])?;
+        if !output.success {
+            return Err(anyhow!("Failed to execute netstat command"));
+        }
+        parse_netstat_interfaces(&output.stdout)
+    };
+    links.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(links)
+}
+
+/// Reads the interfaces below a `/sys/class/net`-style directory with
+/// `probe`.
+///
+/// Attributes that cannot be read, such as `carrier` of an interface that is
+/// down, are left unset.
+pub fn read_sys_links(probe: &dyn SystemProbe, root: &Path) -> Result<Vec<Link>> {
+    let mut links = Vec::new();
+    for name in probe.read_dir(root)? {
+        let dir = root.join(&name);
+        let read = |file: &str| {
+            probe
+                .read_file(&dir.join(file))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+        let number = |file: &str| read(file).and_then(|value| value.parse::<u64>().ok());
+        let counter = |file: &str| number(&format!("statistics/{}", file)).unwrap_or(0);
+
+        let flags = read("flags")
+            .and_then(|flags| u32::from_str_radix(flags.trim_start_matches("0x"), 16).ok())
+            .unwrap_or(0);
+        let master = probe
+            .read_link(&dir.join("master"))
+            .ok()
+            .and_then(|target| {
+                target
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            });
+
+        links.push(Link {
+            name,
+            up: flags & IFF_UP != 0,
+            carrier: number("carrier").map(|carrier| carrier == 1),
+            mtu: number("mtu").map(|mtu| mtu as u32),
+            master,
+            stats: LinkStats {
+                rx_bytes: counter("rx_bytes"),
+                tx_bytes: counter("tx_bytes"),
+                rx_packets: counter("rx_packets"),
+                tx_packets: counter("tx_packets"),
+                rx_errors: counter("rx_errors"),
+                tx_errors: counter("tx_errors"),
+                rx_dropped: counter("rx_dropped"),
+                tx_dropped: counter("tx_dropped"),
+            },
+        });
+    }
+    Ok(links)
+}
+
+/// Parses the link-level lines (`<Link#N>`) of BSD/macOS `netstat -ibn`.
+///
+/// Interfaces that are down are marked with a `*` after their name.
+pub fn parse_netstat_interfaces(output: &str) -> Vec<Link> {
+    output
+        .lines()
+        .filter(|line| line.contains("<Link#"))
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            // The address column is empty for some interfaces, so the
+            // counters are taken from the end:
+            // Ipkts Ierrs Ibytes Opkts Oerrs Obytes Coll
+            let counters: Vec<u64> = parts
+                .get(parts.len().checked_sub(7)?..)?
+                .iter()
+                .map(|value| value.parse().unwrap_or(0))
+                .collect();
+            let name = parts.first()?;
+
+            Some(Link {
+                name: name.trim_end_matches('*').to_string(),
+                up: !name.ends_with('*'),
+                carrier: None,
+                mtu: parts.get(1).and_then(|mtu| mtu.parse().ok()),
+                master: None,
+                stats: LinkStats {
+                    rx_packets: counters[0],
+                    rx_errors: counters[1],
+                    rx_bytes: counters[2],
+                    tx_packets: counters[3],
+                    tx_errors: counters[4],
+                    tx_bytes: counters[5],
+                    ..Default::default()
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_sys_links() {
+        let root = std::env::temp_dir().join(format!("ls_net_sys_{}", std::process::id()));
+        let eth0 = root.join("eth0");
+        fs::create_dir_all(eth0.join("statistics")).unwrap();
+        fs::write(eth0.join("flags"), "0x1003\n").unwrap();
+        fs::write(eth0.join("carrier"), "1\n").unwrap();
+        fs::write(eth0.join("mtu"), "1500\n").unwrap();
+        fs::write(eth0.join("statistics/rx_bytes"), "123456\n").unwrap();
+        fs::write(eth0.join("statistics/tx_errors"), "3\n").unwrap();
+        let down = root.join("veth1");
+        fs::create_dir_all(&down).unwrap();
+        fs::write(down.join("flags"), "0x1002\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("../br0", down.join("master")).unwrap();
+
+        let mut links = read_sys_links(&RealProbe, &root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        links.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(links.len(), 2);
+        assert!(links[0].is_running());
+        assert_eq!(links[0].mtu, Some(1500));
+        assert_eq!(links[0].stats.rx_bytes, 123456);
+        assert_eq!(links[0].stats.tx_errors, 3);
+        assert!(!links[1].up);
+        assert_eq!(links[1].carrier, None);
+        #[cfg(unix)]
+        assert_eq!(links[1].master.as_deref(), Some("br0"));
+    }
+
+    #[test]
+    fn test_parse_netstat_interfaces() {
+        let output = "\
+Name       Mtu   Network       Address            Ipkts Ierrs     Ibytes    Opkts Oerrs     Obytes  Coll
+lo0        16384 <Link#1>                        104530     0   18204826   104530     0   18204826     0
+lo0        16384 127           127.0.0.1         104530     -   18204826   104530     -   18204826     -
+en0        1500  <Link#4>    a0:b1:c2:d3:e4:f5  9081726     0 9829384722  3942716     5  894857312     0
+utun3*     1380  <Link#12>                            0     0          0        2     0        160     0
+";
+        let links = parse_netstat_interfaces(output);
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].name, "lo0");
+        assert_eq!(links[0].mtu, Some(16384));
+        assert_eq!(links[1].stats.rx_bytes, 9829384722);
+        assert_eq!(links[1].stats.tx_errors, 5);
+        assert_eq!(links[2].name, "utun3");
+        assert!(!links[2].up);
+        assert_eq!(links[2].stats.tx_bytes, 160);
+    }
+
+    #[test]
+    fn test_get_links() {
+        assert!(get_links().is_ok());
+    }
+
+    #[test]
+    fn test_get_links_golden() {
+        use crate::golden::{assert_golden_json, fixture};
+        use crate::probe::FakeProbe;
+
+        // Recorded from /sys/class/net on Debian 12
+        let linux = FakeProbe::new("linux")
+            .file("/sys/class/net/eth0/flags", "0x1003\n")
+            .file("/sys/class/net/eth0/carrier", "1\n")
+            .file("/sys/class/net/eth0/mtu", "1400\n")
+            .file("/sys/class/net/eth0/statistics/rx_bytes", "48211523\n")
+            .file("/sys/class/net/eth0/statistics/rx_packets", "35187\n")
+            .file("/sys/class/net/eth0/statistics/tx_bytes", "1928375\n")
+            .file("/sys/class/net/eth0/statistics/tx_packets", "21420\n")
+            .file("/sys/class/net/ifb0/flags", "0x82\n")
+            .file("/sys/class/net/ifb0/mtu", "1500\n")
+            .file("/sys/class/net/lo/flags", "0x9\n")
+            .file("/sys/class/net/lo/carrier", "1\n")
+            .file("/sys/class/net/lo/mtu", "65536\n")
+            .file("/sys/class/net/lo/statistics/rx_bytes", "5040\n")
+            .file("/sys/class/net/lo/statistics/tx_bytes", "5040\n")
+            .file("/sys/class/net/veth0/flags", "0x1003\n")
+            .file("/sys/class/net/veth0/carrier", "1\n")
+            .file("/sys/class/net/veth0/mtu", "1500\n")
+            .link("/sys/class/net/veth0/master", "../br0");
+        assert_golden_json("links/linux-sysfs.json", &get_links_from(&linux).unwrap());
+
+        let macos =
+            FakeProbe::new("macos").command("netstat -ibn", &fixture("netstat/macos-14-ibn.txt"));
+        assert_golden_json("links/macos-14.json", &get_links_from(&macos).unwrap());
+    }
+}
@@ -1,74 +1,829 @@
+mod api;
+mod calc;
+mod check;
+mod diff;
+mod doctor;
+mod error;
+#[cfg(test)]
+mod golden;
+mod http;
 mod ip_interfaces;
+mod links;
 mod machine_main_ip;
+mod monitor;
+mod neighbors;
+#[cfg(target_os = "linux")]
+mod netlink;
+mod netns;
+mod prefix;
+mod probe;
+mod prometheus;
+mod render;
 mod route_table;
+mod snapshot;
+mod sockets;
+mod table;
+mod topology;
+mod tui;
+
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::io::{self, IsTerminal, Read, Write};
+use std::net::{IpAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use anyhow::Result;
-use clap::Parser;
-use colored::*;
+use crate::diff::SnapshotDiff;
+use crate::error::{Error, ErrorKind, ErrorReport};
+use crate::render::{Element, Output, Style, Theme};
+use crate::route_table::ParseOptions;
+use crate::route_table::analysis;
+use crate::route_table::filter::RouteFilter;
+use crate::route_table::route_table::RouteCollector;
+use crate::route_table::rules::{self, RouteQuery};
+use crate::route_table::source::RouteSource;
+use crate::route_table::view::{self, RouteColumn, RouteView};
+use crate::snapshot::Snapshot;
 
 /// A CLI tool for displaying local network interfaces, IP addresses and routes.
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     /// Protocol type to use: "all", "ipv4", or "ipv6". Defaults to "ipv4".
-    #[clap(short, long, default_value="ipv4", value_parser=["all","ipv4","ipv6"])]
+    #[clap(short, long, global = true, default_value="ipv4", value_parser=["all","ipv4","ipv6"])]
     protocol: String,
 
     /// Only show the main IP address of the machine
     #[clap(long)]
     ip: bool,
+
+    /// Output format: "text", "json", the interface or route tables as "csv" or "markdown",
+    /// or a topology graph as "dot" (Graphviz) or "mermaid".
+    #[clap(long, global = true, default_value="text", value_parser=["text","json","csv","markdown","dot","mermaid"])]
+    format: String,
+
+    /// Run inside a network namespace, given by name, path or process ID (Linux only).
+    #[clap(long, global = true)]
+    netns: Option<String>,
+
+    /// Fail on lines of route command output (e.g. netstat) that are not routes, instead of
+    /// skipping them
+    #[clap(long, global = true)]
+    strict: bool,
+
+    /// Print a warning for every skipped line of route command output
+    #[clap(short, long, global = true)]
+    verbose: bool,
+
+    /// When to color the output: "auto" colors a terminal unless NO_COLOR is set, or any output
+    /// if CLICOLOR_FORCE is set
+    #[clap(long, global = true, default_value = "auto", value_parser = ["auto", "always", "never"])]
+    color: String,
+
+    /// Theme file with the colors of the text output; defaults to ls_net/theme.toml in the
+    /// user's configuration directory if it exists
+    #[clap(long, global = true, value_name = "FILE")]
+    theme: Option<PathBuf>,
+
+    /// Write only ASCII characters, for legacy consoles
+    #[clap(long, global = true)]
+    ascii: bool,
+
+    /// Exit with an error (code 6) if part of the output, such as the interfaces or the routes,
+    /// could not be collected, instead of showing the rest
+    #[clap(long, global = true)]
+    fail_on_partial: bool,
+
+    /// Where to read routes from. "auto" tries the sources available on this system in turn,
+    /// e.g. netlink, iproute2, procfs and netstat on Linux.
+    #[clap(long, global = true, default_value = "auto", value_parser = [
+        "auto", "procfs", "netlink", "netstat-linux", "netstat-macos", "netstat-bsd", "iproute2",
+        "windows-route-print", "file-replay",
+    ])]
+    route_source: String,
+
+    /// Snapshot file (from "snapshot save") whose routes the file-replay route source reads
+    #[clap(long, global = true)]
+    replay_file: Option<PathBuf>,
+
+    /// Read routes from captured route command output in a file, or "-" for stdin, instead of
+    /// the system
+    #[clap(
+        long,
+        global = true,
+        requires = "input_format",
+        conflicts_with = "route_source"
+    )]
+    from_file: Option<PathBuf>,
+
+    /// Format of the --from-file output: "linux-netstat" (netstat -rn or route -n),
+    /// "macos-netstat", "bsd-netstat", "iproute2" (ip route, text or JSON) or "windows"
+    /// (route print)
+    #[clap(long, global = true, value_parser = [
+        "linux-netstat", "macos-netstat", "bsd-netstat", "iproute2", "windows",
+    ])]
+    input_format: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect network namespaces (Linux only)
+    Netns {
+        #[clap(subcommand)]
+        action: NetnsCommand,
+    },
+
+    /// Show the routing tables
+    Routes {
+        /// Routing table to show, by name or ID, or "all" for every table and the policy rules.
+        #[clap(long, default_value = "main")]
+        table: String,
+
+        /// Comma-separated columns to show: dest, gw, flags, iface, expire, genmask, metric,
+        /// proto, scope, src, table, type, mtu, refs, use, mss, window or irtt. Defaults to
+        /// "dest,gw,flags,iface,expire".
+        #[clap(long, value_delimiter = ',', value_parser = view::parse_column)]
+        columns: Vec<RouteColumn>,
+
+        /// Sort the routes by "metric", "dest", "iface" or "prefixlen"
+        #[clap(long, value_parser = ["metric", "dest", "iface", "prefixlen"])]
+        sort: Option<String>,
+
+        /// Reverse the order of the routes
+        #[clap(long)]
+        reverse: bool,
+
+        /// Summarize the routes: counts per next hop, interface and prefix length, and the
+        /// routes of each next hop aggregated into covering prefixes
+        #[clap(long)]
+        summary: bool,
+
+        #[clap(flatten)]
+        filter: RouteFilter,
+    },
+
+    /// Show which policy rule and route the kernel would use for a destination
+    Lookup {
+        /// Destination IP address
+        destination: IpAddr,
+
+        /// Source address of the traffic
+        #[clap(long)]
+        from: Option<IpAddr>,
+
+        /// Firewall mark of the traffic, in decimal or hex (0x...)
+        #[clap(long, value_parser = rules::parse_fwmark)]
+        fwmark: Option<u32>,
+
+        /// Incoming interface of the traffic; locally generated traffic uses "lo"
+        #[clap(long)]
+        iif: Option<String>,
+
+        /// Also list the other routes to the destination and why they lost
+        #[clap(long)]
+        explain: bool,
+    },
+
+    /// Save the network state to a file for a later diff
+    Snapshot {
+        #[clap(subcommand)]
+        action: SnapshotCommand,
+    },
+
+    /// Show route and address changes between two snapshots
+    Diff {
+        /// Snapshot file to compare from, or "live" for the current state
+        old: String,
+
+        /// Snapshot file to compare to, or "live" for the current state
+        #[clap(default_value = "live")]
+        new: String,
+    },
+
+    /// Stream route, address, link and neighbor changes as they happen
+    Monitor {
+        /// Compare snapshots periodically instead of listening for netlink
+        /// notifications; always used on platforms other than Linux
+        #[clap(long)]
+        poll: bool,
+
+        /// Polling interval in seconds
+        #[clap(long, default_value_t = 2)]
+        interval: u64,
+
+        /// Exit after printing this many events
+        #[clap(long)]
+        count: Option<usize>,
+    },
+
+    /// Serve host network state to other programs
+    Serve {
+        /// Expose Prometheus metrics on this address, e.g. ":9100"
+        #[clap(long, value_name = "ADDR")]
+        prometheus: Option<String>,
+
+        /// Serve the JSON API on this address, e.g. "127.0.0.1:8080"
+        #[clap(long, value_name = "ADDR")]
+        http: Option<String>,
+
+        /// Serve the JSON API on this Unix domain socket
+        #[clap(long, value_name = "PATH")]
+        unix: Option<PathBuf>,
+    },
+
+    /// Check that gateways answer
+    Check {
+        #[clap(subcommand)]
+        action: CheckCommand,
+    },
+
+    /// Diagnose common network misconfigurations
+    Doctor,
+
+    /// Find overlapping subnets and routes shadowing other routes
+    Conflicts,
+
+    /// Subnet calculator: addresses, masks and host ranges of prefixes
+    Calc {
+        /// Prefixes in addr/len notation; a bare address is a single host
+        #[clap(required = true)]
+        prefixes: Vec<String>,
+
+        /// Split each prefix into subnets of this length, e.g. /26
+        #[clap(long, value_name = "LEN", value_parser = calc::parse_split)]
+        split: Option<u8>,
+
+        /// Merge the prefixes into the fewest covering prefixes and show their common supernet
+        #[clap(long)]
+        aggregate: bool,
+    },
+
+    /// Interactive dashboard of interfaces, routes, neighbors and sockets
+    Tui {
+        /// Refresh interval in seconds
+        #[clap(long, default_value_t = 2)]
+        interval: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    /// Save interfaces, addresses and all routing tables as JSON
+    Save {
+        /// File to write the snapshot to
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CheckCommand {
+    /// Probe every default gateway and next hop with ICMP echo, the neighbor table and
+    /// optionally TCP
+    Gw {
+        /// Number of echo requests per gateway
+        #[clap(long, default_value_t = 3)]
+        count: u32,
+
+        /// Seconds to wait for each reply or TCP handshake
+        #[clap(long, default_value_t = 1)]
+        timeout: u64,
+
+        /// Also try a TCP connection to this port of each gateway
+        #[clap(long, value_name = "PORT")]
+        tcp: Option<u16>,
+    },
+}
+
+#[derive(Subcommand)]
+enum NetnsCommand {
+    /// List all network namespaces with their interfaces
+    List,
+}
+
+/// Prints a value as pretty-printed JSON.
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Loads the snapshot `source` refers to: a snapshot file, or "live" for the
+/// current network state with the route table from `routes`.
+fn load_snapshot(source: &str, routes: &RouteCollector) -> Result<Snapshot> {
+    if source == "live" {
+        Snapshot::collect(routes)
+    } else {
+        Snapshot::load(Path::new(source))
+    }
+}
+
+/// Binds the requested servers and serves them until the process is
+/// stopped: Prometheus metrics on `prometheus`, and the JSON API on `http`
+/// and/or the Unix socket `unix`.
+///
+/// # Errors
+///
+/// Returns an error if no server is requested, an address cannot be bound,
+/// or a server thread panics.
+fn serve(
+    prometheus: Option<&str>,
+    http: Option<&str>,
+    unix: Option<&Path>,
+    routes: &RouteCollector,
+) -> Result<()> {
+    let mut servers = Vec::new();
+
+    for (addr, handler, what) in [
+        (
+            prometheus,
+            prometheus::handler(routes.clone()),
+            "Prometheus metrics",
+        ),
+        (http, api::handler(routes.clone()), "JSON API"),
+    ] {
+        let Some(addr) = addr else { continue };
+        let addr = http::parse_listen_addr(addr)?;
+        let listener =
+            TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+        eprintln!("Serving {} on http://{}", what, addr);
+        servers.push(thread::spawn(move || http::serve_tcp(listener, handler)));
+    }
+
+    if let Some(path) = unix {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            use std::os::unix::net::UnixListener;
+
+            // Replace a socket left behind by a previous run
+            if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to listen on {}", path.display()))?;
+            eprintln!("Serving JSON API on unix:{}", path.display());
+            let handler = api::handler(routes.clone());
+            servers.push(thread::spawn(move || http::serve_unix(listener, handler)));
+        }
+        #[cfg(not(unix))]
+        return Err(anyhow!(
+            "Unix sockets are not supported on this platform: {}",
+            path.display()
+        ));
+    }
+
+    if servers.is_empty() {
+        return Err(anyhow!(
+            "Nothing to serve, use --prometheus ADDR, --http ADDR or --unix PATH"
+        ));
+    }
+    for server in servers {
+        server
+            .join()
+            .map_err(|_| anyhow!("Server thread panicked"))?;
+    }
+    Ok(())
+}
+
+/// Prints the network topology graph in the given format ("dot" or
+/// "mermaid").
+///
+/// # Errors
+///
+/// Returns an error if the network interfaces or the route table cannot be
+/// collected.
+fn print_topology(protocol: &str, format: &str, routes: &RouteCollector) -> Result<()> {
+    let interfaces = ip_interfaces::get_ip_interfaces()?;
+    let route_table = routes.collect()?;
+    let topology = topology::Topology::build(&interfaces, &route_table, protocol);
+
+    match format {
+        "dot" => print!("{}", topology.to_dot()),
+        _ => print!("{}", topology.to_mermaid()),
+    }
+
+    Ok(())
+}
+
+/// Returns the content of the file at `path`, or of stdin for `-`.
+///
+/// # Errors
+///
+/// Returns an error if the file or stdin cannot be read.
+fn read_input(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read stdin")?;
+        return Ok(input);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Returns the style of the text output chosen with `--color`, `--theme` and
+/// `--ascii`.
+///
+/// # Errors
+///
+/// Returns an error if the theme file cannot be loaded.
+fn output_style(args: &Args) -> Result<Style> {
+    let color = render::use_color(
+        &args.color,
+        std::env::var("NO_COLOR").ok().as_deref(),
+        std::env::var("CLICOLOR_FORCE").ok().as_deref(),
+        io::stdout().is_terminal(),
+    );
+    Ok(Style {
+        color,
+        ascii: args.ascii,
+        theme: Theme::load(args.theme.as_deref())?,
+    })
+}
+
+/// Returns how route tables are collected: from the captured output of
+/// `--from-file`, the source of `--route-source` or an auto-detected one,
+/// parsed as chosen with `--strict` and `--verbose`.
+///
+/// # Errors
+///
+/// Returns an error if the captured output cannot be read or the route
+/// source is missing its replay file.
+fn route_collector(args: &Args) -> Result<RouteCollector> {
+    let source: Option<Arc<dyn RouteSource>> =
+        if let (Some(path), Some(format)) = (&args.from_file, &args.input_format) {
+            Some(Arc::new(route_table::source::CapturedOutput {
+                format: format.clone(),
+                output: read_input(path)?,
+            }))
+        } else if args.route_source != "auto" {
+            Some(Arc::from(route_table::source::source_by_name(
+                &args.route_source,
+                args.replay_file.as_deref(),
+            )?))
+        } else {
+            None
+        };
+    Ok(RouteCollector {
+        source,
+        options: ParseOptions {
+            strict: args.strict,
+            verbose: args.verbose,
+        },
+    })
 }
 
-fn run(protocol: &str, only_show_ip: bool) -> Result<()> {
-    let only_show_ip = only_show_ip;
+fn run(args: &Args) -> Result<()> {
+    let protocol = args.protocol.as_str();
+    let only_show_ip = args.ip;
+    let style = output_style(args)?;
 
-    let main_ip_addr = machine_main_ip::get_local_ip().unwrap_or_else(|e| {
-        eprintln!("Error getting IP address: {}", e);
-        std::process::exit(1);
-    });
+    if let Some(netns) = &args.netns {
+        netns::enter_netns(netns)?;
+    }
+    let routes = route_collector(args)?;
+
+    match &args.command {
+        Some(Command::Netns {
+            action: NetnsCommand::List,
+        }) => {
+            return netns::display_netns_list(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                protocol,
+            );
+        }
+        Some(Command::Routes {
+            table,
+            columns,
+            sort,
+            reverse,
+            summary,
+            filter,
+        }) => {
+            if *summary {
+                let mut selected =
+                    routes
+                        .collect()?
+                        .select(table, protocol, &routes.table_names())?;
+                filter.apply(&mut selected);
+                let summary = route_table::summary::summarize(&selected);
+                if args.format == "json" {
+                    return print_json(&summary);
+                }
+                route_table::summary::print_summary(
+                    &mut Output::new(&mut io::stdout(), &args.format, style),
+                    &summary,
+                )?;
+                return Ok(());
+            }
+            let view = RouteView {
+                columns: columns.clone(),
+                sort: sort.clone(),
+                reverse: *reverse,
+            };
+            if args.format == "json" {
+                let mut selected =
+                    routes
+                        .collect()?
+                        .select(table, protocol, &routes.table_names())?;
+                filter.apply(&mut selected);
+                view.sort(&mut selected.ipv4_routes);
+                view.sort(&mut selected.ipv6_routes);
+                return print_json(&selected);
+            }
+            return route_table::route_table::get_route_table(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                &routes,
+                protocol,
+                table,
+                filter,
+                &view,
+            );
+        }
+        Some(Command::Lookup {
+            destination,
+            from,
+            fwmark,
+            iif,
+            explain,
+        }) => {
+            let query = RouteQuery {
+                dst: *destination,
+                src: *from,
+                fwmark: *fwmark,
+                iif: iif.clone(),
+                oif: None,
+            };
+            if *explain {
+                let snapshot = Snapshot::collect(&routes)?;
+                let subnets = analysis::connected_subnets(&snapshot.interfaces);
+                let explanation = analysis::explain(&snapshot.routes, &subnets, &query)
+                    .ok_or_else(|| anyhow!("No route to {}", query.dst))?;
+                if args.format == "json" {
+                    return print_json(&explanation);
+                }
+                analysis::print_explanation(
+                    &mut Output::new(&mut io::stdout(), &args.format, style),
+                    &explanation,
+                )?;
+                return Ok(());
+            }
+            if args.format == "json" {
+                let route_table = routes.collect()?;
+                return print_json(&route_table.lookup(&query));
+            }
+            return route_table::route_table::display_route_lookup(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                &routes,
+                &query,
+            );
+        }
+        Some(Command::Snapshot {
+            action: SnapshotCommand::Save { file },
+        }) => {
+            Snapshot::collect(&routes)?.save(file)?;
+            println!("Snapshot saved to {}", file.display());
+            return Ok(());
+        }
+        Some(Command::Diff { old, new }) => {
+            let old = load_snapshot(old, &routes)?.filter_protocol(protocol);
+            let new = load_snapshot(new, &routes)?.filter_protocol(protocol);
+            let diff = SnapshotDiff::between(&old, &new);
+            if args.format == "json" {
+                return print_json(&diff);
+            }
+            if diff.is_empty() {
+                println!("No changes");
+            } else {
+                diff.display(&mut Output::new(&mut io::stdout(), &args.format, style))?;
+            }
+            return Ok(());
+        }
+        Some(Command::Monitor {
+            poll,
+            interval,
+            count,
+        }) => {
+            return monitor::run_monitor(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                protocol,
+                *poll,
+                Duration::from_secs(*interval),
+                *count,
+                &routes,
+            );
+        }
+        Some(Command::Serve {
+            prometheus,
+            http,
+            unix,
+        }) => {
+            return serve(
+                prometheus.as_deref(),
+                http.as_deref(),
+                unix.as_deref(),
+                &routes,
+            );
+        }
+        Some(Command::Check {
+            action:
+                CheckCommand::Gw {
+                    count,
+                    timeout,
+                    tcp,
+                },
+        }) => {
+            let options = check::CheckOptions {
+                count: *count,
+                timeout: Duration::from_secs(*timeout),
+                tcp_port: *tcp,
+            };
+            return check::check_gateways(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                &routes.collect()?,
+                protocol,
+                &options,
+            );
+        }
+        Some(Command::Doctor) => {
+            return doctor::run_doctor(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                &routes,
+            );
+        }
+        Some(Command::Calc {
+            prefixes,
+            split,
+            aggregate,
+        }) => {
+            return calc::run_calc(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                prefixes,
+                *split,
+                *aggregate,
+            );
+        }
+        Some(Command::Conflicts) => {
+            let snapshot = Snapshot::collect(&routes)?.filter_protocol(protocol);
+            let subnets = analysis::connected_subnets(&snapshot.interfaces);
+            let conflicts = analysis::find_conflicts(&snapshot.routes, &subnets);
+            if args.format == "json" {
+                return print_json(&conflicts);
+            }
+            analysis::print_conflicts(
+                &mut Output::new(&mut io::stdout(), &args.format, style),
+                &conflicts,
+            )?;
+            return Ok(());
+        }
+        Some(Command::Tui { interval }) => {
+            return tui::run_tui(
+                protocol,
+                Duration::from_secs(*interval),
+                style.ascii,
+                &routes,
+            );
+        }
+        None => {}
+    }
 
     if only_show_ip {
-        println!("{}", main_ip_addr);
+        println!(
+            "{}",
+            machine_main_ip::get_local_ip().context("Error getting IP address")?
+        );
         return Ok(());
     }
 
-    println!(
-        "{}",
-        "Local Network Interfaces and IP Addresses".green().bold()
-    );
+    if args.format == "json" {
+        let snapshot = Snapshot::collect_partial(&routes).filter_protocol(protocol);
+        // Without any data, only the errors are printed
+        if snapshot.errors.len() < Snapshot::PARTS {
+            print_json(&snapshot)?;
+        } else {
+            print_json(&serde_json::json!({ "errors": snapshot.errors }))?;
+        }
+        return check_partial(snapshot.errors, Snapshot::PARTS, args.fail_on_partial);
+    }
+
+    let mut stdout = io::stdout();
+    let mut output = Output::new(&mut stdout, &args.format, style);
+    if matches!(args.format.as_str(), "csv" | "markdown") {
+        return ip_interfaces::display_ip_interfaces(&mut output, protocol);
+    }
+
+    if args.format != "text" {
+        return print_topology(protocol, &args.format, &routes);
+    }
+
+    let mut errors = Vec::new();
+    let title = output.paint(Element::Title, "Local Network Interfaces and IP Addresses");
+    writeln!(output, "{}", title)?;
     match machine_main_ip::get_local_ip() {
         Ok(ip) => {
-            println!(
-                "{} {}",
-                "Main IP address: ".blue().bold(),
-                ip.to_string().yellow()
-            );
+            let label = output.paint(Element::Header, "Main IP address: ");
+            let ip = output.paint(Element::Address, &ip);
+            writeln!(output, "{} {}", label, ip)?;
+        }
+        Err(e) => {
+            eprintln!("Error getting IP address: {}", e);
+            errors.push(ErrorReport::from(&e.context("Error getting IP address")));
         }
-        Err(e) => eprintln!("Error getting IP address: {}", e),
     };
 
-    match ip_interfaces::display_ip_interfaces(protocol) {
+    match ip_interfaces::display_ip_interfaces(&mut output, protocol) {
         Ok(_) => {}
         Err(e) => {
-            println!("{}", "============================================".red());
+            let separator = output.paint(
+                Element::Error,
+                "============================================",
+            );
+            writeln!(output, "{}", separator)?;
             eprintln!("Failed to get network interfaces: {}", e);
-            println!("{}", "============================================".red());
+            writeln!(output, "{}", separator)?;
+            errors.push(ErrorReport::from(&e));
         }
     }
 
-    println!();
+    writeln!(output)?;
 
-    match route_table::route_table::get_route_table(protocol) {
+    match route_table::route_table::get_route_table(
+        &mut output,
+        &routes,
+        protocol,
+        "main",
+        &RouteFilter::default(),
+        &RouteView::default(),
+    ) {
         Ok(_) => {}
-        Err(e) => eprintln!("{}", e),
+        Err(e) => {
+            eprintln!("{}", e);
+            errors.push(ErrorReport::from(&e));
+        }
     }
 
+    check_partial(errors, Snapshot::PARTS, args.fail_on_partial)
+}
+
+/// Decides the outcome of a command whose output has `parts` parts that
+/// were collected independently, given the `errors` of the parts that could
+/// not be collected, which have been reported already.
+///
+/// # Errors
+///
+/// Returns the most specific error, the first one of a kind other than
+/// `Other`, marked as reported if no part could be collected, and an error
+/// of kind `PartialData` if some could not with `fail_on_partial`.
+fn check_partial(errors: Vec<ErrorReport>, parts: usize, fail_on_partial: bool) -> Result<()> {
+    let Some(first) = errors.first() else {
+        return Ok(());
+    };
+    if errors.len() >= parts {
+        let error = errors
+            .iter()
+            .find(|error| error.kind != ErrorKind::Other)
+            .unwrap_or(first);
+        return Err(Error::new(error.kind, error.message.clone())
+            .reported()
+            .into());
+    }
+    if fail_on_partial {
+        return Err(Error::new(
+            ErrorKind::PartialData,
+            format!(
+                "Incomplete output: {} of {} parts could not be collected",
+                errors.len(),
+                parts
+            ),
+        )
+        .into());
+    }
     Ok(())
 }
 
-fn main() -> Result<()> {
+/// Runs ls_net and exits with the code of the error kind if it fails.
+///
+/// Errors that have not been reported yet are printed once to stderr. With
+/// `--format json`, a failure that left no other output is also printed to
+/// stdout as an object with an `errors` array, the same as the `errors` of
+/// `--format json` without a subcommand.
+fn main() {
     let args = Args::parse();
-    run(&args.protocol, args.ip)
+    if let Err(error) = run(&args) {
+        let report = ErrorReport::from(&error);
+        if !Error::is_reported(&error) {
+            if args.format == "json" && report.kind != ErrorKind::PartialData {
+                let _ = print_json(&serde_json::json!({ "errors": [&report] }));
+            }
+            eprintln!("Error: {:#}", error);
+        }
+        std::process::exit(report.kind.exit_code());
+    }
 }
 
 #[cfg(test)]
@@ -76,20 +831,23 @@ mod tests {
     use super::*;
 
     #[test]
-    fn run_v4() {
-        let result = run("ipv4", false);
-        assert!(result.is_ok());
-    }
+    fn test_check_partial() {
+        let report = ErrorReport::from(&anyhow::Error::new(Error::new(
+            ErrorKind::NoInterfaces,
+            "No network interfaces found.",
+        )));
+        assert!(check_partial(Vec::new(), 3, true).is_ok());
+        assert!(check_partial(vec![report.clone()], 3, false).is_ok());
 
-    #[test]
-    fn run_v6() {
-        let result = run("ipv6", false);
-        assert!(result.is_ok());
-    }
+        let error = check_partial(vec![report.clone()], 3, true).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::PartialData);
+        assert_eq!(
+            error.to_string(),
+            "Incomplete output: 1 of 3 parts could not be collected"
+        );
 
-    #[test]
-    fn run_all() {
-        let result = run("all", false);
-        assert!(result.is_ok());
+        let main_ip = ErrorReport::from(&anyhow::anyhow!("Error getting IP address"));
+        let error = check_partial(vec![main_ip, report.clone(), report], 3, false).unwrap_err();
+        assert_eq!(ErrorKind::of(&error).exit_code(), 3);
     }
 }
@@ -0,0 +1,632 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::diff::{AddressChange, RouteChange, SnapshotDiff};
+use crate::ip_interfaces::InterfaceAddr;
+use crate::neighbors::Neighbor;
+use crate::render::{Element, Output, Style};
+use crate::route_table::route_table::RouteCollector;
+use crate::route_table::{IpVersion, RouteEntry};
+use crate::snapshot::Snapshot;
+
+/// A change of the network state reported by `ls_net monitor`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RouteAdded { route: Box<RouteEntry> },
+    RouteRemoved { route: Box<RouteEntry> },
+    AddressAdded { address: InterfaceAddr },
+    AddressRemoved { address: InterfaceAddr },
+    LinkUp { name: String },
+    LinkDown { name: String },
+    LinkRemoved { name: String },
+    Neighbor { neighbor: Neighbor },
+    NeighborRemoved { neighbor: Neighbor },
+}
+
+/// An event together with the time it was observed.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorEvent {
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Event {
+    /// Returns `true` if the event concerns the given protocol ("ipv4",
+    /// "ipv6", or "all"). Link events concern every protocol.
+    pub fn matches_protocol(&self, protocol: &str) -> bool {
+        let is_ipv4 = match self {
+            Event::RouteAdded { route } | Event::RouteRemoved { route } => {
+                route.ip_version == IpVersion::IPv4
+            }
+            Event::AddressAdded { address } | Event::AddressRemoved { address } => {
+                address.is_ipv4()
+            }
+            Event::Neighbor { neighbor } | Event::NeighborRemoved { neighbor } => {
+                neighbor.ip.is_ipv4()
+            }
+            Event::LinkUp { .. } | Event::LinkDown { .. } | Event::LinkRemoved { .. } => {
+                return true;
+            }
+        };
+        match protocol {
+            "ipv4" => is_ipv4,
+            "ipv6" => !is_ipv4,
+            _ => true,
+        }
+    }
+}
+
+impl MonitorEvent {
+    /// Creates an event observed now.
+    pub fn now(event: Event) -> Self {
+        Self {
+            timestamp: format_timestamp(SystemTime::now()),
+            event,
+        }
+    }
+
+    /// Renders the event as a single line styled with `style`.
+    pub fn to_text(&self, style: &Style) -> String {
+        let address = |address: &InterfaceAddr| {
+            format!("{}/{} dev {}", address.ip, address.prefix_len, address.name)
+        };
+        let neighbor = |neighbor: &Neighbor| {
+            format!(
+                "{} dev {} lladdr {} {}",
+                neighbor.ip,
+                neighbor.iface,
+                neighbor.mac.as_deref().unwrap_or("-"),
+                neighbor.state
+            )
+        };
+
+        let (element, line) = match &self.event {
+            Event::RouteAdded { route } => {
+                (Element::Added, format!("+ route    {}", route.describe()))
+            }
+            Event::RouteRemoved { route } => {
+                (Element::Removed, format!("- route    {}", route.describe()))
+            }
+            Event::AddressAdded { address: a } => {
+                (Element::Added, format!("+ address  {}", address(a)))
+            }
+            Event::AddressRemoved { address: a } => {
+                (Element::Removed, format!("- address  {}", address(a)))
+            }
+            Event::LinkUp { name } => (Element::Added, format!("  link     {} up", name)),
+            Event::LinkDown { name } => (Element::Down, format!("  link     {} down", name)),
+            Event::LinkRemoved { name } => (Element::Removed, format!("- link     {}", name)),
+            Event::Neighbor { neighbor: n } => {
+                (Element::Warning, format!("  neighbor {}", neighbor(n)))
+            }
+            Event::NeighborRemoved { neighbor: n } => {
+                (Element::Removed, format!("- neighbor {}", neighbor(n)))
+            }
+        };
+        format!(
+            "{} {}",
+            style.paint(Element::Header, &self.timestamp),
+            style.paint(element, &line)
+        )
+    }
+}
+
+/// Formats a point in time as an RFC 3339 UTC timestamp with milliseconds,
+/// e.g. `2024-05-01T12:30:00.250Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = elapsed.as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Converts the differences between two snapshots into monitor events.
+///
+/// A changed route is reported as the removal of the old route followed by
+/// the addition of the new one.
+pub fn events_from_diff(diff: &SnapshotDiff) -> Vec<Event> {
+    let mut events = Vec::new();
+    for change in &diff.routes {
+        match change {
+            RouteChange::Added { route } => events.push(Event::RouteAdded {
+                route: Box::new(route.clone()),
+            }),
+            RouteChange::Removed { route } => events.push(Event::RouteRemoved {
+                route: Box::new(route.clone()),
+            }),
+            RouteChange::Changed { old, new } => {
+                events.push(Event::RouteRemoved { route: old.clone() });
+                events.push(Event::RouteAdded { route: new.clone() });
+            }
+        }
+    }
+    for change in &diff.addresses {
+        match change {
+            AddressChange::Added { address } => events.push(Event::AddressAdded {
+                address: address.clone(),
+            }),
+            AddressChange::Removed { address } => events.push(Event::AddressRemoved {
+                address: address.clone(),
+            }),
+        }
+    }
+    events
+}
+
+/// Watches the network state by collecting a snapshot, with the route table
+/// from `routes`, every `interval` and reporting the differences to the
+/// previous one.
+///
+/// This is the fallback for platforms without netlink. Link and neighbor
+/// changes are not detected. `emit` is called for every event and stops the
+/// monitor by returning `false`.
+///
+/// # Errors
+///
+/// Returns an error if a snapshot cannot be collected.
+pub fn poll(
+    interval: Duration,
+    routes: &RouteCollector,
+    mut emit: impl FnMut(MonitorEvent) -> bool,
+) -> Result<()> {
+    let mut previous = Snapshot::collect(routes)?;
+    loop {
+        std::thread::sleep(interval);
+        let current = Snapshot::collect(routes)?;
+        for event in events_from_diff(&SnapshotDiff::between(&previous, &current)) {
+            if !emit(MonitorEvent::now(event)) {
+                return Ok(());
+            }
+        }
+        previous = current;
+    }
+}
+
+/// Streams network state changes to `output` until interrupted, until
+/// `count` events have been written, or until the output is closed.
+///
+/// On Linux the changes are received from netlink notifications unless
+/// `use_poll` is set; other platforms compare snapshots every `interval`,
+/// with the route table from `routes`.
+/// If the output format is "json", each event is written as one JSON object
+/// per line.
+///
+/// # Errors
+///
+/// Returns an error if the notifications cannot be subscribed to or the
+/// network state cannot be collected.
+pub fn run_monitor(
+    output: &mut Output,
+    protocol: &str,
+    use_poll: bool,
+    interval: Duration,
+    count: Option<usize>,
+    routes: &RouteCollector,
+) -> Result<()> {
+    let mut printed = 0;
+    let emit = |event: MonitorEvent| {
+        if !event.event.matches_protocol(protocol) {
+            return true;
+        }
+        let written = if output.format == "json" {
+            match serde_json::to_string(&event) {
+                Ok(line) => writeln!(output, "{}", line),
+                Err(e) => {
+                    eprintln!("Failed to encode event: {}", e);
+                    Ok(())
+                }
+            }
+        } else {
+            let line = event.to_text(&output.style);
+            writeln!(output, "{}", line)
+        };
+        if written.is_err() {
+            return false;
+        }
+        printed += 1;
+        count.is_none_or(|count| printed < count)
+    };
+
+    if count == Some(0) {
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    if !use_poll {
+        return linux::listen(protocol, emit);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = use_poll;
+
+    poll(interval, routes, emit)
+}
+
+#[cfg(target_os = "linux")]
+pub mod linux {
+    use anyhow::Result;
+    use std::collections::HashMap;
+
+    use super::{Event, MonitorEvent};
+    use crate::ip_interfaces::InterfaceAddr;
+    use crate::neighbors::netlink::parse_neighbor_message;
+    use crate::netlink::{
+        AF_INET, AF_INET6, NetlinkSocket, attr_ip, attr_string, if_name, parse_attributes, read_u32,
+    };
+    use crate::prefix::Prefix;
+    use crate::probe::RealProbe;
+    use crate::route_table::netlink::parse_route_message;
+    use crate::route_table::rules::read_table_names;
+
+    const RTMGRP_LINK: u32 = 0x1;
+    const RTMGRP_NEIGH: u32 = 0x4;
+    const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+    const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+    const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+    const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+
+    const RTM_NEWLINK: u16 = 16;
+    const RTM_DELLINK: u16 = 17;
+    const RTM_GETLINK: u16 = 18;
+    const RTM_NEWADDR: u16 = 20;
+    const RTM_DELADDR: u16 = 21;
+    const RTM_NEWROUTE: u16 = 24;
+    const RTM_DELROUTE: u16 = 25;
+    const RTM_NEWNEIGH: u16 = 28;
+    const RTM_DELNEIGH: u16 = 29;
+
+    /// Size of `struct ifinfomsg`.
+    const IFINFOMSG_LEN: usize = 16;
+    /// Size of `struct ifaddrmsg`.
+    const IFADDRMSG_LEN: usize = 8;
+
+    const IFLA_IFNAME: u16 = 3;
+    const IFA_ADDRESS: u16 = 1;
+    const IFA_LOCAL: u16 = 2;
+    const IFA_LABEL: u16 = 3;
+
+    const IFF_UP: u32 = 0x1;
+    const IFF_RUNNING: u32 = 0x40;
+
+    /// The state of a network interface from an `RTM_NEWLINK` message.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Link {
+        pub index: u32,
+        pub name: String,
+        /// The interface is administratively up and has a carrier.
+        pub up: bool,
+    }
+
+    /// Converts the payload of an `RTM_NEWLINK` or `RTM_DELLINK` message.
+    pub fn parse_link_message(payload: &[u8]) -> Option<Link> {
+        if payload.len() < IFINFOMSG_LEN {
+            return None;
+        }
+        let index = read_u32(&payload[4..]);
+        let flags = read_u32(&payload[8..]);
+        let name = parse_attributes(&payload[IFINFOMSG_LEN..])
+            .into_iter()
+            .find(|(attr_type, _)| *attr_type == IFLA_IFNAME)
+            .map(|(_, value)| attr_string(value))
+            .unwrap_or_else(|| index.to_string());
+
+        Some(Link {
+            index,
+            name,
+            up: flags & IFF_UP != 0 && flags & IFF_RUNNING != 0,
+        })
+    }
+
+    /// Converts the payload of an `RTM_NEWADDR` or `RTM_DELADDR` message.
+    /// Interface indexes are resolved with `if_name`.
+    pub fn parse_address_message(
+        payload: &[u8],
+        if_name: impl Fn(u32) -> Option<String>,
+    ) -> Option<InterfaceAddr> {
+        if payload.len() < IFADDRMSG_LEN {
+            return None;
+        }
+        let family = payload[0];
+        let prefix_len = payload[1];
+        let index = read_u32(&payload[4..]);
+        if family != AF_INET && family != AF_INET6 {
+            return None;
+        }
+
+        let mut address = None;
+        let mut local = None;
+        let mut label = None;
+        for (attr_type, value) in parse_attributes(&payload[IFADDRMSG_LEN..]) {
+            match attr_type {
+                IFA_ADDRESS => address = attr_ip(family, value),
+                IFA_LOCAL => local = attr_ip(family, value),
+                IFA_LABEL => label = Some(attr_string(value)),
+                _ => {}
+            }
+        }
+
+        // For point-to-point links IFA_ADDRESS is the peer; IFA_LOCAL is ours
+        let ip = local.or(address)?;
+        Some(InterfaceAddr {
+            name: label
+                .or_else(|| if_name(index))
+                .unwrap_or_else(|| index.to_string()),
+            ip,
+            netmask: Prefix::new(ip, prefix_len).netmask(),
+            prefix_len,
+        })
+    }
+
+    /// Tracks the link states to turn `RTM_NEWLINK` notifications, which the
+    /// kernel sends for any attribute change, into up/down events.
+    #[derive(Debug, Default)]
+    pub struct EventDecoder {
+        links: HashMap<u32, bool>,
+        table_names: HashMap<u32, String>,
+    }
+
+    impl EventDecoder {
+        pub fn new(links: &[Link], table_names: HashMap<u32, String>) -> Self {
+            Self {
+                links: links.iter().map(|link| (link.index, link.up)).collect(),
+                table_names,
+            }
+        }
+
+        /// Converts a notification into events. Interface indexes are
+        /// resolved with `if_name`.
+        pub fn decode(
+            &mut self,
+            msg_type: u16,
+            payload: &[u8],
+            if_name: impl Fn(u32) -> Option<String>,
+        ) -> Vec<Event> {
+            match msg_type {
+                RTM_NEWROUTE | RTM_DELROUTE => {
+                    parse_route_message(payload, &self.table_names, if_name)
+                        .into_iter()
+                        .map(|route| match msg_type {
+                            RTM_NEWROUTE => Event::RouteAdded {
+                                route: Box::new(route),
+                            },
+                            _ => Event::RouteRemoved {
+                                route: Box::new(route),
+                            },
+                        })
+                        .collect()
+                }
+                RTM_NEWADDR => parse_address_message(payload, if_name)
+                    .map(|address| Event::AddressAdded { address })
+                    .into_iter()
+                    .collect(),
+                RTM_DELADDR => parse_address_message(payload, if_name)
+                    .map(|address| Event::AddressRemoved { address })
+                    .into_iter()
+                    .collect(),
+                RTM_NEWLINK => {
+                    let Some(link) = parse_link_message(payload) else {
+                        return Vec::new();
+                    };
+                    if self.links.insert(link.index, link.up) == Some(link.up) {
+                        return Vec::new();
+                    }
+                    match link.up {
+                        true => vec![Event::LinkUp { name: link.name }],
+                        false => vec![Event::LinkDown { name: link.name }],
+                    }
+                }
+                RTM_DELLINK => {
+                    let Some(link) = parse_link_message(payload) else {
+                        return Vec::new();
+                    };
+                    self.links.remove(&link.index);
+                    vec![Event::LinkRemoved { name: link.name }]
+                }
+                RTM_NEWNEIGH => parse_neighbor_message(payload, if_name)
+                    .map(|neighbor| Event::Neighbor { neighbor })
+                    .into_iter()
+                    .collect(),
+                RTM_DELNEIGH => parse_neighbor_message(payload, if_name)
+                    .map(|neighbor| Event::NeighborRemoved { neighbor })
+                    .into_iter()
+                    .collect(),
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    /// Returns the multicast groups to subscribe to for the given protocol.
+    fn groups(protocol: &str) -> u32 {
+        let ipv4 = RTMGRP_IPV4_ROUTE | RTMGRP_IPV4_IFADDR;
+        let ipv6 = RTMGRP_IPV6_ROUTE | RTMGRP_IPV6_IFADDR;
+        RTMGRP_LINK
+            | RTMGRP_NEIGH
+            | match protocol {
+                "ipv4" => ipv4,
+                "ipv6" => ipv6,
+                _ => ipv4 | ipv6,
+            }
+    }
+
+    /// Receives netlink notifications and passes the decoded events to
+    /// `emit` until it returns `false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the netlink socket cannot be opened or read.
+    pub fn listen(protocol: &str, mut emit: impl FnMut(MonitorEvent) -> bool) -> Result<()> {
+        let mut socket = NetlinkSocket::subscribe(groups(protocol))?;
+
+        // Seed the link states so only actual up/down changes are reported
+        let links: Vec<Link> = NetlinkSocket::connect()?
+            .dump(RTM_GETLINK, &[0; IFINFOMSG_LEN])?
+            .iter()
+            .filter(|message| message.msg_type == RTM_NEWLINK)
+            .filter_map(|message| parse_link_message(&message.payload))
+            .collect();
+        let mut decoder = EventDecoder::new(&links, read_table_names(&RealProbe));
+
+        loop {
+            let messages = match socket.recv() {
+                Ok(messages) => messages,
+                Err(e)
+                    if e.downcast_ref::<std::io::Error>()
+                        .and_then(|e| e.raw_os_error())
+                        == Some(libc::ENOBUFS) =>
+                {
+                    eprintln!("Warning: events were lost because the receive buffer overflowed");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for message in messages {
+                for event in decoder.decode(message.msg_type, &message.payload, if_name) {
+                    if !emit(MonitorEvent::now(event)) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn attribute(attr_type: u16, value: &[u8]) -> Vec<u8> {
+            let mut buf = Vec::new();
+            buf.extend_from_slice(&((4 + value.len()) as u16).to_ne_bytes());
+            buf.extend_from_slice(&attr_type.to_ne_bytes());
+            buf.extend_from_slice(value);
+            buf.resize((buf.len() + 3) & !3, 0);
+            buf
+        }
+
+        fn link_message(index: u32, flags: u32, name: &str) -> Vec<u8> {
+            let mut payload = vec![0u8; 4];
+            payload.extend_from_slice(&index.to_ne_bytes());
+            payload.extend_from_slice(&flags.to_ne_bytes());
+            payload.extend_from_slice(&0u32.to_ne_bytes());
+            payload.extend(attribute(IFLA_IFNAME, format!("{}\0", name).as_bytes()));
+            payload
+        }
+
+        #[test]
+        fn test_parse_address_message() {
+            let mut payload = vec![AF_INET, 24, 0, 0];
+            payload.extend_from_slice(&3u32.to_ne_bytes());
+            payload.extend(attribute(IFA_ADDRESS, &[10, 8, 0, 1]));
+            payload.extend(attribute(IFA_LOCAL, &[10, 8, 0, 2]));
+
+            let address = parse_address_message(&payload, |_| Some("wg0".to_string())).unwrap();
+            assert_eq!(address.name, "wg0");
+            assert_eq!(address.ip.to_string(), "10.8.0.2");
+            assert_eq!(address.netmask.to_string(), "255.255.255.0");
+            assert_eq!(address.prefix_len, 24);
+        }
+
+        #[test]
+        fn test_decoder_reports_link_state_changes_only() {
+            let up = IFF_UP | IFF_RUNNING;
+            let initial = parse_link_message(&link_message(2, up, "eth0")).unwrap();
+            let mut decoder = EventDecoder::new(&[initial], HashMap::new());
+            let no_name = |_| None;
+
+            let events = decoder.decode(RTM_NEWLINK, &link_message(2, up, "eth0"), no_name);
+            assert!(events.is_empty());
+
+            let events = decoder.decode(RTM_NEWLINK, &link_message(2, IFF_UP, "eth0"), no_name);
+            assert!(matches!(&events[..], [Event::LinkDown { name }] if name == "eth0"));
+
+            let events = decoder.decode(RTM_NEWLINK, &link_message(5, up, "wg0"), no_name);
+            assert!(matches!(&events[..], [Event::LinkUp { name }] if name == "wg0"));
+
+            let events = decoder.decode(RTM_DELLINK, &link_message(5, 0, "wg0"), no_name);
+            assert!(matches!(&events[..], [Event::LinkRemoved { name }] if name == "wg0"));
+        }
+
+        #[test]
+        #[ignore = "integration test, needs a netlink socket on the live kernel"]
+        fn test_subscribe() {
+            assert!(NetlinkSocket::subscribe(groups("all")).is_ok());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_250);
+        assert_eq!(format_timestamp(time), "2024-02-29T12:34:56.250Z");
+    }
+
+    #[test]
+    fn test_events_from_diff() {
+        let old = RouteEntry {
+            destination: "default".to_string(),
+            gateway: "192.168.1.1".to_string(),
+            iface: "eth0".to_string(),
+            ..Default::default()
+        };
+        let new = RouteEntry {
+            gateway: "10.8.0.1".to_string(),
+            iface: "wg0".to_string(),
+            ..old.clone()
+        };
+        let diff = SnapshotDiff {
+            routes: vec![RouteChange::Changed {
+                old: Box::new(old),
+                new: Box::new(new),
+            }],
+            ..Default::default()
+        };
+
+        let events = events_from_diff(&diff);
+        assert!(matches!(
+            &events[..],
+            [Event::RouteRemoved { route: old }, Event::RouteAdded { route: new }]
+                if old.iface == "eth0" && new.iface == "wg0"
+        ));
+        assert!(events[0].matches_protocol("ipv4"));
+        assert!(!events[0].matches_protocol("ipv6"));
+
+        let event = MonitorEvent {
+            timestamp: format_timestamp(UNIX_EPOCH),
+            event: events[1].clone(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "route_added");
+        assert_eq!(json["timestamp"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(json["route"]["iface"], "wg0");
+    }
+}
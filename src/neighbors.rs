@@ -0,0 +1,266 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::probe::{RealProbe, SystemProbe};
+
+/// An entry of the neighbor table (ARP for IPv4, NDP for IPv6).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    pub ip: IpAddr,
+    /// The link-layer address, if it has been resolved.
+    pub mac: Option<String>,
+    pub iface: String,
+    /// The resolution state, e.g. `reachable`, `stale` or `failed`.
+    pub state: String,
+}
+
+/// Gets the neighbor table of the host, sorted by interface and address.
+///
+/// On Linux the table is read over netlink, falling back to `arp -an` (IPv4
+/// only) if netlink is not available. Other platforms use `arp -an`.
+/// Entries without address resolution (`noarp`, e.g. multicast) are left out.
+///
+/// # Errors
+///
+/// Returns an error if neither netlink nor the `arp` command is available.
+pub fn get_neighbors() -> Result<Vec<Neighbor>> {
+    #[cfg(target_os = "linux")]
+    let netlink_neighbors = netlink::get_netlink_neighbors().ok();
+    #[cfg(not(target_os = "linux"))]
+    let netlink_neighbors: Option<Vec<Neighbor>> = None;
+
+    let mut neighbors = match netlink_neighbors {
+        Some(neighbors) => neighbors,
+        None => get_arp_neighbors(&RealProbe)?,
+    };
+
+    neighbors.retain(|neighbor| neighbor.state != "noarp" && neighbor.state != "none");
+    neighbors.sort_by(|a, b| a.iface.cmp(&b.iface).then(a.ip.cmp(&b.ip)));
+    Ok(neighbors)
+}
+
+/// Gets the neighbor table from `arp -an`, run by `probe`.
+///
+/// # Errors
+///
+/// Returns an error if the `arp` command cannot be run or fails.
+pub fn get_arp_neighbors(probe: &dyn SystemProbe) -> Result<Vec<Neighbor>> {
+    let output = probe.run("arp", &["-an"])?;
+    if !output.success {
+        return Err(anyhow!("Failed to execute arp command"));
+    }
+    Ok(parse_arp_output(&output.stdout))
+}
+
+/// Parses the output of `arp -an`, in both the BSD/macOS format
+/// (`? (192.168.1.1) at a0:b1:c2:d3:e4:f5 on en0 ifscope [ethernet]`) and the
+/// Linux net-tools format (`? (192.168.1.1) at a0:b1:c2:d3:e4:f5 [ether] on
+/// eth0`).
+///
+/// Lines that are not neighbor entries are skipped.
+pub fn parse_arp_output(output: &str) -> Vec<Neighbor> {
+    output.lines().filter_map(parse_arp_line).collect()
+}
+
+fn parse_arp_line(line: &str) -> Option<Neighbor> {
+    let start = line.find('(')?;
+    let end = start + line[start..].find(')')?;
+    let ip: IpAddr = line[start + 1..end].parse().ok()?;
+
+    let parts: Vec<&str> = line[end + 1..].split_whitespace().collect();
+    let after = |keyword: &str| {
+        parts
+            .iter()
+            .position(|part| *part == keyword)
+            .and_then(|i| parts.get(i + 1))
+    };
+
+    let mac = after("at")
+        .filter(|mac| mac.contains(':'))
+        .map(|mac| mac.to_lowercase());
+    let state = if parts
+        .iter()
+        .any(|part| *part == "permanent" || *part == "PERM")
+    {
+        "permanent"
+    } else if mac.is_some() {
+        "reachable"
+    } else {
+        "incomplete"
+    };
+
+    Some(Neighbor {
+        ip,
+        mac,
+        iface: after("on")?.to_string(),
+        state: state.to_string(),
+    })
+}
+
+/// Formats a link-layer address as colon-separated hex bytes.
+pub fn format_mac(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(target_os = "linux")]
+pub mod netlink {
+    use anyhow::Result;
+
+    use super::{Neighbor, format_mac};
+    use crate::netlink::{NetlinkSocket, attr_ip, if_name, parse_attributes, read_u32};
+
+    const RTM_NEWNEIGH: u16 = 28;
+    const RTM_GETNEIGH: u16 = 30;
+
+    /// Size of `struct ndmsg`.
+    const NDMSG_LEN: usize = 12;
+
+    const NDA_DST: u16 = 1;
+    const NDA_LLADDR: u16 = 2;
+
+    /// Reads the IPv4 and IPv6 neighbor tables over a `NETLINK_ROUTE`
+    /// socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the netlink socket cannot be opened or the dump
+    /// request fails.
+    pub fn get_netlink_neighbors() -> Result<Vec<Neighbor>> {
+        let mut socket = NetlinkSocket::connect()?;
+        Ok(socket
+            .dump(RTM_GETNEIGH, &[0; NDMSG_LEN])?
+            .iter()
+            .filter(|message| message.msg_type == RTM_NEWNEIGH)
+            .filter_map(|message| parse_neighbor_message(&message.payload, if_name))
+            .collect())
+    }
+
+    /// Returns the name of a `NUD_*` neighbor state, as printed by
+    /// `ip neigh`.
+    pub fn state_name(state: u16) -> String {
+        let name = match state {
+            0x00 => "none",
+            0x01 => "incomplete",
+            0x02 => "reachable",
+            0x04 => "stale",
+            0x08 => "delay",
+            0x10 => "probe",
+            0x20 => "failed",
+            0x40 => "noarp",
+            0x80 => "permanent",
+            _ => return format!("{:#x}", state),
+        };
+        name.to_string()
+    }
+
+    /// Converts the payload of an `RTM_NEWNEIGH` or `RTM_DELNEIGH` message
+    /// into a neighbor entry. Interface indexes are resolved with `if_name`.
+    pub fn parse_neighbor_message(
+        payload: &[u8],
+        if_name: impl Fn(u32) -> Option<String>,
+    ) -> Option<Neighbor> {
+        if payload.len() < NDMSG_LEN {
+            return None;
+        }
+        let family = payload[0];
+        let index = read_u32(&payload[4..]);
+        let state = u16::from_ne_bytes([payload[8], payload[9]]);
+
+        let mut ip = None;
+        let mut mac = None;
+        for (attr_type, value) in parse_attributes(&payload[NDMSG_LEN..]) {
+            match attr_type {
+                NDA_DST => ip = attr_ip(family, value),
+                NDA_LLADDR if !value.is_empty() => mac = Some(format_mac(value)),
+                _ => {}
+            }
+        }
+
+        Some(Neighbor {
+            ip: ip?,
+            mac,
+            iface: if_name(index).unwrap_or_else(|| index.to_string()),
+            state: state_name(state),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::netlink::AF_INET;
+
+        #[test]
+        fn test_parse_neighbor_message() {
+            let mut payload = vec![AF_INET, 0, 0, 0];
+            payload.extend_from_slice(&2u32.to_ne_bytes());
+            payload.extend_from_slice(&0x02u16.to_ne_bytes());
+            payload.extend_from_slice(&[0, 1]);
+            payload.extend_from_slice(&8u16.to_ne_bytes());
+            payload.extend_from_slice(&NDA_DST.to_ne_bytes());
+            payload.extend_from_slice(&[192, 0, 2, 1]);
+            payload.extend_from_slice(&10u16.to_ne_bytes());
+            payload.extend_from_slice(&NDA_LLADDR.to_ne_bytes());
+            payload.extend_from_slice(&[0x52, 0x54, 0x00, 0x12, 0x34, 0x56, 0, 0]);
+
+            let neighbor =
+                parse_neighbor_message(&payload, |index| Some(format!("eth{}", index - 2)))
+                    .unwrap();
+            assert_eq!(neighbor.ip.to_string(), "192.0.2.1");
+            assert_eq!(neighbor.mac.as_deref(), Some("52:54:00:12:34:56"));
+            assert_eq!(neighbor.iface, "eth0");
+            assert_eq!(neighbor.state, "reachable");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_arp_output() {
+        let macos = "? (192.168.1.1) at a0:b1:c2:d3:e4:f5 on en0 ifscope [ethernet]\n\
+                     ? (192.168.1.23) at (incomplete) on en0 ifscope [ethernet]\n\
+                     ? (224.0.0.251) at 1:0:5e:0:0:fb on en0 ifscope permanent [ethernet]\n";
+        let neighbors = parse_arp_output(macos);
+        assert_eq!(neighbors.len(), 3);
+        assert_eq!(neighbors[0].mac.as_deref(), Some("a0:b1:c2:d3:e4:f5"));
+        assert_eq!(neighbors[0].iface, "en0");
+        assert_eq!(neighbors[0].state, "reachable");
+        assert_eq!(neighbors[1].mac, None);
+        assert_eq!(neighbors[1].state, "incomplete");
+        assert_eq!(neighbors[2].state, "permanent");
+
+        let linux = "? (192.0.2.1) at 52:54:00:12:34:56 [ether] on eth0\n\
+                     ? (192.0.2.9) at <incomplete> on eth0\n";
+        let neighbors = parse_arp_output(linux);
+        assert_eq!(neighbors[0].ip.to_string(), "192.0.2.1");
+        assert_eq!(neighbors[0].iface, "eth0");
+        assert_eq!(neighbors[1].state, "incomplete");
+    }
+
+    #[test]
+    fn test_get_neighbors() {
+        assert!(get_neighbors().is_ok());
+    }
+
+    #[test]
+    fn test_get_arp_neighbors_golden() {
+        use crate::golden::{assert_golden_json, fixture};
+        use crate::probe::FakeProbe;
+
+        for (name, os) in [("linux-net-tools", "linux"), ("macos-14", "macos")] {
+            let probe =
+                FakeProbe::new(os).command("arp -an", &fixture(&format!("arp/{}.txt", name)));
+            assert_golden_json(
+                &format!("neighbors/{}.json", name),
+                &get_arp_neighbors(&probe).unwrap(),
+            );
+        }
+    }
+}
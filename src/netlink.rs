@@ -0,0 +1,312 @@
+use anyhow::{Result, anyhow};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const AF_INET: u8 = 2;
+pub const AF_INET6: u8 = 10;
+
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+
+/// A single netlink message with its header stripped.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub msg_type: u16,
+    pub payload: Vec<u8>,
+}
+
+/// Splits a buffer received from a netlink socket into messages.
+///
+/// Truncated trailing data is ignored.
+pub fn parse_messages(buf: &[u8]) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+
+    while offset + NLMSG_HDRLEN <= buf.len() {
+        let len = read_u32(&buf[offset..]) as usize;
+        if len < NLMSG_HDRLEN || offset + len > buf.len() {
+            break;
+        }
+        let msg_type = u16::from_ne_bytes([buf[offset + 4], buf[offset + 5]]);
+        messages.push(Message {
+            msg_type,
+            payload: buf[offset + NLMSG_HDRLEN..offset + len].to_vec(),
+        });
+        offset += align(len);
+    }
+
+    messages
+}
+
+/// Splits a buffer of `rtattr` structures into `(type, value)` pairs.
+///
+/// The nested flag of the attribute type is cleared.
+pub fn parse_attributes(buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attributes = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= buf.len() {
+        let len = u16::from_ne_bytes([buf[offset], buf[offset + 1]]) as usize;
+        let attr_type = u16::from_ne_bytes([buf[offset + 2], buf[offset + 3]]) & 0x3fff;
+        if len < 4 || offset + len > buf.len() {
+            break;
+        }
+        attributes.push((attr_type, &buf[offset + 4..offset + len]));
+        offset += align(len);
+    }
+
+    attributes
+}
+
+/// Reads a native-endian `u32` from the start of `buf`.
+pub fn read_u32(buf: &[u8]) -> u32 {
+    u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
+
+/// Reads a native-endian `u32` attribute value.
+pub fn attr_u32(value: &[u8]) -> Option<u32> {
+    (value.len() >= 4).then(|| read_u32(value))
+}
+
+/// Reads a NUL-terminated string attribute value.
+pub fn attr_string(value: &[u8]) -> String {
+    let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+    String::from_utf8_lossy(&value[..end]).to_string()
+}
+
+/// Reads an address attribute value of the given address family.
+pub fn attr_ip(family: u8, value: &[u8]) -> Option<IpAddr> {
+    match family {
+        AF_INET if value.len() >= 4 => {
+            let octets: [u8; 4] = value[..4].try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        AF_INET6 if value.len() >= 16 => {
+            let octets: [u8; 16] = value[..16].try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// A `NETLINK_ROUTE` socket.
+pub struct NetlinkSocket {
+    fd: std::os::fd::OwnedFd,
+    seq: u32,
+}
+
+impl NetlinkSocket {
+    /// Opens a `NETLINK_ROUTE` socket in the current network namespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be created or bound.
+    pub fn connect() -> Result<Self> {
+        Self::open(0)
+    }
+
+    /// Opens a `NETLINK_ROUTE` socket that receives the notifications of the
+    /// given multicast groups (a mask of `RTMGRP_*` values).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be created or bound.
+    pub fn subscribe(groups: u32) -> Result<Self> {
+        Self::open(groups)
+    }
+
+    fn open(groups: u32) -> Result<Self> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+
+        // SAFETY: plain socket syscall; the descriptor is owned right away.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: `fd` is a freshly created descriptor nobody else owns.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let socket = Self { fd, seq: 0 };
+        socket.bind(groups)?;
+        Ok(socket)
+    }
+
+    fn bind(&self, groups: u32) -> Result<()> {
+        use std::os::fd::AsRawFd;
+
+        // SAFETY: `sockaddr_nl` is plain old data, zero is a valid value.
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = groups;
+
+        // SAFETY: `addr` is a valid `sockaddr_nl` of the given size.
+        let ret = unsafe {
+            libc::bind(
+                self.fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Sends a dump request of `msg_type` with the given family header as
+    /// payload and collects all messages of the reply.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request cannot be sent or the kernel answers
+    /// with an error.
+    pub fn dump(&mut self, msg_type: u16, payload: &[u8]) -> Result<Vec<Message>> {
+        self.seq += 1;
+        let len = NLMSG_HDRLEN + payload.len();
+        let mut request = Vec::with_capacity(align(len));
+        request.extend_from_slice(&(len as u32).to_ne_bytes());
+        request.extend_from_slice(&msg_type.to_ne_bytes());
+        request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+        request.extend_from_slice(&self.seq.to_ne_bytes());
+        request.extend_from_slice(&0u32.to_ne_bytes());
+        request.extend_from_slice(payload);
+        request.resize(align(len), 0);
+
+        self.send(&request)?;
+
+        let mut messages = Vec::new();
+        loop {
+            for message in self.recv()? {
+                match message.msg_type {
+                    NLMSG_DONE => return Ok(messages),
+                    NLMSG_ERROR => {
+                        let errno = message.payload.get(..4).map(read_u32).unwrap_or(0) as i32;
+                        if errno == 0 {
+                            continue;
+                        }
+                        let error = std::io::Error::from_raw_os_error(-errno);
+                        return Err(anyhow!("Netlink request failed: {}", error));
+                    }
+                    _ => messages.push(message),
+                }
+            }
+        }
+    }
+
+    fn send(&self, request: &[u8]) -> Result<()> {
+        use std::os::fd::AsRawFd;
+
+        // SAFETY: `request` is a valid buffer of the given length.
+        let ret = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                request.as_ptr() as *const libc::c_void,
+                request.len(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Receives the next batch of messages from the socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the socket fails.
+    pub fn recv(&mut self) -> Result<Vec<Message>> {
+        use std::os::fd::AsRawFd;
+
+        let mut buf = vec![0u8; 64 * 1024];
+        // SAFETY: `buf` is a valid, writable buffer of the given length.
+        let len = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if len < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(parse_messages(&buf[..len as usize]))
+    }
+}
+
+/// Returns the name of the interface with the given index in the current
+/// network namespace.
+pub fn if_name(index: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    // SAFETY: `buf` has room for `IF_NAMESIZE` bytes as required.
+    let ptr = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if ptr.is_null() {
+        return None;
+    }
+    // SAFETY: on success the buffer holds a NUL-terminated name.
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+    Some(name.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(attr_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&((4 + value.len()) as u16).to_ne_bytes());
+        buf.extend_from_slice(&attr_type.to_ne_bytes());
+        buf.extend_from_slice(value);
+        buf.resize(align(buf.len()), 0);
+        buf
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let mut buf = Vec::new();
+        for (msg_type, payload) in [(24u16, vec![1u8, 2, 3]), (NLMSG_DONE, vec![0, 0, 0, 0])] {
+            let len = (NLMSG_HDRLEN + payload.len()) as u32;
+            buf.extend_from_slice(&len.to_ne_bytes());
+            buf.extend_from_slice(&msg_type.to_ne_bytes());
+            buf.extend_from_slice(&[0; 10]);
+            buf.extend_from_slice(&payload);
+            buf.resize(align(buf.len()), 0);
+        }
+
+        let messages = parse_messages(&buf);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].msg_type, 24);
+        assert_eq!(messages[0].payload, vec![1, 2, 3]);
+        assert_eq!(messages[1].msg_type, NLMSG_DONE);
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        let mut buf = attribute(1, &[10, 0, 0, 1]);
+        buf.extend(attribute(3, b"eth0\0"));
+        buf.extend(attribute(6, &100u32.to_ne_bytes()));
+
+        let attributes = parse_attributes(&buf);
+        assert_eq!(attributes.len(), 3);
+        assert_eq!(
+            attr_ip(AF_INET, attributes[0].1),
+            Some("10.0.0.1".parse().unwrap())
+        );
+        assert_eq!(attr_string(attributes[1].1), "eth0");
+        assert_eq!(attr_u32(attributes[2].1), Some(100));
+    }
+}
@@ -0,0 +1,509 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::ip_interfaces::{InterfaceAddr, get_ip_interfaces};
+use crate::render::{Element, Output};
+
+/// Directories where `ip netns add` bind-mounts named network namespaces.
+const NETNS_RUN_DIRS: [&str; 2] = ["/run/netns", "/var/run/netns"];
+
+/// A network namespace found on the host.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetnsInfo {
+    /// The name given by `ip netns`, or `pid:<pid>` for unnamed namespaces.
+    pub name: String,
+    /// The path that can be passed to `--netns` to enter the namespace.
+    pub path: PathBuf,
+    /// The inode identifying the namespace.
+    pub inode: u64,
+    /// Whether this is the namespace `ls_net` itself runs in.
+    pub current: bool,
+    /// Names of all interfaces in the namespace, including those without
+    /// addresses.
+    pub interfaces: Vec<String>,
+    /// IP addresses assigned to the interfaces of the namespace.
+    pub addresses: Vec<InterfaceAddr>,
+}
+
+/// Resolves a `--netns` argument to the path of a namespace file.
+///
+/// The argument can be a process ID (`1234` resolves to
+/// `/proc/1234/ns/net`), a path to a namespace file, or a name created with
+/// `ip netns add` (looked up in `/run/netns` and `/var/run/netns`).
+///
+/// # Errors
+///
+/// Returns an error if a named namespace does not exist.
+pub fn resolve_netns_path(spec: &str) -> Result<PathBuf> {
+    if !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(PathBuf::from(format!("/proc/{}/ns/net", spec)));
+    }
+
+    if spec.contains('/') {
+        return Ok(PathBuf::from(spec));
+    }
+
+    NETNS_RUN_DIRS
+        .iter()
+        .map(|dir| Path::new(dir).join(spec))
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow!("Network namespace '{}' not found", spec))
+}
+
+/// Moves the current thread into the network namespace given by `spec`.
+///
+/// Everything collected afterwards on this thread, including commands spawned
+/// from it, sees the interfaces and routes of that namespace.
+///
+/// # Errors
+///
+/// Returns an error if the namespace cannot be resolved or entered, e.g.
+/// because the process lacks `CAP_SYS_ADMIN`.
+pub fn enter_netns(spec: &str) -> Result<()> {
+    let path = resolve_netns_path(spec)?;
+    setns(&path).with_context(|| format!("Failed to enter network namespace {}", path.display()))
+}
+
+/// The error of switching a thread back to its original namespace, after
+/// which it keeps running in a foreign one.
+#[derive(Debug)]
+struct RestoreFailed;
+
+impl std::fmt::Display for RestoreFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Failed to restore the original network namespace")
+    }
+}
+
+/// Switches the current thread back to its original network namespace when
+/// dropped, so that a panic cannot leave it in a foreign namespace.
+struct NetnsGuard {
+    original: Option<fs::File>,
+}
+
+impl NetnsGuard {
+    /// Switches back to the original namespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RestoreFailed` error if the namespace cannot be entered.
+    fn restore(mut self) -> Result<()> {
+        match self.original.take() {
+            Some(original) => setns_file(&original).context(RestoreFailed),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for NetnsGuard {
+    fn drop(&mut self) {
+        if let Some(original) = self.original.take()
+            && let Err(error) = setns_file(&original)
+        {
+            // Running on in the foreign namespace would silently report
+            // and change the wrong network
+            eprintln!("Error: {}: {:#}", RestoreFailed, error);
+            std::process::abort();
+        }
+    }
+}
+
+/// Runs `f` inside the network namespace at `path` and switches the current
+/// thread back to its original namespace afterwards, also if `f` panics.
+///
+/// # Errors
+///
+/// Returns an error if either namespace cannot be entered, or the error
+/// returned by `f`. Failing to switch back takes precedence over the error
+/// of `f`.
+pub fn with_netns<T>(path: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let original = fs::File::open("/proc/thread-self/ns/net")
+        .context("Failed to open the current network namespace")?;
+
+    setns(path).with_context(|| format!("Failed to enter network namespace {}", path.display()))?;
+    let guard = NetnsGuard {
+        original: Some(original),
+    };
+    let result = f();
+    guard.restore()?;
+
+    result
+}
+
+/// Lists all network namespaces on the host with their interfaces.
+///
+/// Named namespaces are read from `/run/netns`, unnamed ones are discovered
+/// through the `/proc/<pid>/ns/net` links of all running processes. Every
+/// namespace is listed once, identified by its inode.
+///
+/// # Errors
+///
+/// Returns an error if the current namespace cannot be inspected, or the
+/// thread cannot switch back to it after entering another one. Namespaces
+/// that cannot be entered are listed without interfaces.
+pub fn list_netns() -> Result<Vec<NetnsInfo>> {
+    let current_inode = netns_inode(Path::new("/proc/thread-self/ns/net"))?;
+    let mut seen = HashSet::new();
+    let mut namespaces = Vec::new();
+
+    for dir in NETNS_RUN_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            if let Ok(inode) = netns_inode(&path)
+                && seen.insert(inode)
+            {
+                let name = entry.file_name().to_string_lossy().to_string();
+                namespaces.push(NetnsInfo::new(name, path, inode, current_inode));
+            }
+        }
+    }
+
+    let mut pids: Vec<u32> = fs::read_dir("/proc")?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect();
+    pids.sort_unstable();
+
+    for pid in pids {
+        let path = PathBuf::from(format!("/proc/{}/ns/net", pid));
+        if let Ok(inode) = netns_inode(&path)
+            && seen.insert(inode)
+        {
+            namespaces.push(NetnsInfo::new(
+                format!("pid:{}", pid),
+                path,
+                inode,
+                current_inode,
+            ));
+        }
+    }
+
+    for namespace in &mut namespaces {
+        let path = namespace.path.clone();
+        let collected = with_netns(&path, || Ok((interface_names()?, get_ip_interfaces()?)));
+        match collected {
+            Ok((interfaces, addresses)) => {
+                namespace.interfaces = interfaces;
+                namespace.addresses = addresses;
+            }
+            Err(error) if error.is::<RestoreFailed>() => return Err(error),
+            // A namespace without any address still has interface names
+            Err(_) => match with_netns(&path, interface_names) {
+                Ok(interfaces) => namespace.interfaces = interfaces,
+                Err(error) if error.is::<RestoreFailed>() => return Err(error),
+                Err(_) => {}
+            },
+        }
+    }
+
+    Ok(namespaces)
+}
+
+/// Writes all network namespaces with their interfaces and addresses to
+/// `output` as text, or as JSON if its format is "json".
+///
+/// The `protocol` argument can be either "ipv4", "ipv6", or "all" and filters
+/// the addresses shown for each interface.
+///
+/// # Errors
+///
+/// Returns an error if the namespaces cannot be listed or the output cannot
+/// be written.
+pub fn display_netns_list(output: &mut Output, protocol: &str) -> Result<()> {
+    let mut namespaces = list_netns()?;
+    for namespace in &mut namespaces {
+        namespace.addresses.retain(|addr| match protocol {
+            "ipv4" => addr.is_ipv4(),
+            "ipv6" => addr.is_ipv6(),
+            _ => true,
+        });
+    }
+
+    if output.format == "json" {
+        writeln!(output, "{}", serde_json::to_string_pretty(&namespaces)?)?;
+        return Ok(());
+    }
+
+    let title = output.paint(Element::Title, "Network Namespaces");
+    let separator = output.paint(
+        Element::Separator,
+        "=============================================",
+    );
+    writeln!(output, "{}", title)?;
+    writeln!(output, "{}", separator)?;
+
+    for namespace in &namespaces {
+        let marker = if namespace.current { " (current)" } else { "" };
+        let name = output.paint(Element::Name, &namespace.name);
+        let marker = output.paint(Element::Added, marker);
+        let detail = output.paint(
+            Element::Detail,
+            &format!("[{}, inode {}]", namespace.path.display(), namespace.inode),
+        );
+        writeln!(output, "{}{} {}", name, marker, detail)?;
+
+        if namespace.interfaces.is_empty() {
+            let inaccessible = output.paint(Element::Error, "(not accessible)");
+            writeln!(output, "    {}", inaccessible)?;
+        }
+
+        for interface in &namespace.interfaces {
+            let addresses: Vec<String> = namespace
+                .addresses
+                .iter()
+                .filter(|addr| &addr.name == interface)
+                .map(|addr| format!("{}/{}", addr.ip, addr.prefix_len))
+                .collect();
+            let addresses = output.paint(Element::Address, &addresses.join(", "));
+            writeln!(output, "    {:12} {}", interface, addresses)?;
+        }
+    }
+
+    writeln!(output, "{}", separator)?;
+    writeln!(output, "Found {} network namespaces", namespaces.len())?;
+
+    Ok(())
+}
+
+impl NetnsInfo {
+    fn new(name: String, path: PathBuf, inode: u64, current_inode: u64) -> Self {
+        Self {
+            name,
+            path,
+            inode,
+            current: inode == current_inode,
+            interfaces: Vec::new(),
+            addresses: Vec::new(),
+        }
+    }
+}
+
+/// Returns the inode of the namespace behind `path`, following the
+/// `/proc/<pid>/ns/net` magic link.
+#[cfg(unix)]
+fn netns_inode(path: &Path) -> Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok(fs::metadata(path)?.ino())
+}
+
+#[cfg(not(unix))]
+fn netns_inode(_path: &Path) -> Result<u64> {
+    Err(anyhow!("Network namespaces are only supported on Linux"))
+}
+
+#[cfg(target_os = "linux")]
+fn setns(path: &Path) -> Result<()> {
+    let file = fs::File::open(path)?;
+    setns_file(&file)
+}
+
+#[cfg(target_os = "linux")]
+fn setns_file(file: &fs::File) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the descriptor is owned by `file` and stays open for the call.
+    let ret = unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Returns the names of all interfaces in the current thread's namespace.
+///
+/// Unlike `get_ip_interfaces`, this includes interfaces without addresses,
+/// such as the loopback interface of a freshly created namespace.
+#[cfg(target_os = "linux")]
+fn interface_names() -> Result<Vec<String>> {
+    // SAFETY: `if_nameindex` returns an array terminated by a zeroed entry,
+    // which is released with `if_freenameindex` after copying the names.
+    unsafe {
+        let head = libc::if_nameindex();
+        if head.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        let mut names = Vec::new();
+        let mut entry = head;
+        while (*entry).if_index != 0 && !(*entry).if_name.is_null() {
+            let name = std::ffi::CStr::from_ptr((*entry).if_name);
+            names.push(name.to_string_lossy().to_string());
+            entry = entry.add(1);
+        }
+        libc::if_freenameindex(head);
+
+        names.sort();
+        Ok(names)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn setns(_path: &Path) -> Result<()> {
+    Err(anyhow!("Network namespaces are only supported on Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn setns_file(_file: &fs::File) -> Result<()> {
+    Err(anyhow!("Network namespaces are only supported on Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_names() -> Result<Vec<String>> {
+    Err(anyhow!("Network namespaces are only supported on Linux"))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    /// Starts a process in a fresh user and network namespace with
+    /// `unshare`, returning `None` if the sandbox does not allow it.
+    fn spawn_unshared() -> Option<Child> {
+        let mut child = Command::new("unshare")
+            .args(["--user", "--map-root-user", "--net", "sleep", "30"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        let own_inode = netns_inode(Path::new("/proc/self/ns/net")).ok()?;
+        let path = PathBuf::from(format!("/proc/{}/ns/net", child.id()));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return None;
+            }
+            if netns_inode(&path).is_ok_and(|inode| inode != own_inode) {
+                return Some(child);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let _ = child.kill();
+        None
+    }
+
+    #[test]
+    fn test_resolve_netns_path() {
+        assert_eq!(
+            resolve_netns_path("1234").unwrap(),
+            PathBuf::from("/proc/1234/ns/net")
+        );
+        assert_eq!(
+            resolve_netns_path("/proc/self/ns/net").unwrap(),
+            PathBuf::from("/proc/self/ns/net")
+        );
+        assert!(resolve_netns_path("ls-net-missing-namespace").is_err());
+    }
+
+    #[test]
+    fn test_netns_info_json() {
+        let mut namespace = NetnsInfo::new(
+            "blue".to_string(),
+            PathBuf::from("/run/netns/blue"),
+            4026532300,
+            4026531840,
+        );
+        namespace.interfaces = vec!["lo".to_string()];
+        assert_eq!(
+            serde_json::to_value(&namespace).unwrap(),
+            serde_json::json!({
+                "name": "blue",
+                "path": "/run/netns/blue",
+                "inode": 4026532300u64,
+                "current": false,
+                "interfaces": ["lo"],
+                "addresses": [],
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_netns_in_unshared_namespace() {
+        let Some(mut child) = spawn_unshared() else {
+            eprintln!("skipping: unshare is not permitted in this environment");
+            return;
+        };
+        let path = resolve_netns_path(&child.id().to_string()).unwrap();
+
+        let result = with_netns(&path, interface_names);
+        let own_interfaces = interface_names().unwrap();
+        let listed = list_netns();
+        let _ = child.kill();
+        let _ = child.wait();
+
+        let Ok(interfaces) = result else {
+            eprintln!("skipping: entering the namespace is not permitted");
+            return;
+        };
+        assert_eq!(interfaces, vec!["lo".to_string()]);
+        // The thread is back in its original namespace
+        assert_eq!(interface_names().unwrap(), own_interfaces);
+
+        let listed = listed.unwrap();
+        let namespace = listed
+            .iter()
+            .find(|namespace| namespace.name == format!("pid:{}", child.id()))
+            .unwrap();
+        assert!(!namespace.current);
+        assert_eq!(namespace.interfaces, vec!["lo".to_string()]);
+        assert!(listed.iter().any(|namespace| namespace.current));
+    }
+
+    #[test]
+    fn test_with_netns_restores_after_panic() {
+        let Some(mut child) = spawn_unshared() else {
+            eprintln!("skipping: unshare is not permitted in this environment");
+            return;
+        };
+        let path = resolve_netns_path(&child.id().to_string()).unwrap();
+        let own_inode = netns_inode(Path::new("/proc/thread-self/ns/net")).unwrap();
+
+        let entered = std::thread::spawn(move || {
+            let panicked = std::panic::catch_unwind(|| {
+                with_netns(&path, || -> Result<()> { panic!("inside the namespace") })
+            });
+            (
+                panicked.is_err(),
+                netns_inode(Path::new("/proc/thread-self/ns/net")).unwrap(),
+            )
+        })
+        .join()
+        .unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+
+        let (panicked, inode) = entered;
+        if !panicked {
+            eprintln!("skipping: entering the namespace is not permitted");
+            return;
+        }
+        assert_eq!(inode, own_inode);
+    }
+
+    #[test]
+    fn test_restore_failed_is_detected() {
+        let error = Err::<(), std::io::Error>(std::io::Error::other("EPERM"))
+            .context(RestoreFailed)
+            .unwrap_err();
+        assert!(error.is::<RestoreFailed>());
+        assert!(!anyhow!("Failed to enter network namespace").is::<RestoreFailed>());
+        assert_eq!(
+            format!("{:#}", error),
+            "Failed to restore the original network namespace: EPERM"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// An IP network prefix, e.g. `192.168.1.0/24` or `fd00::/64`.
+///
+/// The address is always stored with its host bits cleared, so two prefixes
+/// describing the same network compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Prefix {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl Prefix {
+    /// Creates the prefix of length `len` containing `addr`.
+    ///
+    /// The length is clamped to the maximum for the address family (32 for
+    /// IPv4, 128 for IPv6) and the host bits of `addr` are cleared.
+    pub fn new(addr: IpAddr, len: u8) -> Self {
+        match addr {
+            IpAddr::V4(v4) => {
+                let len = len.min(32);
+                let bits = u32::from(v4) & v4_mask(len);
+                Self {
+                    addr: IpAddr::V4(Ipv4Addr::from(bits)),
+                    len,
+                }
+            }
+            IpAddr::V6(v6) => {
+                let len = len.min(128);
+                let bits = u128::from(v6) & v6_mask(len);
+                Self {
+                    addr: IpAddr::V6(Ipv6Addr::from(bits)),
+                    len,
+                }
+            }
+        }
+    }
+
+    /// Creates a prefix from an address and a netmask such as
+    /// `255.255.255.0`, as printed in the `Genmask` column of `netstat`.
+    pub fn from_netmask(addr: IpAddr, netmask: IpAddr) -> Self {
+        let len = match netmask {
+            IpAddr::V4(mask) => u32::from(mask).leading_ones(),
+            IpAddr::V6(mask) => u128::from(mask).leading_ones(),
+        };
+        Self::new(addr, len as u8)
+    }
+
+    /// Returns the prefix length in bits.
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    /// Returns the netmask of this prefix, e.g. `255.255.255.0` for a `/24`.
+    pub fn netmask(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(v4_mask(self.len))),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(v6_mask(self.len))),
+        }
+    }
+
+    /// Returns the maximum prefix length of the address family, 32 for IPv4
+    /// and 128 for IPv6.
+    pub fn max_len(&self) -> u8 {
+        match self.addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    /// Returns the first address of this prefix, the network address.
+    pub fn network(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Returns the last address of this prefix, the broadcast address for
+    /// IPv4.
+    pub fn last(&self) -> IpAddr {
+        self.with_bits(bits(self.addr) | bits(self.hostmask()))
+    }
+
+    /// Returns the hostmask (wildcard mask) of this prefix, e.g. `0.0.0.255`
+    /// for a `/24`.
+    pub fn hostmask(&self) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(!v4_mask(self.len))),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(!v6_mask(self.len))),
+        }
+    }
+
+    /// Returns the number of addresses in this prefix, saturating at
+    /// `u128::MAX` for `::/0`.
+    pub fn size(&self) -> u128 {
+        1u128
+            .checked_shl(u32::from(self.max_len() - self.len))
+            .unwrap_or(u128::MAX)
+    }
+
+    /// Returns the prefix one bit shorter that contains this one, or `None`
+    /// for a `/0`.
+    pub fn supernet(&self) -> Option<Prefix> {
+        let len = self.len.checked_sub(1)?;
+        Some(Prefix::new(self.addr, len))
+    }
+
+    /// Returns the subnets of length `len` that this prefix splits into, in
+    /// address order. The iterator is empty if `len` is shorter than this
+    /// prefix or longer than the address family allows.
+    pub fn subnets(&self, len: u8) -> impl Iterator<Item = Prefix> + '_ {
+        let valid = len >= self.len && len <= self.max_len();
+        let count = if valid {
+            1u128
+                .checked_shl(u32::from(len - self.len))
+                .unwrap_or(u128::MAX)
+        } else {
+            0
+        };
+        let step = 1u128
+            .checked_shl(u32::from(self.max_len().saturating_sub(len)))
+            .unwrap_or(0);
+        let network = bits(self.addr);
+        (0..count).map(move |i| Prefix::new(self.with_bits(network + i * step), len))
+    }
+
+    /// Returns an address of the family of this prefix from its bits.
+    fn with_bits(&self, bits: u128) -> IpAddr {
+        match self.addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+        }
+    }
+
+    /// Returns `true` if `ip` is part of this prefix.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                Prefix::new(ip, self.len).addr == self.addr
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the two prefixes share any address, i.e. one of
+    /// them contains the other.
+    pub fn overlaps(&self, other: &Prefix) -> bool {
+        self.contains(other.addr) || other.contains(self.addr)
+    }
+
+    /// Returns `true` if this prefix is a single address (`/32` or `/128`).
+    pub fn is_host(&self) -> bool {
+        match self.addr {
+            IpAddr::V4(_) => self.len == 32,
+            IpAddr::V6(_) => self.len == 128,
+        }
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = anyhow::Error;
+
+    /// Parses `addr/len` notation. A bare address is parsed as a host prefix
+    /// (`/32` or `/128`).
+    fn from_str(s: &str) -> Result<Self> {
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| anyhow!("Invalid IP address in prefix '{}'", s))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let len = match len {
+            Some(len) => len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| anyhow!("Invalid prefix length in '{}'", s))?,
+            None => max_len,
+        };
+        Ok(Self::new(addr, len))
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+impl Serialize for Prefix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Prefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns the smallest set of prefixes covering exactly the same addresses
+/// as `prefixes`: prefixes contained in others are dropped and adjacent
+/// halves of a shorter prefix are merged, e.g. `10.0.0.0/25` and
+/// `10.0.0.128/25` into `10.0.0.0/24`. The result is sorted.
+pub fn aggregate(prefixes: &[Prefix]) -> Vec<Prefix> {
+    let mut sorted = prefixes.to_vec();
+    sorted.sort();
+
+    let mut merged: Vec<Prefix> = Vec::new();
+    for prefix in sorted {
+        // Sorted by address, then length, so a containing prefix comes first
+        if merged.last().is_some_and(|last| last.overlaps(&prefix)) {
+            continue;
+        }
+        merged.push(prefix);
+        while merged.len() >= 2 {
+            let upper = merged[merged.len() - 1];
+            let lower = merged[merged.len() - 2];
+            let parent = match upper.supernet() {
+                Some(parent) if lower.len == upper.len && lower.supernet() == Some(parent) => {
+                    parent
+                }
+                _ => break,
+            };
+            merged.truncate(merged.len() - 2);
+            merged.push(parent);
+        }
+    }
+    merged
+}
+
+/// Returns the bits of an address, IPv4 addresses in the low 32 bits.
+fn bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => u128::from(u32::from(v4)),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+fn v4_mask(len: u8) -> u32 {
+    if len == 0 { 0 } else { u32::MAX << (32 - len) }
+}
+
+fn v6_mask(len: u8) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        u128::MAX << (128 - len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_clears_host_bits() {
+        let prefix = Prefix::new("192.168.1.77".parse().unwrap(), 24);
+        assert_eq!(prefix.to_string(), "192.168.1.0/24");
+
+        let prefix = Prefix::new("fd00::1234".parse().unwrap(), 64);
+        assert_eq!(prefix.to_string(), "fd00::/64");
+    }
+
+    #[test]
+    fn test_parse_and_contains() {
+        let prefix: Prefix = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(prefix.to_string(), "10.0.0.0/8");
+        assert!(prefix.contains("10.200.0.1".parse().unwrap()));
+        assert!(!prefix.contains("11.0.0.1".parse().unwrap()));
+        assert!(!prefix.contains("::1".parse().unwrap()));
+
+        let host: Prefix = "fe80::1".parse().unwrap();
+        assert_eq!(host.prefix_len(), 128);
+        assert!(host.is_host());
+        assert!(!prefix.is_host());
+
+        let inner: Prefix = "10.1.0.0/16".parse().unwrap();
+        assert!(prefix.overlaps(&inner));
+        assert!(inner.overlaps(&prefix));
+        assert!(!inner.overlaps(&"10.2.0.0/16".parse().unwrap()));
+        assert!(!inner.overlaps(&host));
+
+        assert!("10.0.0.0/33".parse::<Prefix>().is_err());
+        assert!("default".parse::<Prefix>().is_err());
+    }
+
+    #[test]
+    fn test_from_netmask() {
+        let prefix = Prefix::from_netmask(
+            "192.168.1.0".parse().unwrap(),
+            "255.255.255.0".parse().unwrap(),
+        );
+        assert_eq!(prefix.to_string(), "192.168.1.0/24");
+        assert_eq!(prefix.netmask().to_string(), "255.255.255.0");
+
+        let prefix: Prefix = "fd00::/64".parse().unwrap();
+        assert_eq!(prefix.netmask().to_string(), "ffff:ffff:ffff:ffff::");
+    }
+
+    #[test]
+    fn test_address_arithmetic() {
+        let prefix: Prefix = "192.168.1.0/26".parse().unwrap();
+        assert_eq!(prefix.network().to_string(), "192.168.1.0");
+        assert_eq!(prefix.last().to_string(), "192.168.1.63");
+        assert_eq!(prefix.hostmask().to_string(), "0.0.0.63");
+        assert_eq!(prefix.size(), 64);
+        assert_eq!(prefix.supernet().unwrap().to_string(), "192.168.1.0/25");
+
+        let prefix: Prefix = "fd00::/64".parse().unwrap();
+        assert_eq!(prefix.last().to_string(), "fd00::ffff:ffff:ffff:ffff");
+        assert_eq!(prefix.size(), 1 << 64);
+        assert_eq!("::/0".parse::<Prefix>().unwrap().size(), u128::MAX);
+        assert_eq!("0.0.0.0/0".parse::<Prefix>().unwrap().supernet(), None);
+    }
+
+    #[test]
+    fn test_subnets() {
+        let prefix: Prefix = "10.0.0.0/24".parse().unwrap();
+        let subnets: Vec<String> = prefix.subnets(26).map(|p| p.to_string()).collect();
+        assert_eq!(
+            subnets,
+            [
+                "10.0.0.0/26",
+                "10.0.0.64/26",
+                "10.0.0.128/26",
+                "10.0.0.192/26"
+            ]
+        );
+        assert_eq!(prefix.subnets(24).count(), 1);
+        assert_eq!(prefix.subnets(16).count(), 0);
+        assert_eq!(prefix.subnets(33).count(), 0);
+
+        let prefix: Prefix = "fd00::/126".parse().unwrap();
+        let last = prefix.subnets(128).last().unwrap();
+        assert_eq!(last.to_string(), "fd00::3/128");
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let prefixes: Vec<Prefix> = [
+            "10.0.0.128/25",
+            "10.0.0.0/25",
+            "10.0.1.0/24",
+            "10.0.1.7/32",
+            "10.0.3.0/24",
+            "fd00::/65",
+            "fd00:0:0:0:8000::/65",
+        ]
+        .iter()
+        .map(|prefix| prefix.parse().unwrap())
+        .collect();
+        let aggregated: Vec<String> = aggregate(&prefixes)
+            .iter()
+            .map(|prefix| prefix.to_string())
+            .collect();
+        assert_eq!(aggregated, ["10.0.0.0/23", "10.0.3.0/24", "fd00::/64"]);
+        assert!(aggregate(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_new_clamps_length() {
+        let prefix = Prefix::new("10.0.0.1".parse().unwrap(), 40);
+        assert_eq!(prefix.to_string(), "10.0.0.1/32");
+
+        let prefix = Prefix::new("10.0.0.1".parse().unwrap(), 0);
+        assert_eq!(prefix.to_string(), "0.0.0.0/0");
+    }
+}
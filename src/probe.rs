@@ -0,0 +1,291 @@
+use anyhow::{Context, Result, anyhow};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Error, ErrorKind};
+
+/// The output of a command run by a `SystemProbe`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandOutput {
+    /// The command exited with status 0.
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Returns the error for a command that failed: `message` followed by
+    /// the command's stderr, of kind `PermissionDenied` if stderr says the
+    /// command was not permitted.
+    pub fn failure(&self, message: &str) -> anyhow::Error {
+        let stderr = self.stderr.trim();
+        let message = if stderr.is_empty() {
+            message.to_string()
+        } else {
+            format!("{}: {}", message, stderr)
+        };
+        let lowercase = stderr.to_lowercase();
+        if lowercase.contains("permission denied") || lowercase.contains("operation not permitted")
+        {
+            Error::new(ErrorKind::PermissionDenied, message).into()
+        } else {
+            anyhow!(message)
+        }
+    }
+}
+
+/// Access to the system for collectors that run commands such as `netstat`
+/// or read files below `/proc` and `/sys`, so they can be tested against
+/// recorded output of other systems.
+pub trait SystemProbe: Send + Sync {
+    /// Returns the operating system, named as in `std::env::consts::OS`.
+    fn os(&self) -> &str;
+
+    /// Returns whether netlink sockets can be used to query the kernel.
+    fn has_netlink(&self) -> bool;
+
+    /// Returns whether `program` is installed.
+    fn has_command(&self, program: &str) -> bool;
+
+    /// Runs `program` with `args` in the C locale and returns its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be started, e.g. because it is
+    /// not installed. A command that fails is not an error.
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput>;
+
+    /// Reads the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or cannot be read.
+    fn read_file(&self, path: &Path) -> Result<String>;
+
+    /// Returns the names of the entries of the directory at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory does not exist or cannot be read.
+    fn read_dir(&self, path: &Path) -> Result<Vec<String>>;
+
+    /// Returns the target of the symbolic link at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` is not a symbolic link.
+    fn read_link(&self, path: &Path) -> Result<PathBuf>;
+}
+
+/// The system ls_net runs on.
+pub struct RealProbe;
+
+impl SystemProbe for RealProbe {
+    fn os(&self) -> &str {
+        env::consts::OS
+    }
+
+    fn has_netlink(&self) -> bool {
+        cfg!(target_os = "linux")
+    }
+
+    fn has_command(&self, program: &str) -> bool {
+        let Some(paths) = env::var_os("PATH") else {
+            return false;
+        };
+        env::split_paths(&paths).any(|dir| {
+            dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file()
+        })
+    }
+
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .env("LC_ALL", "C")
+            .output()
+            .with_context(|| format!("Failed to execute {}", program))?;
+        Ok(CommandOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in
+            fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            names.push(entry?.file_name().to_string_lossy().to_string());
+        }
+        Ok(names)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        fs::read_link(path).with_context(|| format!("Failed to read {}", path.display()))
+    }
+}
+
+/// A system made of recorded command output and files, for tests.
+#[cfg(test)]
+pub struct FakeProbe {
+    os: String,
+    /// Output by command line, e.g. `netstat -rn`.
+    commands: std::collections::BTreeMap<String, CommandOutput>,
+    files: std::collections::BTreeMap<PathBuf, String>,
+    links: std::collections::BTreeMap<PathBuf, PathBuf>,
+}
+
+#[cfg(test)]
+impl FakeProbe {
+    /// Returns a probe of the operating system `os` without any commands or
+    /// files, and without netlink.
+    pub fn new(os: &str) -> Self {
+        Self {
+            os: os.to_string(),
+            commands: Default::default(),
+            files: Default::default(),
+            links: Default::default(),
+        }
+    }
+
+    /// Adds a command line, such as `netstat -rn`, that succeeds with
+    /// `stdout`.
+    pub fn command(mut self, line: &str, stdout: &str) -> Self {
+        let output = CommandOutput {
+            success: true,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+        self.commands.insert(line.to_string(), output);
+        self
+    }
+
+    /// Adds a command line that fails with `stderr`.
+    pub fn failing_command(mut self, line: &str, stderr: &str) -> Self {
+        let output = CommandOutput {
+            success: false,
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+        };
+        self.commands.insert(line.to_string(), output);
+        self
+    }
+
+    /// Adds a file; its parent directories exist implicitly.
+    pub fn file(mut self, path: &str, content: &str) -> Self {
+        self.files.insert(PathBuf::from(path), content.to_string());
+        self
+    }
+
+    /// Adds a symbolic link.
+    pub fn link(mut self, path: &str, target: &str) -> Self {
+        self.links
+            .insert(PathBuf::from(path), PathBuf::from(target));
+        self
+    }
+}
+
+#[cfg(test)]
+impl SystemProbe for FakeProbe {
+    fn os(&self) -> &str {
+        &self.os
+    }
+
+    fn has_netlink(&self) -> bool {
+        false
+    }
+
+    fn has_command(&self, program: &str) -> bool {
+        self.commands
+            .keys()
+            .any(|line| line.split(' ').next() == Some(program))
+    }
+
+    fn run(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        let line = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        self.commands
+            .get(&line)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Failed to execute {}", program))
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Failed to read {}", path.display()))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self
+            .files
+            .keys()
+            .chain(self.links.keys())
+            .filter_map(|file| file.strip_prefix(path).ok()?.iter().next())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        if names.is_empty() {
+            return Err(anyhow::anyhow!("Failed to read {}", path.display()));
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        self.links
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Failed to read {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_probe() {
+        let probe = FakeProbe::new("macos")
+            .command("netstat -rn", "Routing tables\n")
+            .failing_command("arp -an", "arp: permission denied")
+            .file("/sys/class/net/eth0/mtu", "1500\n")
+            .file("/sys/class/net/eth0/statistics/rx_bytes", "1\n")
+            .link("/sys/class/net/veth0/master", "../br0");
+
+        assert_eq!(probe.os(), "macos");
+        assert!(probe.has_command("netstat") && !probe.has_command("ip"));
+        assert_eq!(
+            probe.run("netstat", &["-rn"]).unwrap().stdout,
+            "Routing tables\n"
+        );
+        assert!(!probe.run("arp", &["-an"]).unwrap().success);
+        assert!(probe.run("netstat", &["-an"]).is_err());
+
+        assert_eq!(
+            probe.read_dir(Path::new("/sys/class/net")).unwrap(),
+            ["eth0", "veth0"]
+        );
+        assert_eq!(
+            probe.read_dir(Path::new("/sys/class/net/eth0")).unwrap(),
+            ["mtu", "statistics"]
+        );
+        assert!(probe.read_dir(Path::new("/proc")).is_err());
+        assert_eq!(
+            probe
+                .read_file(Path::new("/sys/class/net/eth0/mtu"))
+                .unwrap(),
+            "1500\n"
+        );
+    }
+}
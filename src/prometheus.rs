@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+
+use crate::http::{Handler, Request, Response};
+use crate::links::{Link, LinkStats, get_links};
+use crate::machine_main_ip::get_local_ip;
+use crate::route_table::route_table::RouteCollector;
+use crate::route_table::{IpVersion, RouteTable};
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The per-interface counters: metric name and help text, in the order of
+/// `counters`.
+const COUNTERS: [(&str, &str); 8] = [
+    ("receive_bytes", "Bytes received"),
+    ("transmit_bytes", "Bytes transmitted"),
+    ("receive_packets", "Packets received"),
+    ("transmit_packets", "Packets transmitted"),
+    ("receive_errors", "Receive errors"),
+    ("transmit_errors", "Transmit errors"),
+    ("receive_drops", "Received packets dropped"),
+    ("transmit_drops", "Transmitted packets dropped"),
+];
+
+fn counters(stats: &LinkStats) -> [u64; 8] {
+    [
+        stats.rx_bytes,
+        stats.tx_bytes,
+        stats.rx_packets,
+        stats.tx_packets,
+        stats.rx_errors,
+        stats.tx_errors,
+        stats.rx_dropped,
+        stats.tx_dropped,
+    ]
+}
+
+/// The host state the metrics are computed from.
+#[derive(Debug, Default)]
+pub struct MetricsData {
+    pub links: Vec<Link>,
+    pub routes: RouteTable,
+    pub main_ip: Option<String>,
+    /// Names of the collectors that failed; their metrics are missing.
+    pub failed: Vec<&'static str>,
+}
+
+impl MetricsData {
+    /// Collects the interface and route state of the host, with the route
+    /// table from `routes`. A failing collector is recorded in `failed`
+    /// instead of failing the scrape.
+    pub fn collect(routes: &RouteCollector) -> Self {
+        let mut data = MetricsData::default();
+        match get_links() {
+            Ok(links) => data.links = links,
+            Err(_) => data.failed.push("links"),
+        }
+        match routes.collect() {
+            Ok(routes) => data.routes = routes,
+            Err(_) => data.failed.push("routes"),
+        }
+        match get_local_ip() {
+            Ok(ip) => data.main_ip = Some(ip),
+            Err(_) => data.failed.push("main_ip"),
+        }
+        data
+    }
+}
+
+/// Writes metric families in the Prometheus text exposition format.
+#[derive(Default)]
+struct Encoder {
+    out: String,
+}
+
+impl Encoder {
+    /// Starts a metric family with its `HELP` and `TYPE` lines.
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+}
+
+/// Escapes a label value: backslash, double quote and line feed.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn family_label(ip_version: &IpVersion) -> &'static str {
+    match ip_version {
+        IpVersion::IPv4 => "ipv4",
+        IpVersion::IPv6 => "ipv6",
+    }
+}
+
+/// Encodes the metrics for `data` in the Prometheus text exposition format.
+pub fn encode(data: &MetricsData) -> String {
+    let mut encoder = Encoder::default();
+
+    encoder.family(
+        "ls_net_collector_success",
+        "gauge",
+        "Whether a collector of ls_net succeeded.",
+    );
+    for collector in ["links", "routes", "main_ip"] {
+        let success = !data.failed.contains(&collector);
+        encoder.sample(
+            "ls_net_collector_success",
+            &[("collector", collector)],
+            u8::from(success),
+        );
+    }
+
+    encoder.family(
+        "ls_net_interface_up",
+        "gauge",
+        "Whether the interface is up and has a carrier.",
+    );
+    for link in &data.links {
+        encoder.sample(
+            "ls_net_interface_up",
+            &[("iface", &link.name)],
+            u8::from(link.is_running()),
+        );
+    }
+
+    encoder.family(
+        "ls_net_interface_mtu",
+        "gauge",
+        "MTU of the interface in bytes.",
+    );
+    for link in &data.links {
+        if let Some(mtu) = link.mtu {
+            encoder.sample("ls_net_interface_mtu", &[("iface", &link.name)], mtu);
+        }
+    }
+
+    for (i, (name, help)) in COUNTERS.iter().enumerate() {
+        let name = format!("ls_net_interface_{}_total", name);
+        encoder.family(&name, "counter", &format!("{} by the interface.", help));
+        for link in &data.links {
+            encoder.sample(&name, &[("iface", &link.name)], counters(&link.stats)[i]);
+        }
+    }
+
+    let mut route_counts: BTreeMap<(String, &str), usize> = BTreeMap::new();
+    for route in data
+        .routes
+        .ipv4_routes
+        .iter()
+        .chain(&data.routes.ipv6_routes)
+    {
+        let key = (
+            route.table_name().to_string(),
+            family_label(&route.ip_version),
+        );
+        *route_counts.entry(key).or_default() += 1;
+    }
+    encoder.family(
+        "ls_net_route_count",
+        "gauge",
+        "Number of routes per routing table and address family.",
+    );
+    for ((table, family), count) in route_counts {
+        encoder.sample(
+            "ls_net_route_count",
+            &[("table", &table), ("family", family)],
+            count,
+        );
+    }
+
+    encoder.family(
+        "ls_net_default_gateway_info",
+        "gauge",
+        "The default gateway of the host, as labels.",
+    );
+    for ip_version in [IpVersion::IPv4, IpVersion::IPv6] {
+        if let Some(route) = data.routes.get_default_gateway(ip_version.clone()) {
+            encoder.sample(
+                "ls_net_default_gateway_info",
+                &[
+                    ("family", family_label(&ip_version)),
+                    ("iface", &route.iface),
+                    ("gateway", &route.gateway),
+                ],
+                1,
+            );
+        }
+    }
+
+    encoder.family(
+        "ls_net_main_ip_info",
+        "gauge",
+        "The main IP address of the host, as a label.",
+    );
+    if let Some(ip) = &data.main_ip {
+        encoder.sample("ls_net_main_ip_info", &[("ip", ip)], 1);
+    }
+
+    encoder.out
+}
+
+/// Answers `GET /metrics` with freshly collected metrics.
+fn handle(request: &Request, routes: &RouteCollector) -> Response {
+    match request.path.as_str() {
+        "/metrics" => Response::new(200, CONTENT_TYPE, encode(&MetricsData::collect(routes))),
+        "/" => Response::text(200, "ls_net Prometheus exporter, metrics at /metrics\n"),
+        _ => Response::not_found(),
+    }
+}
+
+/// Returns the HTTP handler serving the metrics at `/metrics`, with the
+/// route table collected by `routes`.
+pub fn handler(routes: RouteCollector) -> Handler {
+    Arc::new(move |request| handle(request, &routes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::RouteEntry;
+
+    fn sample_data() -> MetricsData {
+        let mut routes = RouteTable::new();
+        for (destination, table) in [
+            ("default", "main"),
+            ("192.168.1.0/24", "main"),
+            ("192.168.1.10", "local"),
+        ] {
+            routes.add_route(RouteEntry {
+                destination: destination.to_string(),
+                gateway: "192.168.1.1".to_string(),
+                iface: "eth0".to_string(),
+                table: Some(table.to_string()),
+                ..Default::default()
+            });
+        }
+
+        MetricsData {
+            links: vec![Link {
+                name: "eth0".to_string(),
+                up: true,
+                carrier: Some(true),
+                mtu: Some(1500),
+                master: None,
+                stats: LinkStats {
+                    rx_bytes: 1024,
+                    tx_errors: 2,
+                    ..Default::default()
+                },
+            }],
+            routes,
+            main_ip: Some("192.168.1.10".to_string()),
+            failed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let text = encode(&sample_data());
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(lines.contains(&"# TYPE ls_net_interface_up gauge"));
+        assert!(lines.contains(&"ls_net_interface_up{iface=\"eth0\"} 1"));
+        assert!(lines.contains(&"ls_net_interface_mtu{iface=\"eth0\"} 1500"));
+        assert!(lines.contains(&"# TYPE ls_net_interface_receive_bytes_total counter"));
+        assert!(lines.contains(&"ls_net_interface_receive_bytes_total{iface=\"eth0\"} 1024"));
+        assert!(lines.contains(&"ls_net_interface_transmit_errors_total{iface=\"eth0\"} 2"));
+        assert!(lines.contains(&"ls_net_route_count{table=\"main\",family=\"ipv4\"} 2"));
+        assert!(lines.contains(&"ls_net_route_count{table=\"local\",family=\"ipv4\"} 1"));
+        assert!(lines.contains(
+            &"ls_net_default_gateway_info{family=\"ipv4\",iface=\"eth0\",gateway=\"192.168.1.1\"} 1"
+        ));
+        assert!(lines.contains(&"ls_net_main_ip_info{ip=\"192.168.1.10\"} 1"));
+        assert!(lines.contains(&"ls_net_collector_success{collector=\"links\"} 1"));
+
+        // Every sample belongs to a family declared before it
+        let mut declared = Vec::new();
+        for line in &lines {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                declared.push(rest.split_whitespace().next().unwrap());
+            } else if !line.starts_with('#') {
+                let name = line.split(['{', ' ']).next().unwrap();
+                assert_eq!(declared.last(), Some(&name));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_failed_collector_and_escaping() {
+        let mut data = sample_data();
+        data.failed.push("main_ip");
+        data.main_ip = None;
+        data.links[0].name = "we\"ird\\".to_string();
+
+        let text = encode(&data);
+        assert!(text.contains("ls_net_collector_success{collector=\"main_ip\"} 0\n"));
+        assert!(!text.contains("ls_net_main_ip_info{"));
+        assert!(text.contains("ls_net_interface_mtu{iface=\"we\\\"ird\\\\\"} 1500\n"));
+    }
+}
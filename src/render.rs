@@ -0,0 +1,414 @@
+use anyhow::{Context, Result};
+use colored::Color;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::table::Table;
+
+/// A part of the text output that is styled on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    /// Titles
+    Title,
+    /// `====` separator lines, also around section titles
+    Separator,
+    /// Table headers and labels such as "Main IP address:"
+    Header,
+    /// Interface names
+    Name,
+    /// IP addresses and the keys of table rows
+    Address,
+    /// Gateways and next hops
+    Gateway,
+    /// Names of interfaces that are down
+    Down,
+    /// Failures, such as a route lookup rejected by a rule
+    Error,
+    /// Added routes and addresses, and links that came up
+    Added,
+    /// Removed routes, addresses and links
+    Removed,
+    /// Warnings and notes
+    Warning,
+    /// Secondary details, such as the path of a network namespace
+    Detail,
+}
+
+/// The color and attributes of an element, written in a theme file as a
+/// color name as understood by `colored` ("red", "bright blue", ...)
+/// and/or the attributes "bold", "dimmed", "italic" and "underline", e.g.
+/// "bright magenta bold". "none" leaves the element unstyled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Paint {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Paint {
+    const fn color(color: Color) -> Self {
+        Self {
+            color: Some(color),
+            bold: false,
+            dimmed: false,
+            italic: false,
+            underline: false,
+        }
+    }
+
+    const fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    /// Returns `text` wrapped in the ANSI escape codes of the paint.
+    pub fn apply(&self, text: &str) -> String {
+        let mut codes = Vec::new();
+        for (enabled, code) in [
+            (self.bold, "1"),
+            (self.dimmed, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+        ] {
+            if enabled {
+                codes.push(code.to_string());
+            }
+        }
+        if let Some(color) = self.color {
+            codes.push(color.to_fg_str().to_string());
+        }
+        if codes.is_empty() || text.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+}
+
+impl TryFrom<String> for Paint {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut paint = Paint::default();
+        let mut color = Vec::new();
+        for word in value.split_whitespace() {
+            match word.to_lowercase().as_str() {
+                "bold" => paint.bold = true,
+                "dimmed" => paint.dimmed = true,
+                "italic" => paint.italic = true,
+                "underline" => paint.underline = true,
+                "none" => {}
+                _ => color.push(word),
+            }
+        }
+        if !color.is_empty() {
+            let name = color.join(" ");
+            paint.color = Some(
+                name.parse()
+                    .map_err(|_| format!("unknown color '{}'", name))?,
+            );
+        }
+        Ok(paint)
+    }
+}
+
+/// The paint of each element of the text output, read from a theme file in
+/// TOML with a key per element, e.g. `gateway = "bright magenta"`. Elements
+/// the file leaves out keep the default colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub title: Paint,
+    pub separator: Paint,
+    pub header: Paint,
+    pub name: Paint,
+    pub address: Paint,
+    pub gateway: Paint,
+    pub down: Paint,
+    pub error: Paint,
+    pub added: Paint,
+    pub removed: Paint,
+    pub warning: Paint,
+    pub detail: Paint,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            title: Paint::color(Color::Green).bold(),
+            separator: Paint::color(Color::Green),
+            header: Paint::color(Color::Blue).bold(),
+            name: Paint::color(Color::Blue).bold(),
+            address: Paint::color(Color::Yellow),
+            gateway: Paint::color(Color::Yellow),
+            down: Paint::color(Color::Red),
+            error: Paint::color(Color::Red),
+            added: Paint::color(Color::Green),
+            removed: Paint::color(Color::Red),
+            warning: Paint::color(Color::Yellow),
+            detail: Paint {
+                dimmed: true,
+                ..Paint::default()
+            },
+        }
+    }
+}
+
+impl Theme {
+    /// Loads the theme file `path`, or with `None` the user's theme file
+    /// `ls_net/theme.toml` in the configuration directory if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid theme.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_theme_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid theme file {}", path.display()))
+    }
+
+    fn paint(&self, element: Element) -> &Paint {
+        match element {
+            Element::Title => &self.title,
+            Element::Separator => &self.separator,
+            Element::Header => &self.header,
+            Element::Name => &self.name,
+            Element::Address => &self.address,
+            Element::Gateway => &self.gateway,
+            Element::Down => &self.down,
+            Element::Error => &self.error,
+            Element::Added => &self.added,
+            Element::Removed => &self.removed,
+            Element::Warning => &self.warning,
+            Element::Detail => &self.detail,
+        }
+    }
+}
+
+/// Returns the path of the user's theme file: `ls_net/theme.toml` in
+/// `$XDG_CONFIG_HOME` or `~/.config`, or in `%APPDATA%` on Windows.
+fn default_theme_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".config")))?
+    };
+    Some(dir.join("ls_net").join("theme.toml"))
+}
+
+/// Returns whether to color output for `--color` `choice` ("auto", "always"
+/// or "never"), the values of the `NO_COLOR` and `CLICOLOR_FORCE`
+/// environment variables and whether stdout is a terminal.
+///
+/// With "auto", a non-empty `NO_COLOR` disables colors, then a
+/// `CLICOLOR_FORCE` other than "0" enables them, and otherwise only a
+/// terminal gets colors.
+pub fn use_color(
+    choice: &str,
+    no_color: Option<&str>,
+    clicolor_force: Option<&str>,
+    is_terminal: bool,
+) -> bool {
+    match choice {
+        "always" => true,
+        "never" => false,
+        _ if no_color.is_some_and(|value| !value.is_empty()) => false,
+        _ if clicolor_force.is_some_and(|value| value != "0") => true,
+        _ => is_terminal,
+    }
+}
+
+/// How text output is styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Colors the elements with ANSI escape codes.
+    pub color: bool,
+    /// Writes only ASCII characters, replacing all others with `?`, for
+    /// legacy consoles.
+    pub ascii: bool,
+    pub theme: Theme,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            color: true,
+            ascii: false,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Style {
+    /// Returns `text` styled as `element`.
+    pub fn paint(&self, element: Element, text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        self.theme.paint(element).apply(text)
+    }
+}
+
+/// Where renderers write their output, and in which format: "text", or
+/// tables as "csv" or "markdown".
+pub struct Output<'a> {
+    writer: &'a mut dyn Write,
+    pub format: &'a str,
+    pub style: Style,
+}
+
+impl<'a> Output<'a> {
+    pub fn new(writer: &'a mut dyn Write, format: &'a str, style: Style) -> Self {
+        Self {
+            writer,
+            format,
+            style,
+        }
+    }
+
+    /// Returns whether the output is text for the terminal rather than
+    /// tables only.
+    pub fn is_text(&self) -> bool {
+        !matches!(self.format, "csv" | "markdown")
+    }
+
+    /// Returns `text` styled as `element`.
+    pub fn paint(&self, element: Element, text: &str) -> String {
+        self.style.paint(element, text)
+    }
+
+    /// Writes `table` in the output format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output cannot be written.
+    pub fn table(&mut self, table: &Table) -> io::Result<()> {
+        let rendered = match self.format {
+            "csv" => table.render_csv(),
+            "markdown" => table.render_markdown(),
+            _ => table.render(&self.style),
+        };
+        self.write_all(rendered.as_bytes())
+    }
+}
+
+impl Write for Output<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.style.ascii || buf.is_ascii() {
+            return self.writer.write(buf);
+        }
+        // Output is written in whole strings, so `buf` is valid UTF-8
+        let ascii: String = String::from_utf8_lossy(buf)
+            .chars()
+            .map(|c| if c.is_ascii() { c } else { '?' })
+            .collect();
+        self.writer.write_all(ascii.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output() {
+        let mut buffer = Vec::new();
+        let plain = Style {
+            color: false,
+            ..Style::default()
+        };
+        let mut output = Output::new(&mut buffer, "text", plain);
+        let title = output.paint(Element::Title, "Routes");
+        writeln!(output, "{}", title).unwrap();
+        let mut table = Table::new(["Destination", "Gateway"]);
+        table.push_row(vec!["default".to_string(), "192.0.2.1".to_string()]);
+        output.table(&table).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Routes\nDestination  Gateway\ndefault      192.0.2.1\n"
+        );
+
+        let mut buffer = Vec::new();
+        let mut output = Output::new(&mut buffer, "csv", Style::default());
+        assert!(!output.is_text());
+        output.table(&table).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Destination,Gateway\ndefault,192.0.2.1\n"
+        );
+
+        let mut buffer = Vec::new();
+        let ascii = Style {
+            ascii: true,
+            ..plain
+        };
+        writeln!(Output::new(&mut buffer, "text", ascii), "Ethernet → café").unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "Ethernet ? caf?\n");
+    }
+
+    #[test]
+    fn test_theme() {
+        let style = Style::default();
+        assert_eq!(
+            style.paint(Element::Header, "Gateway"),
+            "\x1b[1;34mGateway\x1b[0m"
+        );
+        assert_eq!(
+            style.paint(Element::Address, "10.0.0.1"),
+            "\x1b[33m10.0.0.1\x1b[0m"
+        );
+
+        let theme: Theme = toml::from_str(
+            "gateway = \"bright magenta bold\"\ndown = \"red dimmed\"\naddress = \"none\"\n",
+        )
+        .unwrap();
+        let style = Style {
+            theme,
+            ..Style::default()
+        };
+        assert_eq!(
+            style.paint(Element::Gateway, "10.0.0.1"),
+            "\x1b[1;95m10.0.0.1\x1b[0m"
+        );
+        assert_eq!(style.paint(Element::Down, "eth1"), "\x1b[2;31meth1\x1b[0m");
+        assert_eq!(style.paint(Element::Address, "10.0.0.2"), "10.0.0.2");
+        // Elements the theme leaves out keep the default colors
+        assert_eq!(theme.header, Theme::default().header);
+
+        let error = toml::from_str::<Theme>("gateway = \"ultraviolet\"").unwrap_err();
+        assert!(error.to_string().contains("unknown color 'ultraviolet'"));
+        assert!(toml::from_str::<Theme>("gatway = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_use_color() {
+        assert!(use_color("auto", None, None, true));
+        assert!(!use_color("auto", None, None, false));
+        assert!(!use_color("auto", Some("1"), None, true));
+        assert!(use_color("auto", Some(""), None, true));
+        assert!(use_color("auto", None, Some("1"), false));
+        assert!(!use_color("auto", None, Some("0"), false));
+        assert!(!use_color("auto", Some("1"), Some("1"), true));
+        assert!(use_color("always", Some("1"), None, false));
+        assert!(!use_color("never", None, Some("1"), true));
+    }
+}
@@ -0,0 +1,628 @@
+use serde::Serialize;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+use crate::ip_interfaces::InterfaceAddr;
+use crate::prefix::Prefix;
+use crate::render::{Element, Output};
+use crate::route_table::rules::{RouteQuery, RouteRule};
+use crate::route_table::{IpVersion, RouteEntry, RouteTable};
+
+/// A subnet an interface is directly connected to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Subnet {
+    pub prefix: Prefix,
+    pub iface: String,
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}", self.prefix, self.iface)
+    }
+}
+
+/// A route or subnet that takes traffic away from another one, typically a
+/// VPN client shadowing the LAN.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Conflict<'a> {
+    /// Two interfaces are connected to overlapping subnets.
+    OverlappingSubnets { subnet: Subnet, other: Subnet },
+    /// A route is overridden through another next hop, by more specific
+    /// routes or by a route to the same prefix with a lower metric. `full`
+    /// is set if no traffic is left for the route.
+    ShadowedRoute {
+        route: &'a RouteEntry,
+        by: Vec<&'a RouteEntry>,
+        full: bool,
+    },
+    /// A route through another interface takes all or part of the traffic
+    /// for a connected subnet.
+    ConnectedSubnet {
+        subnet: Subnet,
+        route: &'a RouteEntry,
+    },
+}
+
+impl Conflict<'_> {
+    /// Returns the name of the kind of conflict, as used in JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Conflict::OverlappingSubnets { .. } => "overlapping-subnets",
+            Conflict::ShadowedRoute { .. } => "shadowed-route",
+            Conflict::ConnectedSubnet { .. } => "connected-subnet",
+        }
+    }
+
+    /// Returns a one-line description of the conflict.
+    pub fn message(&self) -> String {
+        match self {
+            Conflict::OverlappingSubnets { subnet, other } => {
+                format!("{} overlaps {}", subnet, other)
+            }
+            Conflict::ShadowedRoute { route, by, full } => format!(
+                "{} is {} shadowed by {} route{}",
+                route.describe(),
+                if *full { "fully" } else { "partly" },
+                by.len(),
+                if by.len() == 1 { "" } else { "s" }
+            ),
+            Conflict::ConnectedSubnet { subnet, route } => format!(
+                "{} takes traffic for the connected subnet {}",
+                route.describe(),
+                subnet
+            ),
+        }
+    }
+}
+
+/// Why the kernel picks a route for a destination, and which other routes
+/// to the destination it overrides.
+#[derive(Debug, Serialize)]
+pub struct Explanation<'a> {
+    pub destination: IpAddr,
+    /// The policy rule that selected the route, if rules are known.
+    pub rule: Option<&'a RouteRule>,
+    /// The selected route; `None` if the rule rejects the traffic.
+    pub route: Option<&'a RouteEntry>,
+    /// The other routes containing the destination, the most specific first.
+    pub overridden: Vec<Overridden<'a>>,
+    /// The connected subnet containing the destination, if the selected route
+    /// leaves through another interface.
+    pub bypassed_subnet: Option<Subnet>,
+}
+
+/// A route to the destination that lost against the selected route.
+#[derive(Debug, Serialize)]
+pub struct Overridden<'a> {
+    pub route: &'a RouteEntry,
+    pub reason: String,
+}
+
+/// Returns `true` for loopback and link-local addresses, which every host has
+/// and which never reach beyond the link.
+pub fn is_local_only(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unicast_link_local(),
+    }
+}
+
+/// Returns the subnets of the interface addresses, leaving out loopback,
+/// link-local and single-address (e.g. point-to-point) subnets.
+pub fn connected_subnets(interfaces: &[InterfaceAddr]) -> Vec<Subnet> {
+    let mut subnets: Vec<Subnet> = Vec::new();
+    for interface in interfaces {
+        if is_local_only(&interface.ip) {
+            continue;
+        }
+        let subnet = Subnet {
+            prefix: Prefix::new(interface.ip, interface.prefix_len),
+            iface: interface.name.clone(),
+        };
+        if !subnet.prefix.is_host() && !subnets.contains(&subnet) {
+            subnets.push(subnet);
+        }
+    }
+    subnets
+}
+
+/// Returns the pairs of subnets on different interfaces that overlap.
+pub fn overlapping_subnets(subnets: &[Subnet]) -> Vec<(&Subnet, &Subnet)> {
+    let mut pairs = Vec::new();
+    for (i, subnet) in subnets.iter().enumerate() {
+        for other in &subnets[i + 1..] {
+            if subnet.iface != other.iface && subnet.prefix.overlaps(&other.prefix) {
+                pairs.push((subnet, other));
+            }
+        }
+    }
+    pairs
+}
+
+/// Returns `true` if the kernel forwards traffic with `route`, as opposed to
+/// reject, local and broadcast routes. Interface-scoped macOS routes only
+/// apply to traffic bound to the interface and are left out as well.
+fn forwards(route: &RouteEntry) -> bool {
+    route
+        .route_type
+        .as_deref()
+        .is_none_or(|route_type| route_type == "unicast")
+        && !route.flags.contains('I')
+}
+
+/// Returns where `route` sends traffic: the gateway and the interface. Direct
+/// routes, including macOS routes with a link-layer gateway, have no gateway.
+fn next_hop(route: &RouteEntry) -> (Option<IpAddr>, &str) {
+    let gateway = route
+        .gateway
+        .split('%')
+        .next()
+        .and_then(|gateway| gateway.parse::<IpAddr>().ok())
+        .filter(|ip| !ip.is_unspecified());
+    (gateway, &route.iface)
+}
+
+/// Returns `true` if every address of `prefix` is in one of `covering`, all
+/// of which are contained in `prefix`.
+fn is_covered(prefix: &Prefix, covering: &[Prefix]) -> bool {
+    let mut outermost: Vec<&Prefix> = covering
+        .iter()
+        .filter(|inner| {
+            !covering
+                .iter()
+                .any(|outer| outer.prefix_len() < inner.prefix_len() && outer.overlaps(inner))
+        })
+        .collect();
+    outermost.sort();
+    outermost.dedup();
+    outermost
+        .iter()
+        .map(|inner| inner.size())
+        .fold(0u128, u128::saturating_add)
+        >= prefix.size()
+}
+
+/// Returns the forwarding routes with a known destination network.
+fn routes_with_prefix(routes: &[RouteEntry]) -> Vec<(&RouteEntry, Prefix)> {
+    routes
+        .iter()
+        .filter(|route| forwards(route))
+        .filter_map(|route| Some((route, route.prefix()?)))
+        .collect()
+}
+
+/// Returns the routes that other routes of the same table override through
+/// another next hop. Default routes, which every more specific route
+/// overrides, are left out, as are connected routes overridden through
+/// another interface, which `connected_conflicts` reports. Direct routes on
+/// the same interface, such as the subnet of the gateway or macOS neighbor
+/// entries, only change the next hop on the link and do not count.
+fn shadowed_routes<'a>(routes: &'a [RouteEntry], subnets: &[Subnet]) -> Vec<Conflict<'a>> {
+    let candidates = routes_with_prefix(routes);
+    let mut conflicts = Vec::new();
+    for (route, prefix) in &candidates {
+        if prefix.prefix_len() == 0 {
+            continue;
+        }
+        let connected = subnets
+            .iter()
+            .any(|subnet| &subnet.prefix == prefix && subnet.iface == route.iface);
+
+        let shadowing: Vec<(&RouteEntry, Prefix)> = candidates
+            .iter()
+            .filter(|(other, other_prefix)| {
+                other.table_name() == route.table_name()
+                    && next_hop(other) != next_hop(route)
+                    && !(connected && other.iface != route.iface)
+                    && !(other.iface == route.iface && next_hop(other).0.is_none())
+                    && prefix.overlaps(other_prefix)
+                    && (other_prefix.prefix_len() > prefix.prefix_len()
+                        || other.metric.unwrap_or(0) < route.metric.unwrap_or(0))
+            })
+            .copied()
+            .collect();
+        if shadowing.is_empty() {
+            continue;
+        }
+
+        let covering: Vec<Prefix> = shadowing.iter().map(|(_, prefix)| *prefix).collect();
+        conflicts.push(Conflict::ShadowedRoute {
+            route,
+            by: shadowing.into_iter().map(|(other, _)| other).collect(),
+            full: is_covered(prefix, &covering),
+        });
+    }
+    conflicts
+}
+
+/// Returns the routes through another interface that are at least as
+/// specific as a connected subnet they overlap. Connected routes of other
+/// interfaces are left out; `overlapping_subnets` reports those.
+fn connected_conflicts<'a>(routes: &'a [RouteEntry], subnets: &[Subnet]) -> Vec<Conflict<'a>> {
+    let candidates = routes_with_prefix(routes);
+    let mut conflicts = Vec::new();
+    for subnet in subnets {
+        for (route, prefix) in &candidates {
+            let own_subnet = subnets
+                .iter()
+                .any(|other| &other.prefix == prefix && other.iface == route.iface);
+            if route.iface != subnet.iface
+                && !own_subnet
+                && prefix.overlaps(&subnet.prefix)
+                && prefix.prefix_len() >= subnet.prefix.prefix_len()
+            {
+                conflicts.push(Conflict::ConnectedSubnet {
+                    subnet: subnet.clone(),
+                    route,
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Returns all conflicts between the connected subnets and the routes of
+/// `route_table`: overlapping subnets first, then connected subnets taken
+/// over by other interfaces, then shadowed routes.
+pub fn find_conflicts<'a>(route_table: &'a RouteTable, subnets: &[Subnet]) -> Vec<Conflict<'a>> {
+    let mut conflicts: Vec<Conflict> = overlapping_subnets(subnets)
+        .into_iter()
+        .map(|(subnet, other)| Conflict::OverlappingSubnets {
+            subnet: subnet.clone(),
+            other: other.clone(),
+        })
+        .collect();
+    for routes in [&route_table.ipv4_routes, &route_table.ipv6_routes] {
+        conflicts.extend(connected_conflicts(routes, subnets));
+    }
+    for routes in [&route_table.ipv4_routes, &route_table.ipv6_routes] {
+        conflicts.extend(shadowed_routes(routes, subnets));
+    }
+    conflicts
+}
+
+/// Returns why `overridden` lost against the route selected by `lookup`.
+fn override_reason(
+    overridden: &RouteEntry,
+    prefix: &Prefix,
+    rule: Option<&RouteRule>,
+    selected: Option<(&RouteEntry, Prefix)>,
+) -> String {
+    let Some((selected, selected_prefix)) = selected else {
+        return match rule {
+            Some(rule) => format!("rule {} rejects the traffic", rule.priority),
+            None => "the traffic is rejected".to_string(),
+        };
+    };
+    if let Some(rule) = rule
+        && overridden.table_name() != selected.table_name()
+    {
+        return format!(
+            "in table {}, while rule {} selected table {}",
+            overridden.table_name(),
+            rule.priority,
+            selected.table_name()
+        );
+    }
+    if prefix.prefix_len() < selected_prefix.prefix_len() {
+        return format!("less specific than /{}", selected_prefix.prefix_len());
+    }
+    let (metric, selected_metric) = (overridden.metric.unwrap_or(0), selected.metric.unwrap_or(0));
+    if metric > selected_metric {
+        format!("higher metric ({} > {})", metric, selected_metric)
+    } else {
+        "same prefix and metric as the selected route".to_string()
+    }
+}
+
+/// Explains the route lookup for `query`: the selected route, every other
+/// route containing the destination with the reason it lost, and the
+/// connected subnet the traffic bypasses, if any.
+///
+/// Returns `None` if no route matches the destination.
+pub fn explain<'a>(
+    route_table: &'a RouteTable,
+    subnets: &[Subnet],
+    query: &RouteQuery,
+) -> Option<Explanation<'a>> {
+    let lookup = route_table.lookup(query)?;
+    let selected = lookup
+        .route
+        .and_then(|route| Some((route, route.prefix()?)));
+
+    let mut candidates: Vec<(&RouteEntry, Prefix)> =
+        routes_with_prefix(route_table.routes(&IpVersion::of(&query.dst)))
+            .into_iter()
+            .filter(|(route, prefix)| {
+                prefix.contains(query.dst)
+                    && !lookup
+                        .route
+                        .is_some_and(|selected| std::ptr::eq(selected, *route))
+            })
+            .collect();
+    candidates.sort_by_key(|(route, prefix)| {
+        (
+            std::cmp::Reverse(prefix.prefix_len()),
+            route.metric.unwrap_or(0),
+        )
+    });
+    let overridden = candidates
+        .into_iter()
+        .map(|(route, prefix)| Overridden {
+            route,
+            reason: override_reason(route, &prefix, lookup.rule, selected),
+        })
+        .collect();
+
+    let bypassed_subnet = subnets
+        .iter()
+        .filter(|subnet| subnet.prefix.contains(query.dst))
+        .max_by_key(|subnet| subnet.prefix.prefix_len())
+        .filter(|subnet| {
+            lookup
+                .route
+                .is_some_and(|route| route.iface != subnet.iface)
+        })
+        .cloned();
+
+    Some(Explanation {
+        destination: query.dst,
+        rule: lookup.rule,
+        route: lookup.route,
+        overridden,
+        bypassed_subnet,
+    })
+}
+
+/// Writes the conflicts found by `find_conflicts` to `output`.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written.
+pub fn print_conflicts(output: &mut Output, conflicts: &[Conflict]) -> io::Result<()> {
+    let title = output.paint(Element::Title, "Route Conflicts");
+    writeln!(output, "{}", title)?;
+    let width = conflicts
+        .iter()
+        .map(|conflict| conflict.kind().len())
+        .max()
+        .unwrap_or(0);
+    for conflict in conflicts {
+        let kind = output.paint(
+            Element::Warning,
+            &format!("{:width$}", conflict.kind(), width = width),
+        );
+        writeln!(output, "{}  {}", kind, conflict.message())?;
+        if let Conflict::ShadowedRoute { by, .. } = conflict {
+            for route in by {
+                writeln!(
+                    output,
+                    "{:width$}    by {}",
+                    "",
+                    route.describe(),
+                    width = width
+                )?;
+            }
+        }
+    }
+    if conflicts.is_empty() {
+        let none = output.paint(Element::Added, "No conflicts found");
+        writeln!(output, "{}", none)?;
+    }
+    Ok(())
+}
+
+/// Writes an explanation returned by `explain` to `output`.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written.
+pub fn print_explanation(output: &mut Output, explanation: &Explanation) -> io::Result<()> {
+    let title = output.paint(Element::Title, "Route lookup for");
+    let destination = output.paint(Element::Address, &explanation.destination.to_string());
+    writeln!(output, "{} {}", title, destination)?;
+    if let Some(rule) = explanation.rule {
+        let label = output.paint(Element::Header, "Rule: ");
+        writeln!(output, "{} {}", label, rule.to_string().replace('\t', " "))?;
+    }
+    let label = output.paint(Element::Header, "Route:");
+    match explanation.route {
+        Some(route) => writeln!(output, "{} {}", label, route.describe())?,
+        None => {
+            let rejected = output.paint(Element::Error, "rejected by rule");
+            writeln!(output, "{} {}", label, rejected)?
+        }
+    }
+    if !explanation.overridden.is_empty() {
+        let label = output.paint(Element::Header, "Overridden:");
+        writeln!(output, "{}", label)?;
+        for overridden in &explanation.overridden {
+            let reason = output.paint(Element::Warning, &overridden.reason);
+            writeln!(output, "  {}  ({})", overridden.route.describe(), reason)?;
+        }
+    }
+    if let Some(subnet) = &explanation.bypassed_subnet
+        && let Some(route) = explanation.route
+    {
+        let label = output.paint(Element::Warning, "Note:");
+        writeln!(
+            output,
+            "{} {} is in the connected subnet {} of {}, but leaves through {}",
+            label, explanation.destination, subnet.prefix, subnet.iface, route.iface
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, gateway: &str, iface: &str, metric: u32) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            iface: iface.to_string(),
+            metric: Some(metric),
+            ..Default::default()
+        }
+    }
+
+    fn subnet(prefix: &str, iface: &str) -> Subnet {
+        Subnet {
+            prefix: prefix.parse().unwrap(),
+            iface: iface.to_string(),
+        }
+    }
+
+    /// A laptop on 192.168.1.0/24 whose VPN client routes half of the LAN,
+    /// and a second network overlapping the VPN subnet.
+    fn vpn_table() -> RouteTable {
+        let mut route_table = RouteTable::new();
+        for route in [
+            route("default", "192.168.1.1", "eth0", 100),
+            route("192.168.1.0/24", "0.0.0.0", "eth0", 100),
+            route("192.168.1.128/25", "10.8.0.1", "tun0", 50),
+            route("10.8.0.0/24", "0.0.0.0", "tun0", 0),
+            route("10.0.0.0/8", "10.8.0.1", "tun0", 0),
+            route("10.1.0.0/16", "10.8.0.254", "tun0", 0),
+            route("172.16.0.0/12", "192.168.1.1", "eth0", 200),
+            route("172.16.0.0/12", "10.8.0.1", "tun0", 50),
+        ] {
+            route_table.add_route(route);
+        }
+        route_table
+    }
+
+    #[test]
+    fn test_overlapping_subnets() {
+        let interfaces = vec![
+            InterfaceAddr {
+                name: "eth0".to_string(),
+                ip: "10.0.0.5".parse().unwrap(),
+                netmask: "255.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+            InterfaceAddr {
+                name: "eth0".to_string(),
+                ip: "10.0.0.6".parse().unwrap(),
+                netmask: "255.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+            InterfaceAddr {
+                name: "tun0".to_string(),
+                ip: "10.8.0.2".parse().unwrap(),
+                netmask: "255.255.255.0".parse().unwrap(),
+                prefix_len: 24,
+            },
+            InterfaceAddr {
+                name: "lo".to_string(),
+                ip: "127.0.0.1".parse().unwrap(),
+                netmask: "255.0.0.0".parse().unwrap(),
+                prefix_len: 8,
+            },
+        ];
+        let subnets = connected_subnets(&interfaces);
+        assert_eq!(
+            subnets,
+            [subnet("10.0.0.0/8", "eth0"), subnet("10.8.0.0/24", "tun0")]
+        );
+
+        let route_table = RouteTable::new();
+        let conflicts = find_conflicts(&route_table, &subnets);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].message(),
+            "10.0.0.0/8 on eth0 overlaps 10.8.0.0/24 on tun0"
+        );
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let route_table = vpn_table();
+        let subnets = [
+            subnet("192.168.1.0/24", "eth0"),
+            subnet("10.8.0.0/24", "tun0"),
+        ];
+        let conflicts = find_conflicts(&route_table, &subnets);
+        let found: Vec<(&str, String)> = conflicts
+            .iter()
+            .map(|conflict| match conflict {
+                Conflict::ShadowedRoute { route, by, full } => (
+                    conflict.kind(),
+                    format!("{} by {} full={}", route.destination, by.len(), full),
+                ),
+                _ => (conflict.kind(), conflict.message()),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "connected-subnet",
+                    "192.168.1.128/25 via 10.8.0.1 dev tun0 table main metric 50 takes \
+                     traffic for the connected subnet 192.168.1.0/24 on eth0"
+                        .to_string()
+                ),
+                // Routed through the other gateway of tun0
+                ("shadowed-route", "10.0.0.0/8 by 1 full=false".to_string()),
+                // Same prefix with a lower metric
+                ("shadowed-route", "172.16.0.0/12 by 1 full=true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_coverage() {
+        let halves: Vec<Prefix> = ["10.0.0.0/9", "10.128.0.0/9", "10.1.0.0/16"]
+            .iter()
+            .map(|prefix| prefix.parse().unwrap())
+            .collect();
+        assert!(is_covered(&"10.0.0.0/8".parse().unwrap(), &halves));
+        assert!(!is_covered(&"10.0.0.0/8".parse().unwrap(), &halves[1..]));
+        assert!(is_covered(
+            &"fd00::/8".parse().unwrap(),
+            &["fd00::/8".parse().unwrap()]
+        ));
+    }
+
+    #[test]
+    fn test_explain() {
+        let route_table = vpn_table();
+        let subnets = [subnet("192.168.1.0/24", "eth0")];
+        let query = RouteQuery::new("192.168.1.200".parse().unwrap());
+        let explanation = explain(&route_table, &subnets, &query).unwrap();
+
+        assert_eq!(explanation.route.unwrap().destination, "192.168.1.128/25");
+        let overridden: Vec<(&str, &str)> = explanation
+            .overridden
+            .iter()
+            .map(|overridden| {
+                (
+                    overridden.route.destination.as_str(),
+                    overridden.reason.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            overridden,
+            [
+                ("192.168.1.0/24", "less specific than /25"),
+                ("default", "less specific than /25")
+            ]
+        );
+        assert_eq!(explanation.bypassed_subnet, Some(subnets[0].clone()));
+
+        // Not bypassed when the connected route wins
+        let query = RouteQuery::new("192.168.1.20".parse().unwrap());
+        let explanation = explain(&route_table, &subnets, &query).unwrap();
+        assert_eq!(explanation.route.unwrap().iface, "eth0");
+        assert_eq!(explanation.bypassed_subnet, None);
+
+        let query = RouteQuery::new("172.16.0.1".parse().unwrap());
+        let explanation = explain(&route_table, &subnets, &query).unwrap();
+        assert_eq!(explanation.overridden[0].reason, "higher metric (200 > 50)");
+    }
+}
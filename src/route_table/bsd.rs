@@ -0,0 +1,140 @@
+use crate::route_table::{IpVersion, ParsedRoutes, mac::parse_netstat_sections};
+use anyhow::Result;
+
+/// Parses the output of `netstat -rn -f inet` or `netstat -rn -f inet6` on
+/// FreeBSD, OpenBSD, NetBSD and DragonFly BSD and returns the routes it
+/// contains.
+///
+/// The output has the sections of macOS (see `parse_netstat_sections`), with
+/// columns that vary between the systems: FreeBSD prints `Netif Expire`,
+/// OpenBSD `Refs Use Mtu Prio Iface` and NetBSD `Refs Use Mtu Interface`.
+/// Destinations may be abbreviated (`127/8`, `192.168.1/24`) and gateways of
+/// directly connected routes are `link#N`, a link-layer address or an
+/// interface name. Routes before a section title are of `ip_version`.
+///
+/// # Errors
+///
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_bsd_route_output(
+    output: &str,
+    ip_version: IpVersion,
+    strict: bool,
+) -> Result<ParsedRoutes> {
+    parse_netstat_sections(output, Some(ip_version), strict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::RouteEntry;
+
+    fn parse(output: &str, ip_version: IpVersion) -> Vec<RouteEntry> {
+        let parsed = parse_bsd_route_output(output, ip_version.clone(), true).unwrap();
+        match ip_version {
+            IpVersion::IPv4 => parsed.route_table.ipv4_routes,
+            IpVersion::IPv6 => parsed.route_table.ipv6_routes,
+        }
+    }
+
+    fn prefixes(routes: &[RouteEntry]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|route| route.prefix().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_freebsd_route_output() {
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/freebsd-inet.txt"),
+            IpVersion::IPv4,
+        );
+        assert_eq!(
+            prefixes(&routes),
+            [
+                "0.0.0.0/0",
+                "127.0.0.1/32",
+                "192.168.1.0/24",
+                "192.168.1.20/32"
+            ]
+        );
+        assert_eq!(
+            (routes[2].gateway.as_str(), routes[2].iface.as_str()),
+            ("link#1", "em0")
+        );
+
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/freebsd-inet6.txt"),
+            IpVersion::IPv6,
+        );
+        assert_eq!(routes.len(), 10);
+        assert_eq!(routes[1].gateway, "fe80::1%em0");
+        assert_eq!(routes[7].prefix().unwrap().to_string(), "fe80::/64");
+    }
+
+    #[test]
+    fn test_parse_openbsd_route_output() {
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/openbsd-inet.txt"),
+            IpVersion::IPv4,
+        );
+        assert_eq!(routes.len(), 8);
+        let default = &routes[0];
+        assert_eq!((default.refs, default.use_count), (Some(5), Some(623)));
+        // `Mtu` is `-` unless set, `Prio` is read as the metric
+        assert_eq!((default.mtu, default.metric), (None, Some(8)));
+        assert_eq!(routes[2].mtu, Some(32768));
+        assert_eq!(routes[4].prefix().unwrap().to_string(), "192.168.1.0/24");
+        assert_eq!(routes[5].gateway, "00:11:22:33:44:55");
+
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/openbsd-inet6.txt"),
+            IpVersion::IPv6,
+        );
+        assert_eq!(routes.len(), 10);
+        assert_eq!(routes[1].refs, Some(10));
+        assert_eq!(routes[8].prefix().unwrap().to_string(), "ff01::/32");
+    }
+
+    #[test]
+    fn test_parse_netbsd_route_output() {
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/netbsd-inet.txt"),
+            IpVersion::IPv4,
+        );
+        assert_eq!(
+            prefixes(&routes),
+            [
+                "0.0.0.0/0",
+                "10.0.2.0/24",
+                "10.0.2.2/32",
+                "10.0.2.15/32",
+                "127.0.0.0/8",
+                "127.0.0.1/32"
+            ]
+        );
+        assert_eq!((routes[0].refs, routes[0].use_count), (None, None));
+        assert_eq!(
+            (routes[4].mtu, routes[4].iface.as_str()),
+            (Some(33624), "lo0")
+        );
+        assert_eq!(routes[5].gateway, "lo0");
+
+        let routes = parse(
+            include_str!("../../tests/fixtures/netstat/netbsd-inet6.txt"),
+            IpVersion::IPv6,
+        );
+        assert_eq!(routes.len(), 7);
+        assert_eq!(routes[4].iface, "wm0");
+    }
+
+    #[test]
+    fn test_output_without_section_title() {
+        let output = "\
+Destination        Gateway            Flags   Refs      Use   Mtu  Prio Iface
+default            192.168.1.1        UGS        5      623     -     8 em0
+";
+        assert_eq!(parse(output, IpVersion::IPv4).len(), 1);
+    }
+}
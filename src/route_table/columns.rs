@@ -0,0 +1,223 @@
+use std::net::IpAddr;
+
+use crate::route_table::{IpVersion, RouteEntry};
+
+/// A column of route command output such as `netstat -rn`, `netstat -rnv`
+/// or `route -n` on Linux, macOS and the BSDs, identified by its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Destination,
+    Gateway,
+    Genmask,
+    Flags,
+    Iface,
+    Expire,
+    Refs,
+    Use,
+    Mss,
+    Window,
+    Irtt,
+    Metric,
+    Mtu,
+}
+
+impl Column {
+    /// Returns the column with the header `name`. The systems name some
+    /// columns differently, e.g. `Iface`, `Netif` and `Interface` or `Ref`
+    /// and `Refs`. OpenBSD's route priority `Prio` is read as the metric:
+    /// lower values are preferred for both.
+    pub fn from_header(name: &str) -> Option<Column> {
+        let column = match name.to_ascii_lowercase().as_str() {
+            "destination" => Column::Destination,
+            "gateway" => Column::Gateway,
+            "genmask" => Column::Genmask,
+            "flags" => Column::Flags,
+            "iface" | "netif" | "interface" => Column::Iface,
+            "expire" => Column::Expire,
+            "ref" | "refs" => Column::Refs,
+            "use" => Column::Use,
+            "mss" => Column::Mss,
+            "window" => Column::Window,
+            "irtt" => Column::Irtt,
+            "metric" | "prio" => Column::Metric,
+            "mtu" => Column::Mtu,
+            _ => return None,
+        };
+        Some(column)
+    }
+}
+
+/// The layout of a route table, built from its header line: which column is
+/// at which position. Unknown columns are kept as `None` so the positions of
+/// the known ones stay right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMap {
+    columns: Vec<Option<Column>>,
+}
+
+impl ColumnMap {
+    /// Returns the column map of a header line such as
+    /// `Destination Gateway Genmask Flags MSS Window irtt Iface`, or `None`
+    /// if the line is not a route table header. A header starts with the
+    /// destination and names the gateway and the interface.
+    pub fn from_header(line: &str) -> Option<Self> {
+        let columns: Vec<Option<Column>> =
+            line.split_whitespace().map(Column::from_header).collect();
+        let map = Self { columns };
+        if map.columns.first() != Some(&Some(Column::Destination))
+            || map.position(Column::Gateway).is_none()
+            || map.position(Column::Iface).is_none()
+        {
+            return None;
+        }
+        Some(map)
+    }
+
+    fn position(&self, column: Column) -> Option<usize> {
+        self.columns.iter().position(|other| *other == Some(column))
+    }
+
+    /// Parses a route line laid out as described by this map.
+    ///
+    /// Trailing columns may be empty, such as `Expire` for routes that do not
+    /// expire; all columns up to the interface and genmask must be present.
+    /// Numeric columns that do not hold a number, such as `-`, are left
+    /// unset.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason if columns are missing or the destination, gateway
+    /// or genmask is invalid.
+    pub fn parse(&self, line: &str, ip_version: IpVersion) -> Result<RouteEntry, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let required = [
+            Column::Destination,
+            Column::Gateway,
+            Column::Flags,
+            Column::Iface,
+            Column::Genmask,
+        ]
+        .iter()
+        .filter_map(|column| self.position(*column))
+        .max()
+        .unwrap_or(0)
+            + 1;
+        if parts.len() < required {
+            return Err(format!(
+                "expected at least {} columns, found {}",
+                required,
+                parts.len()
+            ));
+        }
+
+        let mut route = RouteEntry {
+            ip_version,
+            ..Default::default()
+        };
+        for (column, value) in self.columns.iter().zip(&parts) {
+            let value = value.to_string();
+            match column {
+                Some(Column::Destination) => route.destination = value,
+                Some(Column::Gateway) => route.gateway = value,
+                Some(Column::Genmask) => route.genmask = Some(value),
+                Some(Column::Flags) => route.flags = value,
+                Some(Column::Iface) => route.iface = value,
+                Some(Column::Expire) => route.expire = Some(value),
+                Some(Column::Refs) => route.refs = value.parse().ok(),
+                Some(Column::Use) => route.use_count = value.parse().ok(),
+                Some(Column::Mss) => route.mss = value.parse().ok(),
+                Some(Column::Window) => route.window = value.parse().ok(),
+                Some(Column::Irtt) => route.irtt = value.parse().ok(),
+                Some(Column::Metric) => route.metric = value.parse().ok(),
+                Some(Column::Mtu) => route.mtu = value.parse().ok(),
+                None => {}
+            }
+        }
+
+        // macOS gateways may be link-layer addresses or `link#N`, so only a
+        // genmask (Linux) implies an IP gateway.
+        if let Some(genmask) = &route.genmask {
+            if genmask.parse::<IpAddr>().is_err() {
+                return Err(format!("invalid genmask '{}'", genmask));
+            }
+            if route.gateway.parse::<IpAddr>().is_err() {
+                return Err(format!("invalid gateway '{}'", route.gateway));
+            }
+        }
+        if route.prefix().is_none() {
+            return Err(format!("invalid destination '{}'", route.destination));
+        }
+        Ok(route)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_detection() {
+        assert!(
+            ColumnMap::from_header("Destination Gateway Genmask Flags MSS Window irtt Iface")
+                .is_some()
+        );
+        assert!(ColumnMap::from_header("Destination Gateway Flags Netif Expire").is_some());
+        assert!(ColumnMap::from_header("default 192.168.1.1 UGScg en0").is_none());
+        assert!(ColumnMap::from_header("Kernel IP routing table").is_none());
+        // Without an interface column, routes cannot be read
+        assert!(ColumnMap::from_header("Destination Gateway Flags").is_none());
+    }
+
+    #[test]
+    fn test_parse_by_header() {
+        // `route -n` on Linux
+        let map = ColumnMap::from_header("Destination Gateway Genmask Flags Metric Ref Use Iface")
+            .unwrap();
+        let route = map
+            .parse(
+                "0.0.0.0 192.168.1.1 0.0.0.0 UG 100 2 47 eth0",
+                IpVersion::IPv4,
+            )
+            .unwrap();
+        assert_eq!(route.metric, Some(100));
+        assert_eq!(route.refs, Some(2));
+        assert_eq!(route.use_count, Some(47));
+        assert_eq!(route.iface, "eth0");
+
+        // `netstat -rnv` on older macOS, with columns in another order and a
+        // missing trailing Expire
+        let map =
+            ColumnMap::from_header("Destination Gateway Flags Refs Use Mtu Netif Expire Prio")
+                .unwrap();
+        let route = map
+            .parse("default 192.168.1.1 UGSc 12 3400 1500 en0", IpVersion::IPv4)
+            .unwrap();
+        assert_eq!(route.iface, "en0");
+        assert_eq!((route.refs, route.use_count), (Some(12), Some(3400)));
+        assert_eq!(route.mtu, Some(1500));
+        assert_eq!(route.expire, None);
+
+        let map = ColumnMap::from_header("Destination Gateway Genmask Flags MSS Window irtt Iface")
+            .unwrap();
+        let route = map
+            .parse(
+                "10.0.0.0 0.0.0.0 255.0.0.0 U 1460 0 0 eth1",
+                IpVersion::IPv4,
+            )
+            .unwrap();
+        assert_eq!(
+            (route.mss, route.window, route.irtt),
+            (Some(1460), Some(0), Some(0))
+        );
+        assert_eq!(route.prefix().unwrap().to_string(), "10.0.0.0/8");
+
+        assert_eq!(
+            map.parse("10.0.0.0 0.0.0.0 255.0.0.0 U 0 0 0", IpVersion::IPv4),
+            Err("expected at least 8 columns, found 7".to_string())
+        );
+        assert_eq!(
+            map.parse("10.0.0.0 link#4 255.0.0.0 U 0 0 0 eth1", IpVersion::IPv4),
+            Err("invalid gateway 'link#4'".to_string())
+        );
+    }
+}
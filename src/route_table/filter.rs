@@ -0,0 +1,193 @@
+use std::net::IpAddr;
+
+use crate::prefix::Prefix;
+use crate::route_table::{RouteEntry, RouteTable};
+
+/// Selects routes by destination, next hop and attributes. Every condition
+/// that is set must match; an empty filter matches every route.
+///
+/// The fields double as the filter options of the `routes` command.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct RouteFilter {
+    /// Only routes whose destination is contained in or contains this prefix
+    #[clap(long, value_name = "PREFIX")]
+    pub dst: Option<Prefix>,
+
+    /// Only routes via this gateway
+    #[clap(long, value_name = "GATEWAY")]
+    pub via: Option<IpAddr>,
+
+    /// Only routes out of this interface
+    #[clap(long, value_name = "IFACE")]
+    pub dev: Option<String>,
+
+    /// Only routes installed by this protocol, e.g. "kernel", "dhcp" or "bgp"
+    #[clap(long)]
+    pub proto: Option<String>,
+
+    /// Only routes with this scope, e.g. "global", "link" or "host"
+    #[clap(long)]
+    pub scope: Option<String>,
+
+    /// Only default routes
+    #[clap(long)]
+    pub default_only: bool,
+
+    /// Leave out routes to a single address
+    #[clap(long)]
+    pub exclude_host_routes: bool,
+}
+
+impl RouteFilter {
+    /// Returns `true` if `route` passes every condition of this filter.
+    ///
+    /// Routes from sources that do not report the protocol or scope, such as
+    /// `netstat`, never match a `proto` or `scope` condition.
+    pub fn matches(&self, route: &RouteEntry) -> bool {
+        if let Some(dst) = &self.dst
+            && !route.prefix().is_some_and(|prefix| prefix.overlaps(dst))
+        {
+            return false;
+        }
+        if let Some(via) = self.via {
+            // Strip a scope suffix, e.g. `fe80::1%en0`
+            let gateway = route.gateway.split('%').next().unwrap_or_default();
+            if gateway.parse::<IpAddr>().ok() != Some(via) {
+                return false;
+            }
+        }
+        if let Some(dev) = &self.dev
+            && &route.iface != dev
+        {
+            return false;
+        }
+        if let Some(proto) = &self.proto
+            && route.proto.as_ref() != Some(proto)
+        {
+            return false;
+        }
+        if let Some(scope) = &self.scope
+            && route.scope.as_ref() != Some(scope)
+        {
+            return false;
+        }
+        if self.default_only && !route.is_default() {
+            return false;
+        }
+        if self.exclude_host_routes && route.prefix().is_some_and(|prefix| prefix.is_host()) {
+            return false;
+        }
+        true
+    }
+
+    /// Removes all routes from `route_table` that do not match. The policy
+    /// rules are kept.
+    pub fn apply(&self, route_table: &mut RouteTable) {
+        route_table.ipv4_routes.retain(|route| self.matches(route));
+        route_table.ipv6_routes.retain(|route| self.matches(route));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::IpVersion;
+
+    fn route(destination: &str, gateway: &str, iface: &str, proto: &str) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            iface: iface.to_string(),
+            proto: Some(proto.to_string()),
+            scope: Some("global".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn sample_table() -> RouteTable {
+        let mut route_table = RouteTable::new();
+        for route in [
+            route("default", "192.168.1.1", "eth0", "dhcp"),
+            route("10.0.0.0/8", "10.8.0.1", "wg0", "static"),
+            route("10.20.0.0/16", "10.8.0.1", "wg0", "bgp"),
+            route("10.20.30.40/32", "10.8.0.1", "wg0", "bgp"),
+            route("192.168.1.0/24", "0.0.0.0", "eth0", "kernel"),
+        ] {
+            route_table.add_route(route);
+        }
+        route_table.add_route(RouteEntry {
+            destination: "default".to_string(),
+            gateway: "fe80::1%eth0".to_string(),
+            iface: "eth0".to_string(),
+            ip_version: IpVersion::IPv6,
+            ..Default::default()
+        });
+        route_table
+    }
+
+    fn filtered(filter: RouteFilter) -> Vec<String> {
+        let mut route_table = sample_table();
+        filter.apply(&mut route_table);
+        route_table
+            .ipv4_routes
+            .iter()
+            .chain(&route_table.ipv6_routes)
+            .map(|route| format!("{} {}", route.destination, route.iface))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_by_destination() {
+        let filter = RouteFilter {
+            dst: Some("10.20.0.0/12".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered(filter),
+            [
+                "default eth0",
+                "10.0.0.0/8 wg0",
+                "10.20.0.0/16 wg0",
+                "10.20.30.40/32 wg0"
+            ]
+        );
+
+        let filter = RouteFilter {
+            dst: Some("10.20.0.0/12".parse().unwrap()),
+            exclude_host_routes: true,
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter).len(), 3);
+    }
+
+    #[test]
+    fn test_filter_by_next_hop_and_attributes() {
+        let filter = RouteFilter {
+            via: Some("10.8.0.1".parse().unwrap()),
+            proto: Some("bgp".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), ["10.20.0.0/16 wg0", "10.20.30.40/32 wg0"]);
+
+        let filter = RouteFilter {
+            via: Some("fe80::1".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), ["default eth0"]);
+
+        let filter = RouteFilter {
+            dev: Some("eth0".to_string()),
+            default_only: true,
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), ["default eth0", "default eth0"]);
+
+        // The IPv6 route has no scope, so it never matches a scope filter
+        let filter = RouteFilter {
+            scope: Some("global".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter).len(), 5);
+        assert_eq!(filtered(RouteFilter::default()).len(), 6);
+    }
+}
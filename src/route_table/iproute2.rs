@@ -0,0 +1,553 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use crate::prefix::Prefix;
+use crate::probe::SystemProbe;
+use crate::route_table::{
+    IpVersion, ParseError, ParseOptions, ParsedRoutes, RouteEntry, RouteTable,
+};
+
+/// Route types that `ip route` prints before the destination.
+const ROUTE_TYPES: [&str; 10] = [
+    "unicast",
+    "local",
+    "broadcast",
+    "multicast",
+    "anycast",
+    "blackhole",
+    "unreachable",
+    "prohibit",
+    "throw",
+    "nat",
+];
+
+/// Keywords of `ip route` text output that are followed by a value. Any
+/// other word after the destination is a flag such as `onlink`.
+const KEYS_WITH_VALUE: [&str; 31] = [
+    "via",
+    "dev",
+    "table",
+    "proto",
+    "scope",
+    "src",
+    "from",
+    "metric",
+    "mtu",
+    "advmss",
+    "hoplimit",
+    "weight",
+    "pref",
+    "expires",
+    "realm",
+    "realms",
+    "rtt",
+    "rttvar",
+    "reordering",
+    "window",
+    "cwnd",
+    "initcwnd",
+    "initrwnd",
+    "ssthresh",
+    "features",
+    "quickack",
+    "congctl",
+    "rto_min",
+    "nhid",
+    "tos",
+    "error",
+];
+
+/// A gateway of another address family, e.g. `via inet6 fe80::1` for an
+/// IPv4 route.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+struct Via {
+    host: Option<String>,
+}
+
+/// A next hop of a multipath route.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+struct NextHop {
+    gateway: Option<String>,
+    via: Option<Via>,
+    dev: Option<String>,
+}
+
+/// A route as printed by `ip -j -d route show`. The text output is parsed
+/// into the same structure.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+struct IpRoute {
+    #[serde(rename = "type")]
+    route_type: Option<String>,
+    dst: String,
+    gateway: Option<String>,
+    via: Option<Via>,
+    dev: Option<String>,
+    table: Option<String>,
+    protocol: Option<String>,
+    scope: Option<String>,
+    prefsrc: Option<String>,
+    metric: Option<u32>,
+    /// `[{"mtu": 1400}]`; a locked value is printed as `["lock", 1400]`.
+    metrics: Vec<BTreeMap<String, serde_json::Value>>,
+    nexthops: Vec<NextHop>,
+}
+
+impl IpRoute {
+    fn mtu(&self) -> Option<u32> {
+        let value = self.metrics.iter().find_map(|metrics| metrics.get("mtu"))?;
+        let value = match value {
+            serde_json::Value::Array(values) => values.last()?,
+            value => value,
+        };
+        value.as_u64().and_then(|mtu| u32::try_from(mtu).ok())
+    }
+
+    /// Returns the address family of the route, from its destination or
+    /// same-family gateway. `None` for a default route without one, such as
+    /// `default via inet6 fe80::1`.
+    fn family(&self) -> Option<IpVersion> {
+        let addr = match self.dst.parse::<Prefix>() {
+            Ok(prefix) => prefix.network(),
+            Err(_) => self
+                .gateway
+                .iter()
+                .chain(self.nexthops.iter().filter_map(|hop| hop.gateway.as_ref()))
+                .find_map(|gateway| gateway.parse::<IpAddr>().ok())?,
+        };
+        Some(match addr {
+            IpAddr::V4(_) => IpVersion::IPv4,
+            IpAddr::V6(_) => IpVersion::IPv6,
+        })
+    }
+
+    /// Converts the route into one `RouteEntry` per next hop, in the style
+    /// of `get_netlink_routes`: host routes as `/32` or `/128` prefixes,
+    /// unset gateways as the unspecified address and `-` for no interface.
+    /// Routes of unknown family are of `ip_version`.
+    fn into_entries(self, ip_version: &IpVersion) -> Vec<RouteEntry> {
+        let ip_version = &self.family().unwrap_or_else(|| ip_version.clone());
+        let prefix = if self.dst == "default" {
+            None
+        } else {
+            self.dst.parse::<Prefix>().ok()
+        };
+        let destination = match prefix {
+            Some(prefix) => prefix.to_string(),
+            None => self.dst.clone(),
+        };
+        let genmask = match ip_version {
+            IpVersion::IPv4 => Some(
+                prefix
+                    .map(|prefix| prefix.netmask().to_string())
+                    .unwrap_or_else(|| "0.0.0.0".to_string()),
+            ),
+            IpVersion::IPv6 => None,
+        };
+        let host_route = prefix.is_some_and(|prefix| prefix.is_host());
+        let route_type = self
+            .route_type
+            .clone()
+            .unwrap_or_else(|| "unicast".to_string());
+        let reject = matches!(
+            route_type.as_str(),
+            "blackhole" | "unreachable" | "prohibit"
+        );
+        let mtu = self.mtu();
+
+        let mut next_hops = self.nexthops.clone();
+        if next_hops.is_empty() {
+            next_hops.push(NextHop {
+                gateway: self.gateway.clone(),
+                via: self.via.clone(),
+                dev: self.dev.clone(),
+            });
+        }
+
+        let unspecified = match ip_version {
+            IpVersion::IPv4 => "0.0.0.0",
+            IpVersion::IPv6 => "::",
+        };
+        next_hops
+            .into_iter()
+            .map(|next_hop| {
+                let gateway = next_hop
+                    .gateway
+                    .or_else(|| next_hop.via.and_then(|via| via.host));
+                let mut flags = String::from(if reject { "!" } else { "U" });
+                if gateway.is_some() {
+                    flags.push('G');
+                }
+                if host_route {
+                    flags.push('H');
+                }
+
+                RouteEntry {
+                    destination: destination.clone(),
+                    gateway: gateway.unwrap_or_else(|| unspecified.to_string()),
+                    flags,
+                    iface: next_hop.dev.unwrap_or_else(|| "-".to_string()),
+                    ip_version: ip_version.clone(),
+                    genmask: genmask.clone(),
+                    table: Some(self.table.clone().unwrap_or_else(|| "main".to_string())),
+                    metric: self.metric,
+                    proto: self.protocol.clone(),
+                    scope: self.scope.clone(),
+                    src: self.prefsrc.clone(),
+                    route_type: Some(route_type.clone()),
+                    mtu,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the address family of each of `routes` for the routes without one
+/// of their own: that of the closest route before it that has one, as in a
+/// dump of `ip route` followed by `ip -6 route`, else that of the first such
+/// route after it, else `ip_version`.
+fn dump_families(routes: &[IpRoute], ip_version: IpVersion) -> Vec<IpVersion> {
+    let mut current = routes
+        .iter()
+        .find_map(IpRoute::family)
+        .unwrap_or(ip_version);
+    routes
+        .iter()
+        .map(|route| {
+            if let Some(family) = route.family() {
+                current = family;
+            }
+            current.clone()
+        })
+        .collect()
+}
+
+/// Parses the output of `ip -j -d route show`. Routes whose address family
+/// cannot be told from their own addresses, such as `default dev wg0`, are of
+/// the family of the other routes in the output, or of `ip_version`.
+///
+/// # Errors
+///
+/// Returns an error if the output is not a JSON array of routes.
+pub fn parse_ip_json_output(output: &str, ip_version: IpVersion) -> Result<RouteTable> {
+    let routes: Vec<IpRoute> =
+        serde_json::from_str(output).context("Invalid JSON output of ip route")?;
+    let families = dump_families(&routes, ip_version);
+    let mut route_table = RouteTable::new();
+    for (route, family) in routes.into_iter().zip(families) {
+        for entry in route.into_entries(&family) {
+            route_table.add_route(entry);
+        }
+    }
+    Ok(route_table)
+}
+
+/// Reads the `key value` pairs and flags of an `ip route` line, starting
+/// after the destination or `nexthop`, into `route` or `next_hop`.
+fn parse_attributes<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+    mut set: impl FnMut(&'a str, &'a str),
+) -> Result<(), String> {
+    while let Some(word) = words.next() {
+        if !KEYS_WITH_VALUE.contains(&word) {
+            // A flag such as `onlink` or `linkdown`
+            continue;
+        }
+        let mut key = word;
+        let mut value = words
+            .next()
+            .ok_or_else(|| format!("missing value after '{}'", word))?;
+        // `via inet6 fe80::1`, a gateway of the other address family that
+        // JSON output has as `via.host`, and `mtu lock 1400`
+        if matches!(value, "inet" | "inet6" | "lock") {
+            if word == "via" {
+                key = "via-host";
+            }
+            value = words
+                .next()
+                .ok_or_else(|| format!("missing value after '{} {}'", word, value))?;
+        }
+        set(key, value);
+    }
+    Ok(())
+}
+
+/// Parses a route line of `ip route show` text output, such as
+/// `default via 192.168.1.1 dev eth0 proto dhcp src 192.168.1.10 metric 100`.
+fn parse_text_route(line: &str) -> Result<IpRoute, String> {
+    let mut words = line.split_whitespace().peekable();
+    let mut route = IpRoute::default();
+    if let Some(word) = words.next_if(|word| ROUTE_TYPES.contains(word)) {
+        route.route_type = Some(word.to_string());
+    }
+    let dst = words.next().ok_or("missing destination")?;
+    if dst != "default" && dst.parse::<Prefix>().is_err() {
+        return Err(format!("invalid destination '{}'", dst));
+    }
+    route.dst = dst.to_string();
+
+    let mut mtu = None;
+    parse_attributes(&mut words, |key, value| match key {
+        "via" => route.gateway = Some(value.to_string()),
+        "via-host" => {
+            route.via = Some(Via {
+                host: Some(value.to_string()),
+            })
+        }
+        "dev" => route.dev = Some(value.to_string()),
+        "table" => route.table = Some(value.to_string()),
+        "proto" => route.protocol = Some(value.to_string()),
+        "scope" => route.scope = Some(value.to_string()),
+        "src" => route.prefsrc = Some(value.to_string()),
+        "metric" => route.metric = value.parse().ok(),
+        "mtu" => mtu = value.parse::<u64>().ok(),
+        _ => {}
+    })?;
+    if let Some(mtu) = mtu {
+        route
+            .metrics
+            .push(BTreeMap::from([("mtu".to_string(), mtu.into())]));
+    }
+    Ok(route)
+}
+
+/// Parses a `nexthop via 192.168.1.5 dev eth0 weight 1` line of a multipath
+/// route.
+fn parse_text_next_hop(line: &str) -> Result<NextHop, String> {
+    let mut words = line.split_whitespace().skip(1);
+    let mut next_hop = NextHop::default();
+    parse_attributes(&mut words, |key, value| match key {
+        "via" => next_hop.gateway = Some(value.to_string()),
+        "via-host" => {
+            next_hop.via = Some(Via {
+                host: Some(value.to_string()),
+            })
+        }
+        "dev" => next_hop.dev = Some(value.to_string()),
+        _ => {}
+    })?;
+    Ok(next_hop)
+}
+
+/// Parses the text output of `ip route show` (with or without `-d`). Routes
+/// are assigned an address family as by `parse_ip_json_output`.
+///
+/// The next hops of a multipath route follow it on indented lines starting
+/// with `nexthop`; each becomes a separate `RouteEntry`. Lines that cannot be
+/// parsed are skipped and recorded with their line number in
+/// `ParsedRoutes::skipped`.
+///
+/// # Errors
+///
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_ip_text_output(
+    output: &str,
+    ip_version: IpVersion,
+    strict: bool,
+) -> Result<ParsedRoutes> {
+    let mut parsed = ParsedRoutes::default();
+    let mut routes: Vec<IpRoute> = Vec::new();
+
+    for (index, line) in output.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let result = if trimmed.starts_with("nexthop") {
+            match routes.last_mut() {
+                Some(route) => parse_text_next_hop(trimmed).map(|hop| route.nexthops.push(hop)),
+                None => Err("next hop without a route".to_string()),
+            }
+        } else {
+            parse_text_route(trimmed).map(|route| routes.push(route))
+        };
+        if let Err(reason) = result {
+            parsed.skip(
+                ParseError {
+                    line_number: index + 1,
+                    line: trimmed.to_string(),
+                    reason,
+                },
+                strict,
+            )?;
+        }
+    }
+
+    let families = dump_families(&routes, ip_version);
+    for (route, family) in routes.into_iter().zip(families) {
+        for entry in route.into_entries(&family) {
+            parsed.route_table.add_route(entry);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Runs `ip` with `args` and returns its output.
+fn run_ip(probe: &dyn SystemProbe, args: &[&str]) -> Result<String> {
+    let output = probe.run("ip", args)?;
+    if !output.success {
+        return Err(output.failure(&format!("ip {} failed", args.join(" "))));
+    }
+    Ok(output.stdout)
+}
+
+/// Reads the routes of all tables for both address families with iproute2,
+/// run by `probe`.
+///
+/// `ip -j -d route show table all` is used where iproute2 supports JSON
+/// output; older versions fall back to parsing the text output, which is
+/// skipped or fails per line as described by `options`. Policy routing rules
+/// are not read.
+///
+/// # Errors
+///
+/// Returns an error if `ip` cannot be run or its output cannot be parsed.
+pub fn get_iproute2_routes(probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+    let mut route_table = RouteTable::new();
+    for (ip_version, family) in [(IpVersion::IPv4, "-4"), (IpVersion::IPv6, "-6")] {
+        let routes = match run_ip(
+            probe,
+            &["-j", "-d", family, "route", "show", "table", "all"],
+        ) {
+            Ok(output) => parse_ip_json_output(&output, ip_version)?,
+            Err(_) => {
+                let output = run_ip(probe, &["-d", family, "route", "show", "table", "all"])?;
+                parse_ip_text_output(&output, ip_version, options.strict)?
+                    .into_route_table(options.verbose)
+            }
+        };
+        route_table.ipv4_routes.extend(routes.ipv4_routes);
+        route_table.ipv6_routes.extend(routes.ipv6_routes);
+    }
+    Ok(route_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTE_IPV4_JSON: &str = include_str!("../../tests/fixtures/iproute2/route-ipv4.json");
+    const ROUTE_IPV6_JSON: &str = include_str!("../../tests/fixtures/iproute2/route-ipv6.json");
+    const ROUTE_IPV4_TEXT: &str = include_str!("../../tests/fixtures/iproute2/route-ipv4.txt");
+    const ROUTE_IPV6_TEXT: &str = include_str!("../../tests/fixtures/iproute2/route-ipv6.txt");
+    const ROUTE_PLAIN_TEXT: &str = include_str!("../../tests/fixtures/iproute2/route-plain.txt");
+
+    fn find<'a>(routes: &'a [RouteEntry], destination: &str) -> Vec<&'a RouteEntry> {
+        routes
+            .iter()
+            .filter(|route| route.destination == destination)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_json_output() {
+        let table = parse_ip_json_output(ROUTE_IPV4_JSON, IpVersion::IPv4).unwrap();
+        let routes = &table.ipv4_routes;
+        assert_eq!(routes.len(), 11);
+
+        let default = find(routes, "default")[0];
+        assert_eq!(default.gateway, "192.168.1.1");
+        assert_eq!(default.flags, "UG");
+        assert_eq!(default.proto.as_deref(), Some("dhcp"));
+        assert_eq!(default.src.as_deref(), Some("192.168.1.10"));
+        assert_eq!((default.metric, default.mtu), (Some(100), Some(1400)));
+        assert_eq!(default.genmask.as_deref(), Some("0.0.0.0"));
+
+        let blackhole = find(routes, "10.9.0.0/16")[0];
+        assert_eq!(blackhole.route_type.as_deref(), Some("blackhole"));
+        assert_eq!(
+            (blackhole.flags.as_str(), blackhole.iface.as_str()),
+            ("!", "-")
+        );
+
+        let multipath = find(routes, "10.20.0.0/16");
+        let gateways: Vec<&str> = multipath
+            .iter()
+            .map(|route| route.gateway.as_str())
+            .collect();
+        assert_eq!(gateways, ["192.168.1.5", "192.168.1.6"]);
+
+        assert_eq!(
+            find(routes, "10.30.0.0/16")[0].table.as_deref(),
+            Some("100")
+        );
+        let host = find(routes, "127.0.0.1/32")[0];
+        assert_eq!(
+            (host.flags.as_str(), host.table.as_deref()),
+            ("UH", Some("local"))
+        );
+
+        let table = parse_ip_json_output(ROUTE_IPV6_JSON, IpVersion::IPv6).unwrap();
+        let route = find(&table.ipv6_routes, "2001:db8::/32")[0];
+        assert_eq!(
+            (route.gateway.as_str(), route.metric),
+            ("fd00::1", Some(50))
+        );
+        assert_eq!(route.genmask, None);
+
+        assert!(parse_ip_json_output("Option \"-j\" is unknown", IpVersion::IPv4).is_err());
+    }
+
+    #[test]
+    fn test_text_output_matches_json() {
+        for (json, text, ip_version) in [
+            (ROUTE_IPV4_JSON, ROUTE_IPV4_TEXT, IpVersion::IPv4),
+            (ROUTE_IPV6_JSON, ROUTE_IPV6_TEXT, IpVersion::IPv6),
+        ] {
+            let from_json = parse_ip_json_output(json, ip_version.clone()).unwrap();
+            let from_text = parse_ip_text_output(text, ip_version, true).unwrap();
+            assert!(from_text.skipped.is_empty());
+            assert_eq!(from_text.route_table.ipv4_routes, from_json.ipv4_routes);
+            assert_eq!(from_text.route_table.ipv6_routes, from_json.ipv6_routes);
+        }
+    }
+
+    #[test]
+    fn test_parse_plain_text_output() {
+        let parsed = parse_ip_text_output(ROUTE_PLAIN_TEXT, IpVersion::IPv4, true).unwrap();
+        let routes = &parsed.route_table.ipv4_routes;
+        assert_eq!(routes.len(), 7);
+
+        let default = find(routes, "default")[0];
+        assert_eq!(default.iface, "wlan0");
+        assert_eq!(default.route_type.as_deref(), Some("unicast"));
+        assert_eq!(default.table.as_deref(), Some("main"));
+        assert_eq!(default.metric, Some(600));
+
+        assert_eq!(find(routes, "10.99.0.0/16")[0].flags, "!");
+        let ifaces: Vec<&str> = find(routes, "10.50.0.0/16")
+            .iter()
+            .map(|route| route.iface.as_str())
+            .collect();
+        assert_eq!(ifaces, ["tun0", "wlan0"]);
+        assert_eq!(find(routes, "172.16.0.0/12")[0].gateway, "fe80::1");
+    }
+
+    #[test]
+    fn test_text_output_errors() {
+        let output = "default via 192.168.1.1 dev eth0\n\tnexthop via\nnot-a-route dev eth0\n";
+        let parsed = parse_ip_text_output(output, IpVersion::IPv4, false).unwrap();
+        assert_eq!(parsed.route_table.ipv4_routes.len(), 1);
+        let reasons: Vec<(usize, &str)> = parsed
+            .skipped
+            .iter()
+            .map(|error| (error.line_number, error.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                (2, "missing value after 'via'"),
+                (3, "invalid destination 'not-a-route'")
+            ]
+        );
+
+        assert!(parse_ip_text_output("\tnexthop via 10.0.0.1", IpVersion::IPv4, true).is_err());
+    }
+}
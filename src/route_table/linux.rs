@@ -1,73 +1,126 @@
-use crate::route_table::{IpVersion, RouteTable, parse_route_line};
-use anyhow::{Result, anyhow};
+use crate::route_table::{IpVersion, ParseError, ParsedRoutes, columns::ColumnMap};
+use anyhow::Result;
 
-/// Parses the output of the `netstat -rn` command on Linux and returns a
-/// `RouteTable` containing the routes.
+/// Parses the output of the `netstat -rn` command on Linux and returns the
+/// routes it contains.
 ///
-/// The function takes a string slice containing the output of the `netstat -rn`
-/// command and splits it into lines. It then iterates over the lines, parsing
-/// each line with `parse_route_line` and adding the resulting `RouteEntry` to
-/// the `RouteTable`. The `RouteTable` is then returned.
+/// The output starts with the title "Kernel IP routing table" and the column
+/// header "Destination Gateway Genmask ...", followed by one route per line.
+/// The columns of the routes are read by the names in the header, so the
+/// output of `netstat -rne` and `route -n` is understood as well. Empty
+/// lines are ignored.
 ///
-/// The function skips over empty lines and sections that do not contain the
-/// header line "Destination", which is the header for the IPv4 routes. It also
-/// skips over the header line itself.
-///
-/// If an error occurs while parsing a line, the function returns an error.
+/// Any other line, including a line before the header or a route that
+/// `ColumnMap::parse` rejects, is skipped and recorded with its line number
+/// in `ParsedRoutes::skipped`.
 ///
 /// # Errors
 ///
-/// If an error occurs while parsing a line, the function returns an error.
-pub fn parse_linux_route_output(output: &str) -> Result<RouteTable> {
-    let mut route_table = RouteTable::new();
-    let lines: Vec<&str> = output.lines().collect();
-
-    let mut header_parsed = false;
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_linux_route_output(output: &str, strict: bool) -> Result<ParsedRoutes> {
+    let mut parsed = ParsedRoutes::default();
+    let mut columns: Option<ColumnMap> = None;
 
-    for line in lines {
+    for (index, line) in output.lines().enumerate() {
         let trimmed = line.trim();
 
-        if trimmed.is_empty() {
+        if trimmed.is_empty() || trimmed.starts_with("Kernel IP routing table") {
             continue;
         }
 
-        if trimmed.starts_with("Destination") {
-            header_parsed = true;
+        if let Some(map) = ColumnMap::from_header(trimmed) {
+            columns = Some(map);
+            continue;
         }
 
-        if header_parsed {
-            if let Ok(route) = parse_route_line(trimmed, IpVersion::IPv4) {
-                route_table.add_route(route);
-            }
+        let result = match &columns {
+            Some(columns) => columns.parse(trimmed, IpVersion::IPv4),
+            None => Err("line before the column header".to_string()),
+        };
+        match result {
+            Ok(route) => parsed.route_table.add_route(route),
+            Err(reason) => parsed.skip(
+                ParseError {
+                    line_number: index + 1,
+                    line: trimmed.to_string(),
+                    reason,
+                },
+                strict,
+            )?,
         }
     }
 
-    Ok(route_table)
+    Ok(parsed)
 }
 
-/// Executes the `netstat -rn` command on Linux and parses its output into a
-/// `RouteTable`.
-///
-/// The function executes the `netstat -rn` command, which prints the system's
-/// route table to stdout. It then parses the output with
-/// `parse_linux_route_output` and returns the resulting `RouteTable`.
-///
-/// If an error occurs while executing the command or parsing the output,
-/// the function returns an error.
-///
-/// # Errors
-///
-/// If an error occurs while executing the command or parsing the output,
-/// the function returns an error.
-pub fn get_linux_routes() -> Result<RouteTable> {
-    use std::process::Command;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETSTAT: &str = "\
+Kernel IP routing table
+Destination     Gateway         Genmask         Flags   MSS Window  irtt Iface
+0.0.0.0         192.168.1.1     0.0.0.0         UG        0 0          0 eth0
+192.168.1.0     0.0.0.0         255.255.255.0   U         0 0          0 eth0
+garbage
+";
+
+    #[test]
+    fn test_parse_linux_route_output() {
+        let parsed = parse_linux_route_output(NETSTAT, false).unwrap();
+        let routes = &parsed.route_table.ipv4_routes;
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].gateway, "192.168.1.1");
+        assert_eq!(routes[1].prefix().unwrap().to_string(), "192.168.1.0/24");
+        assert!(
+            routes
+                .iter()
+                .all(|route| route.destination != "Destination")
+        );
 
-    let output = Command::new("netstat").args(&["-rn"]).output()?;
+        assert_eq!(
+            parsed.skipped,
+            [ParseError {
+                line_number: 5,
+                line: "garbage".to_string(),
+                reason: "expected at least 8 columns, found 1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_linux_route_output_strict() {
+        let error = parse_linux_route_output(NETSTAT, true).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.line_number, 5);
+        assert_eq!(
+            error.to_string(),
+            "line 5: expected at least 8 columns, found 1: 'garbage'"
+        );
 
-    if !output.status.success() {
-        return Err(anyhow!("Failed to execute netstat command"));
+        let parsed =
+            parse_linux_route_output("0.0.0.0 192.168.1.1 0.0.0.0 UG 0 0 0 eth0", false).unwrap();
+        assert!(parsed.route_table.ipv4_routes.is_empty());
+        assert_eq!(parsed.skipped[0].reason, "line before the column header");
     }
 
-    let stdout = String::from_utf8(output.stdout)?;
-    parse_linux_route_output(&stdout)
+    #[test]
+    fn test_parse_extended_columns() {
+        // `netstat -rnee` has both the `route -n` and the `netstat -rn` columns
+        let output = "\
+Kernel IP routing table
+Destination     Gateway         Genmask         Flags Metric Ref    Use Iface    MSS   Window irtt
+0.0.0.0         192.168.1.1     0.0.0.0         UG    100    0        0 eth0     0     0      0
+";
+        let parsed = parse_linux_route_output(output, true).unwrap();
+        let route = &parsed.route_table.ipv4_routes[0];
+        assert_eq!(route.iface, "eth0");
+        assert_eq!(route.metric, Some(100));
+        assert_eq!((route.refs, route.use_count), (Some(0), Some(0)));
+        assert_eq!(
+            (route.mss, route.window, route.irtt),
+            (Some(0), Some(0), Some(0))
+        );
+    }
 }
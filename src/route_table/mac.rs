@@ -1,86 +1,122 @@
-use crate::route_table::{IpVersion, RouteTable, parse_route_line};
-use anyhow::{Result, anyhow};
+use crate::route_table::{IpVersion, ParseError, ParsedRoutes, columns::ColumnMap};
+use anyhow::Result;
 
-/// Parses the output of the `netstat -rn` command on macOS and returns a
-/// `RouteTable` containing the routes.
+/// Parses the output of the `netstat -rn` command on macOS and returns the
+/// routes it contains.
 ///
-/// The function takes a string slice containing the output of the `netstat -rn`
-/// command and splits it into lines. It then iterates over the lines, parsing
-/// each line with `parse_route_line` and adding the resulting `RouteEntry` to
-/// the `RouteTable`. The `RouteTable` is then returned.
+/// The output starts with the title "Routing tables" and has a section for
+/// each address family; see `parse_netstat_sections`.
 ///
-/// The function skips over empty lines and sections that do not contain either
-/// "Internet:" or "Internet6:", which are the headers for the IPv4 and IPv6
-/// routes, respectively. It also skips over the header lines themselves.
+/// # Errors
+///
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_macos_route_output(output: &str, strict: bool) -> Result<ParsedRoutes> {
+    parse_netstat_sections(output, None, strict)
+}
+
+/// Parses `netstat -rn` output in the format of macOS and the BSDs.
 ///
-/// If an error occurs while parsing a line, the function returns an error.
+/// Each address family has a section introduced by "Internet:" or
+/// "Internet6:" and a column header "Destination Gateway Flags ...". Lines
+/// before the first section belong to `section`, for output that was limited
+/// to one family without a section title. The columns of the routes are read
+/// by the names in the header, which differ between systems and versions
+/// (e.g. `Refs`, `Use`, `Mtu` and `Prio`) and with `netstat -rnv`. The title
+/// "Routing tables" and empty lines are ignored.
+///
+/// Any other line, including a line outside a section or before its header
+/// and a route that `ColumnMap::parse` rejects, is skipped and recorded with
+/// its line number in `ParsedRoutes::skipped`.
 ///
 /// # Errors
 ///
-/// If an error occurs while parsing a line, the function returns an error.
-pub fn parse_macos_route_output(output: &str) -> Result<RouteTable> {
-    let mut route_table = RouteTable::new();
-    let lines: Vec<&str> = output.lines().collect();
-
-    let mut current_section = None;
-    let mut header_parsed = false;
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_netstat_sections(
+    output: &str,
+    section: Option<IpVersion>,
+    strict: bool,
+) -> Result<ParsedRoutes> {
+    let mut parsed = ParsedRoutes::default();
+    let mut current_section = section;
+    let mut columns: Option<ColumnMap> = None;
 
-    for line in lines {
+    for (index, line) in output.lines().enumerate() {
         let trimmed = line.trim();
 
-        if trimmed.is_empty() {
+        if trimmed.is_empty() || trimmed.starts_with("Routing tables") {
             continue;
         }
 
         if trimmed.starts_with("Internet:") {
             current_section = Some(IpVersion::IPv4);
-            header_parsed = false;
+            columns = None;
             continue;
         } else if trimmed.starts_with("Internet6:") {
             current_section = Some(IpVersion::IPv6);
-            header_parsed = false;
+            columns = None;
             continue;
         }
 
-        if !header_parsed && trimmed.starts_with("Destination") {
-            header_parsed = true;
+        if let Some(map) = ColumnMap::from_header(trimmed) {
+            columns = Some(map);
+            continue;
         }
 
-        if let Some(ip_version) = &current_section {
-            if header_parsed {
-                if let Ok(route) = parse_route_line(trimmed, ip_version.clone()) {
-                    route_table.add_route(route);
-                }
-            }
+        let result = match (&current_section, &columns) {
+            (Some(ip_version), Some(columns)) => columns.parse(trimmed, ip_version.clone()),
+            (Some(_), None) => Err("line before the column header".to_string()),
+            (None, _) => Err("line outside an Internet or Internet6 section".to_string()),
+        };
+        match result {
+            Ok(route) => parsed.route_table.add_route(route),
+            Err(reason) => parsed.skip(
+                ParseError {
+                    line_number: index + 1,
+                    line: trimmed.to_string(),
+                    reason,
+                },
+                strict,
+            )?,
         }
     }
 
-    Ok(route_table)
+    Ok(parsed)
 }
 
-/// Executes the `netstat -rn` command on macOS and parses its output into a
-/// `RouteTable`.
-///
-/// The function executes the `netstat -rn` command, which prints the system's
-/// route table to stdout. It then parses the output with
-/// `parse_macos_route_output` and returns the resulting `RouteTable`.
-///
-/// If an error occurs while executing the command or parsing the output,
-/// the function returns an error.
-///
-/// # Errors
-///
-/// If an error occurs while executing the command or parsing the output,
-/// the function returns an error.
-pub fn get_macos_routes() -> Result<RouteTable> {
-    use std::process::Command;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let output = Command::new("netstat").args(&["-rn"]).output()?;
+    const NETSTAT: &str = "\
+Routing tables
 
-    if !output.status.success() {
-        return Err(anyhow!("Failed to execute netstat command"));
-    }
+Internet:
+Destination        Gateway            Flags               Netif Expire
+default            192.168.1.1        UGScg                 en0
+127                127.0.0.1          UCS                   lo0
+192.168.1.5        aa:bb:cc:dd:ee:ff  UHLWIi                en0   1174
+
+Internet6:
+Destination                             Gateway                                 Flags               Netif Expire
+default                                 fe80::1%en0                             UGcg                  en0
+fe80::%lo0/64                           fe80::1%lo0                             UcI                   lo0
+oops
+";
 
-    let stdout = String::from_utf8(output.stdout)?;
-    parse_macos_route_output(&stdout)
+    #[test]
+    fn test_parse_macos_route_output() {
+        let parsed = parse_macos_route_output(NETSTAT, false).unwrap();
+        let table = &parsed.route_table;
+        assert_eq!(table.ipv4_routes.len(), 3);
+        assert_eq!(table.ipv6_routes.len(), 2);
+        assert_eq!(table.ipv4_routes[2].expire.as_deref(), Some("1174"));
+        assert_eq!(table.ipv4_routes[0].expire, None);
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].line_number, 13);
+
+        let error = parse_macos_route_output(NETSTAT, true).unwrap_err();
+        assert!(error.to_string().starts_with("line 13: "));
+    }
 }
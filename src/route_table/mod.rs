@@ -1,10 +1,33 @@
+pub mod analysis;
+pub mod bsd;
+pub mod columns;
+pub mod filter;
+pub mod iproute2;
 pub mod linux;
 pub mod mac;
+#[cfg(target_os = "linux")]
+pub mod netlink;
+pub mod netstat;
+pub mod procfs;
+#[allow(clippy::module_inception)]
 pub mod route_table;
+pub mod rules;
+pub mod source;
+pub mod summary;
+pub mod view;
+pub mod windows;
 
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
 
-#[derive(Debug, Clone)]
+use crate::prefix::Prefix;
+use crate::route_table::rules::{RouteQuery, RouteRule, RuleAction, resolve_table};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RouteEntry {
     pub destination: String,
     pub gateway: String,
@@ -12,9 +35,23 @@ pub struct RouteEntry {
     pub iface: String, // macos (Netif) 3, linux 7
     pub ip_version: IpVersion,
 
-    #[allow(dead_code)]
     pub genmask: Option<String>, // linux 2
-    pub expire: Option<String>, // macos 4
+    pub expire: Option<String>,  // macos 4
+
+    pub table: Option<String>,      // netlink RTA_TABLE
+    pub metric: Option<u32>,        // netlink RTA_PRIORITY
+    pub proto: Option<String>,      // netlink rtm_protocol
+    pub scope: Option<String>,      // netlink rtm_scope
+    pub src: Option<String>,        // netlink RTA_PREFSRC
+    pub route_type: Option<String>, // netlink rtm_type
+    pub mtu: Option<u32>,           // netlink RTAX_MTU in RTA_METRICS
+
+    pub refs: Option<u32>, // netstat Refs (macOS) / Ref (route -n)
+    #[serde(rename = "use")]
+    pub use_count: Option<u64>, // netstat Use
+    pub mss: Option<u32>,  // netstat MSS (Linux)
+    pub window: Option<u32>, // netstat Window (Linux)
+    pub irtt: Option<u32>, // netstat irtt (Linux)
 }
 
 impl RouteEntry {
@@ -26,21 +63,151 @@ impl RouteEntry {
             "iface" => Some(self.iface.clone()),
             "genmask" => self.genmask.clone(),
             "expire" => self.expire.clone(),
+            "table" => self.table.clone(),
+            "metric" => self.metric.map(|metric| metric.to_string()),
+            "proto" => self.proto.clone(),
+            "scope" => self.scope.clone(),
+            "src" => self.src.clone(),
+            "type" => self.route_type.clone(),
+            "mtu" => self.mtu.map(|mtu| mtu.to_string()),
+            "refs" => self.refs.map(|refs| refs.to_string()),
+            "use" => self.use_count.map(|use_count| use_count.to_string()),
+            "mss" => self.mss.map(|mss| mss.to_string()),
+            "window" => self.window.map(|window| window.to_string()),
+            "irtt" => self.irtt.map(|irtt| irtt.to_string()),
             _ => None,
         }
     }
+
+    /// Returns `true` if this entry is a default route.
+    pub fn is_default(&self) -> bool {
+        self.destination == "default" || self.destination == "0.0.0.0" || self.destination == "::/0"
+    }
+
+    /// Returns the name of the routing table this route belongs to.
+    ///
+    /// Sources that only know a single table, such as `netstat`, leave the
+    /// table unset; those routes belong to the `main` table.
+    pub fn table_name(&self) -> &str {
+        self.table.as_deref().unwrap_or("main")
+    }
+
+    /// Describes a route in the style of `ip route`, e.g.
+    /// `default via 192.168.1.1 dev eth0 table main proto dhcp metric 100`.
+    pub fn describe(&self) -> String {
+        let mut text = self.destination.clone();
+        let has_gateway = self
+            .gateway
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| !ip.is_unspecified());
+        if has_gateway {
+            text.push_str(&format!(" via {}", self.gateway));
+        }
+        text.push_str(&format!(" dev {} table {}", self.iface, self.table_name()));
+        if let Some(proto) = &self.proto {
+            text.push_str(&format!(" proto {}", proto));
+        }
+        if let Some(src) = &self.src {
+            text.push_str(&format!(" src {}", src));
+        }
+        if let Some(metric) = self.metric {
+            text.push_str(&format!(" metric {}", metric));
+        }
+        text
+    }
+
+    /// Returns the destination network of this route.
+    ///
+    /// Besides `default` and `addr/len` notation, this understands the
+    /// `Genmask` column of Linux `netstat` and the abbreviated destinations
+    /// printed by macOS `netstat`, such as `10/8`, `192.168.1` or `127`.
+    pub fn prefix(&self) -> Option<Prefix> {
+        if self.is_default() {
+            let unspecified = match self.ip_version {
+                IpVersion::IPv4 => IpAddr::from([0u8; 4]),
+                IpVersion::IPv6 => IpAddr::from([0u8; 16]),
+            };
+            return Some(Prefix::new(unspecified, 0));
+        }
+
+        // Strip a scope suffix, e.g. `fe80::%lo0/64`
+        let destination = match self.destination.split_once('%') {
+            Some((addr, rest)) => match rest.split_once('/') {
+                Some((_, len)) => format!("{}/{}", addr, len),
+                None => addr.to_string(),
+            },
+            None => self.destination.clone(),
+        };
+
+        if let Some(genmask) = &self.genmask
+            && !destination.contains('/')
+        {
+            let addr: IpAddr = destination.parse().ok()?;
+            let netmask: IpAddr = genmask.parse().ok()?;
+            return Some(Prefix::from_netmask(addr, netmask));
+        }
+
+        if let Ok(prefix) = destination.parse::<Prefix>() {
+            return Some(prefix);
+        }
+
+        if self.ip_version != IpVersion::IPv4 {
+            return None;
+        }
+
+        // Abbreviated IPv4 destination: missing octets are zero and the
+        // prefix length defaults to the number of given octets
+        let (addr, len) = match destination.split_once('/') {
+            Some((addr, len)) => (addr, Some(len.parse::<u8>().ok()?)),
+            None => (destination.as_str(), None),
+        };
+        let octets: Vec<u8> = addr
+            .split('.')
+            .map(|octet| octet.parse().ok())
+            .collect::<Option<_>>()?;
+        if octets.is_empty() || octets.len() > 4 {
+            return None;
+        }
+        let mut full = [0u8; 4];
+        full[..octets.len()].copy_from_slice(&octets);
+        let len = len.unwrap_or(octets.len() as u8 * 8);
+        Some(Prefix::new(IpAddr::from(full), len))
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum IpVersion {
+    #[default]
     IPv4,
     IPv6,
 }
 
-#[derive(Debug)]
+impl IpVersion {
+    pub fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => IpVersion::IPv4,
+            IpAddr::V6(_) => IpVersion::IPv6,
+        }
+    }
+}
+
+/// The result of looking up the route for a destination.
+#[derive(Debug, Serialize)]
+pub struct RouteLookup<'a> {
+    /// The policy rule that selected the route, if rules are known.
+    pub rule: Option<&'a RouteRule>,
+    /// The selected route; `None` if the rule rejects the traffic.
+    pub route: Option<&'a RouteEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RouteTable {
     pub ipv4_routes: Vec<RouteEntry>,
     pub ipv6_routes: Vec<RouteEntry>,
+    /// Policy routing rules, in priority order. Empty if the route source
+    /// only knows a single table.
+    pub rules: Vec<RouteRule>,
 }
 
 impl RouteTable {
@@ -48,6 +215,7 @@ impl RouteTable {
         Self {
             ipv4_routes: Vec::new(),
             ipv6_routes: Vec::new(),
+            rules: Vec::new(),
         }
     }
 
@@ -58,83 +226,435 @@ impl RouteTable {
         }
     }
 
-    pub fn get_default_gateway(&self, ip_version: IpVersion) -> Option<&RouteEntry> {
-        let routes = match ip_version {
+    pub fn routes(&self, ip_version: &IpVersion) -> &Vec<RouteEntry> {
+        match ip_version {
             IpVersion::IPv4 => &self.ipv4_routes,
             IpVersion::IPv6 => &self.ipv6_routes,
+        }
+    }
+
+    /// Returns the default route that unmarked, locally generated traffic
+    /// takes, following the policy routing rules if there are any.
+    pub fn get_default_gateway(&self, ip_version: IpVersion) -> Option<&RouteEntry> {
+        let unspecified = match ip_version {
+            IpVersion::IPv4 => IpAddr::from([0u8; 4]),
+            IpVersion::IPv6 => IpAddr::from([0u8; 16]),
         };
+        let query = RouteQuery::new(unspecified);
 
-        routes.iter().find(|route| {
-            route.destination == "default"
-                || route.destination == "0.0.0.0"
-                || route.destination == "::/0"
+        self.resolve(&query, |routes| {
+            routes.into_iter().find(|route| route.is_default())
+        })?
+        .route
+    }
+
+    /// Looks up the route the kernel would choose for the traffic described
+    /// by `query`.
+    ///
+    /// Rules are evaluated in priority order; within a table the most
+    /// specific route wins, with the lowest metric breaking ties. Without
+    /// rules, all routes are treated as a single table.
+    pub fn lookup(&self, query: &RouteQuery) -> Option<RouteLookup<'_>> {
+        self.resolve(query, |routes| {
+            routes
+                .into_iter()
+                .filter_map(|route| Some((route, route.prefix()?)))
+                .filter(|(_, prefix)| prefix.contains(query.dst))
+                .max_by_key(|(route, prefix)| {
+                    (
+                        prefix.prefix_len(),
+                        std::cmp::Reverse(route.metric.unwrap_or(0)),
+                    )
+                })
+                .map(|(route, _)| route)
         })
     }
-}
 
-/// Parses a single line of the route table output and returns a `RouteEntry`.
-///
-/// The function takes a string slice `line` containing the line of the route
-/// table output and an `IpVersion` enum indicating the IP protocol.
-///
-/// The function splits the line into parts using whitespace as a delimiter and
-/// constructs a `RouteEntry` from the parts. The fields of the `RouteEntry` are
-/// populated based on the operating system and IP protocol.
-///
-/// The function returns an `Err` if the line is invalid or if the operating
-/// system is not supported.
-///
-/// # Errors
-///
-/// If the line is invalid or if the operating system is not supported, the
-/// function returns an error.
-fn parse_route_line(line: &str, ip_version: IpVersion) -> Result<RouteEntry> {
-    let parts: Vec<&str> = line.split_whitespace().collect();
-
-    if cfg!(target_os = "macos") {
-        let destination = parts[0].to_string();
-        let gateway = parts[1].to_string();
-        let flags = parts[2].to_string();
-        let iface = parts[3].to_string();
-        let expire = {
-            let last_part = parts[parts.len() - 1];
-            if last_part == "Expire" {
-                Some("Expire".to_string())
-            } else if last_part.chars().all(|c| c.is_ascii_digit()) {
-                Some(last_part.to_string())
-            } else {
-                None
+    /// Walks the rules for `query` and picks a route from each selected table
+    /// with `select` until one is found.
+    fn resolve<'a, F>(&'a self, query: &RouteQuery, select: F) -> Option<RouteLookup<'a>>
+    where
+        F: Fn(Vec<&'a RouteEntry>) -> Option<&'a RouteEntry>,
+    {
+        let ip_version = IpVersion::of(&query.dst);
+        let routes = self.routes(&ip_version);
+
+        if self.rules.is_empty() {
+            let route = select(routes.iter().collect())?;
+            return Some(RouteLookup {
+                rule: None,
+                route: Some(route),
+            });
+        }
+
+        let mut goto: Option<u32> = None;
+        for rule in &self.rules {
+            if rule.ip_version != ip_version || goto.is_some_and(|target| rule.priority < target) {
+                continue;
+            }
+            goto = None;
+            if !rule.matches(query) {
+                continue;
+            }
+
+            match &rule.action {
+                RuleAction::Table(table) => {
+                    let candidates = routes
+                        .iter()
+                        .filter(|route| route.table_name() == table)
+                        .collect();
+                    let Some(route) = select(candidates) else {
+                        continue;
+                    };
+                    if route.route_type.as_deref() == Some("throw") {
+                        continue;
+                    }
+                    if let Some(suppress) = rule.suppress_prefixlen
+                        && route
+                            .prefix()
+                            .is_some_and(|prefix| prefix.prefix_len() <= suppress)
+                    {
+                        continue;
+                    }
+                    return Some(RouteLookup {
+                        rule: Some(rule),
+                        route: Some(route),
+                    });
+                }
+                RuleAction::Goto(target) => goto = Some(*target),
+                RuleAction::Nop => {}
+                RuleAction::Blackhole | RuleAction::Unreachable | RuleAction::Prohibit => {
+                    return Some(RouteLookup {
+                        rule: Some(rule),
+                        route: None,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the names of all tables with routes, in the order the rules
+    /// reference them, followed by tables no rule references.
+    pub fn table_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if let RuleAction::Table(table) = &rule.action
+                && !names.contains(table)
+            {
+                names.push(table.clone());
             }
+        }
+
+        let mut unreferenced: Vec<String> = Vec::new();
+        for route in self.ipv4_routes.iter().chain(&self.ipv6_routes) {
+            let name = route.table_name().to_string();
+            if !names.contains(&name) && !unreferenced.contains(&name) {
+                unreferenced.push(name);
+            }
+        }
+        unreferenced.sort();
+
+        names.retain(|name| {
+            self.ipv4_routes
+                .iter()
+                .chain(&self.ipv6_routes)
+                .any(|route| route.table_name() == name)
+        });
+        names.extend(unreferenced);
+        names
+    }
+
+    /// Removes all routes and rules not belonging to the given protocol
+    /// ("ipv4", "ipv6", or "all").
+    pub fn retain_protocol(&mut self, protocol: &str) {
+        let keep = match protocol {
+            "ipv4" => IpVersion::IPv4,
+            "ipv6" => IpVersion::IPv6,
+            _ => return,
         };
+        match keep {
+            IpVersion::IPv4 => self.ipv6_routes.clear(),
+            IpVersion::IPv6 => self.ipv4_routes.clear(),
+        }
+        self.rules.retain(|rule| rule.ip_version == keep);
+    }
 
-        Ok(RouteEntry {
-            destination,
-            gateway,
-            flags,
-            iface,
-            expire,
-            ip_version,
-            genmask: None,
-        })
-    } else if cfg!(target_os = "linux") {
-        let destination = parts[0].to_string();
-        let gateway = parts[1].to_string();
-        let genmask = Some(parts[2].to_string());
-        let flags = parts[3].to_string();
-        let iface = parts[parts.len() - 1].to_string();
-
-        Ok(RouteEntry {
-            destination,
-            gateway,
-            genmask,
-            flags,
-            iface,
-            expire: None,
-            ip_version,
-        })
-    } else if cfg!(target_os = "windows") {
-        todo!();
-    } else {
-        return Err(anyhow!("Unsupported operating system"));
+    /// Returns a copy of this route table containing only the routes of the
+    /// given table, without rules.
+    pub fn table(&self, name: &str) -> RouteTable {
+        let mut table = RouteTable::new();
+        for route in self.ipv4_routes.iter().chain(&self.ipv6_routes) {
+            if route.table_name() == name {
+                table.add_route(route.clone());
+            }
+        }
+        table
+    }
+
+    /// Returns the name of `table`, given by name or by an ID resolved
+    /// through `names`, or "all" for every table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table is neither named in `names` nor has
+    /// any routes.
+    pub fn resolve_table(&self, table: &str, names: &HashMap<u32, String>) -> Result<String> {
+        if table == "all" {
+            return Ok(table.to_string());
+        }
+        let name = resolve_table(table, names);
+        if names.values().any(|known| *known == name) || self.table_names().contains(&name) {
+            Ok(name)
+        } else {
+            Err(anyhow!("Unknown routing table '{}'", table))
+        }
+    }
+
+    /// Returns the routes of the given table, by name or ID ("all" for every
+    /// table, including the rules) and protocol, as shown by
+    /// `routes --format json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table is neither named in `names` nor has any
+    /// routes.
+    pub fn select(
+        &self,
+        table: &str,
+        protocol: &str,
+        names: &HashMap<u32, String>,
+    ) -> Result<RouteTable> {
+        let mut selected = match self.resolve_table(table, names)?.as_str() {
+            "all" => self.clone(),
+            name => self.table(name),
+        };
+        selected.retain_protocol(protocol);
+        Ok(selected)
+    }
+}
+
+/// A line of route table output that is neither a route nor a known header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The line number in the output, starting at 1.
+    pub line_number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {}: '{}'",
+            self.line_number, self.reason, self.line
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How route table output from commands such as `netstat` is parsed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Fail on lines that are not routes instead of skipping them.
+    pub strict: bool,
+    /// Print a warning for every skipped line.
+    pub verbose: bool,
+}
+
+/// The routes parsed from command output and the lines that were skipped.
+#[derive(Debug, Default)]
+pub struct ParsedRoutes {
+    pub route_table: RouteTable,
+    pub skipped: Vec<ParseError>,
+}
+
+impl ParsedRoutes {
+    /// Records a line that failed to parse, or returns the error in strict
+    /// mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error if `strict` is set.
+    fn skip(&mut self, error: ParseError, strict: bool) -> Result<()> {
+        if strict {
+            return Err(error.into());
+        }
+        self.skipped.push(error);
+        Ok(())
+    }
+
+    /// Returns the route table, printing a warning for every skipped line to
+    /// stderr if `verbose` is set.
+    pub fn into_route_table(self, verbose: bool) -> RouteTable {
+        if verbose {
+            for error in &self.skipped {
+                eprintln!("warning: skipped route table {}", error);
+            }
+        }
+        self.route_table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::FakeProbe;
+    use crate::route_table::rules::read_table_names;
+
+    fn route(destination: &str, gateway: &str, iface: &str, table: Option<&str>) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            iface: iface.to_string(),
+            ip_version: IpVersion::IPv4,
+            table: table.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn rule(priority: u32, table: &str) -> RouteRule {
+        RouteRule {
+            priority,
+            ip_version: IpVersion::IPv4,
+            invert: false,
+            from: None,
+            to: None,
+            fwmark: None,
+            iif: None,
+            oif: None,
+            action: RuleAction::Table(table.to_string()),
+            suppress_prefixlen: None,
+        }
+    }
+
+    #[test]
+    fn test_prefix_of_destinations() {
+        let cases = [
+            ("default", None, "0.0.0.0/0"),
+            ("0.0.0.0", Some("0.0.0.0"), "0.0.0.0/0"),
+            ("192.168.1.0", Some("255.255.255.0"), "192.168.1.0/24"),
+            ("10.0.0.0/8", None, "10.0.0.0/8"),
+            ("10/8", None, "10.0.0.0/8"),
+            ("127", None, "127.0.0.0/8"),
+            ("169.254", None, "169.254.0.0/16"),
+            ("192.168.1.1", None, "192.168.1.1/32"),
+        ];
+        for (destination, genmask, expected) in cases {
+            let mut entry = route(destination, "0.0.0.0", "eth0", None);
+            entry.genmask = genmask.map(str::to_string);
+            assert_eq!(entry.prefix().unwrap().to_string(), expected);
+        }
+
+        let mut entry = route("fe80::%lo0/64", "::", "lo0", None);
+        entry.ip_version = IpVersion::IPv6;
+        assert_eq!(entry.prefix().unwrap().to_string(), "fe80::/64");
+    }
+
+    #[test]
+    fn test_lookup_without_rules() {
+        let mut table = RouteTable::new();
+        table.add_route(route("default", "192.168.1.1", "eth0", None));
+        table.add_route(route("10.0.0.0/8", "10.8.0.1", "wg0", None));
+        table.add_route(route("10.1.0.0/16", "10.8.0.5", "wg0", None));
+
+        let lookup = table
+            .lookup(&RouteQuery::new("10.1.2.3".parse().unwrap()))
+            .unwrap();
+        assert!(lookup.rule.is_none());
+        assert_eq!(lookup.route.unwrap().gateway, "10.8.0.5");
+
+        let lookup = table
+            .lookup(&RouteQuery::new("8.8.8.8".parse().unwrap()))
+            .unwrap();
+        assert_eq!(lookup.route.unwrap().gateway, "192.168.1.1");
+    }
+
+    #[test]
+    fn test_lookup_follows_policy_rules() {
+        // The routing setup of wg-quick with a full tunnel
+        let mut table = RouteTable::new();
+        table.add_route(route("default", "192.168.1.1", "eth0", Some("main")));
+        table.add_route(route("192.168.1.0/24", "0.0.0.0", "eth0", Some("main")));
+        table.add_route(route("default", "0.0.0.0", "wg0", Some("51820")));
+
+        let mut suppress = rule(32764, "main");
+        suppress.suppress_prefixlen = Some(0);
+        let mut tunnel = rule(32765, "51820");
+        tunnel.invert = true;
+        tunnel.fwmark = Some((0xca6c, u32::MAX));
+        table.rules = vec![rule(0, "local"), suppress, tunnel, rule(32766, "main")];
+
+        let lookup = table
+            .lookup(&RouteQuery::new("192.168.1.20".parse().unwrap()))
+            .unwrap();
+        assert_eq!(lookup.rule.unwrap().priority, 32764);
+        assert_eq!(lookup.route.unwrap().iface, "eth0");
+
+        let lookup = table
+            .lookup(&RouteQuery::new("1.1.1.1".parse().unwrap()))
+            .unwrap();
+        assert_eq!(lookup.rule.unwrap().priority, 32765);
+        assert_eq!(lookup.route.unwrap().iface, "wg0");
+
+        let mut marked = RouteQuery::new("1.1.1.1".parse().unwrap());
+        marked.fwmark = Some(0xca6c);
+        let lookup = table.lookup(&marked).unwrap();
+        assert_eq!(lookup.rule.unwrap().priority, 32766);
+        assert_eq!(lookup.route.unwrap().gateway, "192.168.1.1");
+
+        assert_eq!(
+            table.get_default_gateway(IpVersion::IPv4).unwrap().iface,
+            "wg0"
+        );
+        assert_eq!(table.table_names(), vec!["main", "51820"]);
+    }
+
+    #[test]
+    fn test_lookup_rejecting_rule() {
+        let mut table = RouteTable::new();
+        table.add_route(route("default", "192.168.1.1", "eth0", Some("main")));
+        let mut prohibit = rule(100, "main");
+        prohibit.action = RuleAction::Prohibit;
+        prohibit.to = Some("10.0.0.0/8".parse().unwrap());
+        table.rules = vec![prohibit, rule(32766, "main")];
+
+        let lookup = table
+            .lookup(&RouteQuery::new("10.0.0.1".parse().unwrap()))
+            .unwrap();
+        assert_eq!(lookup.rule.unwrap().priority, 100);
+        assert!(lookup.route.is_none());
+    }
+
+    #[test]
+    fn test_select_table_by_id() {
+        let mut table = RouteTable::new();
+        table.add_route(route("default", "192.168.1.1", "eth0", Some("main")));
+        table.add_route(route("default", "0.0.0.0", "wg0", Some("51820")));
+        let names = read_table_names(&FakeProbe::new("linux"));
+
+        let main = table.select("main", "ipv4", &names).unwrap();
+        assert_eq!(main.ipv4_routes.len(), 1);
+        assert_eq!(
+            table.select("254", "ipv4", &names).unwrap().ipv4_routes,
+            main.ipv4_routes
+        );
+        assert_eq!(
+            table.select("51820", "ipv4", &names).unwrap().ipv4_routes[0].iface,
+            "wg0"
+        );
+        assert!(
+            table
+                .select("local", "ipv4", &names)
+                .unwrap()
+                .ipv4_routes
+                .is_empty()
+        );
+        let error = table.select("nosuch", "ipv4", &names).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown routing table 'nosuch'");
+        assert!(table.select("1234", "ipv4", &names).is_err());
     }
 }
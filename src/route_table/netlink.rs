@@ -0,0 +1,500 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::netlink::{
+    AF_INET, AF_INET6, NetlinkSocket, attr_ip, attr_string, attr_u32, if_name, parse_attributes,
+};
+use crate::prefix::Prefix;
+use crate::probe::SystemProbe;
+use crate::route_table::rules::{RouteRule, RuleAction, read_table_names, table_name};
+use crate::route_table::{IpVersion, RouteEntry, RouteTable};
+
+const RTM_NEWROUTE: u16 = 24;
+const RTM_GETROUTE: u16 = 26;
+const RTM_NEWRULE: u16 = 32;
+const RTM_GETRULE: u16 = 34;
+
+/// Size of `struct rtmsg` and `struct fib_rule_hdr`.
+const RTMSG_LEN: usize = 12;
+
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_PREFSRC: u16 = 7;
+const RTA_METRICS: u16 = 8;
+const RTA_MULTIPATH: u16 = 9;
+const RTA_TABLE: u16 = 15;
+
+const RTAX_MTU: u16 = 2;
+
+const RTM_F_CLONED: u32 = 0x200;
+
+const FRA_DST: u16 = 1;
+const FRA_SRC: u16 = 2;
+const FRA_IIFNAME: u16 = 3;
+const FRA_GOTO: u16 = 4;
+const FRA_PRIORITY: u16 = 6;
+const FRA_FWMARK: u16 = 10;
+const FRA_SUPPRESS_PREFIXLEN: u16 = 14;
+const FRA_TABLE: u16 = 15;
+const FRA_FWMASK: u16 = 16;
+const FRA_OIFNAME: u16 = 17;
+
+const FR_ACT_TO_TBL: u8 = 1;
+const FR_ACT_GOTO: u8 = 2;
+const FR_ACT_BLACKHOLE: u8 = 6;
+const FR_ACT_UNREACHABLE: u8 = 7;
+const FR_ACT_PROHIBIT: u8 = 8;
+const FIB_RULE_INVERT: u32 = 0x2;
+
+/// Reads all routing tables and policy rules of the current network
+/// namespace over a `NETLINK_ROUTE` socket.
+///
+/// Unlike `netstat -rn`, this includes the `local` and `default` tables, any
+/// numbered or named tables (resolved through `/etc/iproute2/rt_tables` of
+/// the system behind `probe`) and
+/// the IPv6 routes.
+///
+/// # Errors
+///
+/// Returns an error if the netlink socket cannot be opened or a dump request
+/// fails.
+pub fn get_netlink_routes(probe: &dyn SystemProbe) -> Result<RouteTable> {
+    let names = read_table_names(probe);
+    let mut socket = NetlinkSocket::connect()?;
+    let mut route_table = RouteTable::new();
+
+    for message in socket.dump(RTM_GETROUTE, &[0; RTMSG_LEN])? {
+        if message.msg_type == RTM_NEWROUTE {
+            for route in parse_route_message(&message.payload, &names, if_name) {
+                route_table.add_route(route);
+            }
+        }
+    }
+
+    for message in socket.dump(RTM_GETRULE, &[0; RTMSG_LEN])? {
+        if message.msg_type == RTM_NEWRULE
+            && let Some(rule) = parse_rule_message(&message.payload, &names)
+        {
+            route_table.rules.push(rule);
+        }
+    }
+
+    Ok(route_table)
+}
+
+/// Converts the payload of an `RTM_NEWROUTE` message into route entries.
+///
+/// A multipath route yields one entry per next hop. Cached clones and
+/// families other than IPv4 and IPv6 yield no entries. Interface indexes are
+/// resolved with `if_name`.
+pub fn parse_route_message(
+    payload: &[u8],
+    names: &HashMap<u32, String>,
+    if_name: impl Fn(u32) -> Option<String>,
+) -> Vec<RouteEntry> {
+    if payload.len() < RTMSG_LEN {
+        return Vec::new();
+    }
+    let family = payload[0];
+    let dst_len = payload[1];
+    let (rtm_table, protocol, scope, rtm_type) = (payload[4], payload[5], payload[6], payload[7]);
+    let flags = u32::from_ne_bytes([payload[8], payload[9], payload[10], payload[11]]);
+
+    let ip_version = match family {
+        AF_INET => IpVersion::IPv4,
+        AF_INET6 => IpVersion::IPv6,
+        _ => return Vec::new(),
+    };
+    if flags & RTM_F_CLONED != 0 {
+        return Vec::new();
+    }
+
+    let mut table = rtm_table as u32;
+    let mut destination = None;
+    let mut gateway = None;
+    let mut oif = None;
+    let mut metric = None;
+    let mut src = None;
+    let mut mtu = None;
+    let mut next_hops = Vec::new();
+
+    for (attr_type, value) in parse_attributes(&payload[RTMSG_LEN..]) {
+        match attr_type {
+            RTA_DST => destination = attr_ip(family, value),
+            RTA_GATEWAY => gateway = attr_ip(family, value),
+            RTA_OIF => oif = attr_u32(value),
+            RTA_PRIORITY => metric = attr_u32(value),
+            RTA_PREFSRC => src = attr_ip(family, value),
+            RTA_METRICS => {
+                mtu = parse_attributes(value)
+                    .into_iter()
+                    .find(|(metric, _)| *metric == RTAX_MTU)
+                    .and_then(|(_, value)| attr_u32(value));
+            }
+            RTA_TABLE => table = attr_u32(value).unwrap_or(table),
+            RTA_MULTIPATH => next_hops = parse_next_hops(family, value),
+            _ => {}
+        }
+    }
+
+    let destination = match destination {
+        Some(addr) if dst_len > 0 => Prefix::new(addr, dst_len).to_string(),
+        Some(addr) if !addr.is_unspecified() => Prefix::new(addr, dst_len).to_string(),
+        _ => "default".to_string(),
+    };
+    let genmask = match (&ip_version, dst_len) {
+        (IpVersion::IPv4, len) => {
+            let mask = if len == 0 {
+                0
+            } else {
+                u32::MAX << (32 - len.min(32))
+            };
+            Some(std::net::Ipv4Addr::from(mask).to_string())
+        }
+        (IpVersion::IPv6, _) => None,
+    };
+    let host_route = match ip_version {
+        IpVersion::IPv4 => dst_len == 32,
+        IpVersion::IPv6 => dst_len == 128,
+    };
+
+    if next_hops.is_empty() {
+        next_hops.push((gateway, oif));
+    }
+
+    next_hops
+        .into_iter()
+        .map(|(gateway, oif)| {
+            let mut route_flags = String::new();
+            if is_reject_type(rtm_type) {
+                route_flags.push('!');
+            } else {
+                route_flags.push('U');
+            }
+            if gateway.is_some() {
+                route_flags.push('G');
+            }
+            if host_route {
+                route_flags.push('H');
+            }
+
+            let unspecified = match ip_version {
+                IpVersion::IPv4 => "0.0.0.0",
+                IpVersion::IPv6 => "::",
+            };
+
+            RouteEntry {
+                destination: destination.clone(),
+                gateway: gateway
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_else(|| unspecified.to_string()),
+                flags: route_flags,
+                iface: oif.and_then(&if_name).unwrap_or_else(|| "-".to_string()),
+                ip_version: ip_version.clone(),
+                genmask: genmask.clone(),
+                table: Some(table_name(table, names)),
+                metric,
+                proto: Some(protocol_name(protocol)),
+                scope: Some(scope_name(scope)),
+                src: src.map(|ip| ip.to_string()),
+                route_type: Some(type_name(rtm_type)),
+                mtu,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Parses the `rtnexthop` entries of an `RTA_MULTIPATH` attribute into
+/// `(gateway, interface index)` pairs.
+fn parse_next_hops(family: u8, buf: &[u8]) -> Vec<(Option<IpAddr>, Option<u32>)> {
+    let mut next_hops = Vec::new();
+    let mut offset = 0;
+
+    // struct rtnexthop { u16 len; u8 flags; u8 hops; i32 ifindex; }
+    while offset + 8 <= buf.len() {
+        let len = u16::from_ne_bytes([buf[offset], buf[offset + 1]]) as usize;
+        if len < 8 || offset + len > buf.len() {
+            break;
+        }
+        let ifindex = attr_u32(&buf[offset + 4..offset + 8]);
+        let gateway = parse_attributes(&buf[offset + 8..offset + len])
+            .into_iter()
+            .find(|(attr_type, _)| *attr_type == RTA_GATEWAY)
+            .and_then(|(_, value)| attr_ip(family, value));
+        next_hops.push((gateway, ifindex));
+        offset += (len + 3) & !3;
+    }
+
+    next_hops
+}
+
+/// Converts the payload of an `RTM_NEWRULE` message into a policy rule.
+pub fn parse_rule_message(payload: &[u8], names: &HashMap<u32, String>) -> Option<RouteRule> {
+    if payload.len() < RTMSG_LEN {
+        return None;
+    }
+    let family = payload[0];
+    let (dst_len, src_len) = (payload[1], payload[2]);
+    let rule_table = payload[4] as u32;
+    let action = payload[7];
+    let flags = u32::from_ne_bytes([payload[8], payload[9], payload[10], payload[11]]);
+
+    let ip_version = match family {
+        AF_INET => IpVersion::IPv4,
+        AF_INET6 => IpVersion::IPv6,
+        _ => return None,
+    };
+
+    let mut rule = RouteRule {
+        priority: 0,
+        ip_version,
+        invert: flags & FIB_RULE_INVERT != 0,
+        from: None,
+        to: None,
+        fwmark: None,
+        iif: None,
+        oif: None,
+        action: RuleAction::Nop,
+        suppress_prefixlen: None,
+    };
+    let mut table = rule_table;
+    let mut goto = 0;
+    let mut fwmark = None;
+    let mut fwmask = u32::MAX;
+
+    for (attr_type, value) in parse_attributes(&payload[RTMSG_LEN..]) {
+        match attr_type {
+            FRA_DST => rule.to = attr_ip(family, value).map(|ip| Prefix::new(ip, dst_len)),
+            FRA_SRC => rule.from = attr_ip(family, value).map(|ip| Prefix::new(ip, src_len)),
+            FRA_IIFNAME => rule.iif = Some(attr_string(value)),
+            FRA_OIFNAME => rule.oif = Some(attr_string(value)),
+            FRA_GOTO => goto = attr_u32(value).unwrap_or(0),
+            FRA_PRIORITY => rule.priority = attr_u32(value).unwrap_or(0),
+            FRA_FWMARK => fwmark = attr_u32(value),
+            FRA_FWMASK => fwmask = attr_u32(value).unwrap_or(u32::MAX),
+            FRA_TABLE => table = attr_u32(value).unwrap_or(table),
+            // The kernel reports "no suppression" as -1
+            FRA_SUPPRESS_PREFIXLEN => {
+                rule.suppress_prefixlen =
+                    attr_u32(value).and_then(|len| u8::try_from(len as i32).ok())
+            }
+            _ => {}
+        }
+    }
+
+    // A mark of zero with a zero mask matches everything and is not shown
+    rule.fwmark = fwmark
+        .filter(|mark| *mark != 0 || fwmask != 0)
+        .map(|mark| (mark, fwmask));
+
+    rule.action = match action {
+        FR_ACT_TO_TBL => RuleAction::Table(table_name(table, names)),
+        FR_ACT_GOTO => RuleAction::Goto(goto),
+        FR_ACT_BLACKHOLE => RuleAction::Blackhole,
+        FR_ACT_UNREACHABLE => RuleAction::Unreachable,
+        FR_ACT_PROHIBIT => RuleAction::Prohibit,
+        _ => RuleAction::Nop,
+    };
+
+    Some(rule)
+}
+
+fn is_reject_type(rtm_type: u8) -> bool {
+    matches!(rtm_type, 6..=8)
+}
+
+/// Returns the iproute2 name of a route type (`rtm_type`).
+fn type_name(rtm_type: u8) -> String {
+    match rtm_type {
+        1 => "unicast",
+        2 => "local",
+        3 => "broadcast",
+        4 => "anycast",
+        5 => "multicast",
+        6 => "blackhole",
+        7 => "unreachable",
+        8 => "prohibit",
+        9 => "throw",
+        10 => "nat",
+        11 => "xresolve",
+        _ => return rtm_type.to_string(),
+    }
+    .to_string()
+}
+
+/// Returns the iproute2 name of a routing protocol (`rtm_protocol`).
+fn protocol_name(protocol: u8) -> String {
+    match protocol {
+        0 => "unspec",
+        1 => "redirect",
+        2 => "kernel",
+        3 => "boot",
+        4 => "static",
+        8 => "gated",
+        9 => "ra",
+        10 => "mrt",
+        11 => "zebra",
+        12 => "bird",
+        13 => "dnrouted",
+        14 => "xorp",
+        15 => "ntk",
+        16 => "dhcp",
+        17 => "mrouted",
+        18 => "keepalived",
+        42 => "babel",
+        99 => "openr",
+        186 => "bgp",
+        187 => "isis",
+        188 => "ospf",
+        189 => "rip",
+        192 => "eigrp",
+        _ => return protocol.to_string(),
+    }
+    .to_string()
+}
+
+/// Returns the iproute2 name of a route scope (`rtm_scope`).
+fn scope_name(scope: u8) -> String {
+    match scope {
+        0 => "global",
+        200 => "site",
+        253 => "link",
+        254 => "host",
+        255 => "nowhere",
+        _ => return scope.to_string(),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::rules::RouteQuery;
+
+    fn attribute(attr_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&((4 + value.len()) as u16).to_ne_bytes());
+        buf.extend_from_slice(&attr_type.to_ne_bytes());
+        buf.extend_from_slice(value);
+        buf.resize((buf.len() + 3) & !3, 0);
+        buf
+    }
+
+    fn names() -> HashMap<u32, String> {
+        [(254, "main"), (255, "local"), (100, "vpn")]
+            .into_iter()
+            .map(|(id, name)| (id, name.to_string()))
+            .collect()
+    }
+
+    fn if_name(index: u32) -> Option<String> {
+        match index {
+            1 => Some("lo".to_string()),
+            2 => Some("eth0".to_string()),
+            3 => Some("wg0".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_default_route() {
+        // family, dst_len, src_len, tos, table, protocol, scope, type, flags
+        let mut payload = vec![AF_INET, 0, 0, 0, 254, 16, 0, 1, 0, 0, 0, 0];
+        payload.extend(attribute(RTA_TABLE, &254u32.to_ne_bytes()));
+        payload.extend(attribute(RTA_PRIORITY, &100u32.to_ne_bytes()));
+        payload.extend(attribute(RTA_GATEWAY, &[192, 168, 1, 1]));
+        payload.extend(attribute(RTA_OIF, &2u32.to_ne_bytes()));
+        payload.extend(attribute(RTA_PREFSRC, &[192, 168, 1, 10]));
+        payload.extend(attribute(
+            RTA_METRICS,
+            &attribute(RTAX_MTU, &1400u32.to_ne_bytes()),
+        ));
+
+        let routes = parse_route_message(&payload, &names(), if_name);
+        assert_eq!(routes.len(), 1);
+        let route = &routes[0];
+        assert_eq!(route.destination, "default");
+        assert!(route.is_default());
+        assert_eq!(route.gateway, "192.168.1.1");
+        assert_eq!(route.iface, "eth0");
+        assert_eq!(route.flags, "UG");
+        assert_eq!(route.table.as_deref(), Some("main"));
+        assert_eq!(route.metric, Some(100));
+        assert_eq!(route.proto.as_deref(), Some("dhcp"));
+        assert_eq!(route.scope.as_deref(), Some("global"));
+        assert_eq!(route.src.as_deref(), Some("192.168.1.10"));
+        assert_eq!(route.route_type.as_deref(), Some("unicast"));
+        assert_eq!(route.mtu, Some(1400));
+    }
+
+    #[test]
+    fn test_parse_local_and_multipath_routes() {
+        let mut payload = vec![AF_INET6, 128, 0, 0, 255, 2, 254, 2, 0, 0, 0, 0];
+        payload.extend(attribute(
+            RTA_DST,
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        ));
+        payload.extend(attribute(RTA_OIF, &1u32.to_ne_bytes()));
+        let routes = parse_route_message(&payload, &names(), if_name);
+        assert_eq!(routes[0].destination, "::1/128");
+        assert_eq!(routes[0].gateway, "::");
+        assert_eq!(routes[0].flags, "UH");
+        assert_eq!(routes[0].table.as_deref(), Some("local"));
+        assert_eq!(routes[0].route_type.as_deref(), Some("local"));
+
+        let mut hops = Vec::new();
+        for (gateway, ifindex) in [([10, 0, 0, 1], 2u32), ([10, 0, 1, 1], 3u32)] {
+            let gateway = attribute(RTA_GATEWAY, &gateway);
+            hops.extend_from_slice(&((8 + gateway.len()) as u16).to_ne_bytes());
+            hops.extend_from_slice(&[0, 0]);
+            hops.extend_from_slice(&ifindex.to_ne_bytes());
+            hops.extend(gateway);
+        }
+        let mut payload = vec![AF_INET, 8, 0, 0, 100, 4, 0, 1, 0, 0, 0, 0];
+        payload.extend(attribute(RTA_DST, &[10, 0, 0, 0]));
+        payload.extend(attribute(RTA_MULTIPATH, &hops));
+        let routes = parse_route_message(&payload, &names(), if_name);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].destination, "10.0.0.0/8");
+        assert_eq!(routes[0].genmask.as_deref(), Some("255.0.0.0"));
+        assert_eq!(routes[0].gateway, "10.0.0.1");
+        assert_eq!(routes[1].iface, "wg0");
+        assert_eq!(routes[1].table.as_deref(), Some("vpn"));
+    }
+
+    #[test]
+    fn test_parse_rule_message() {
+        // family, dst_len, src_len, tos, table, res1, res2, action, flags
+        let mut payload = vec![AF_INET, 0, 24, 0, 100, 0, 0, FR_ACT_TO_TBL];
+        payload.extend_from_slice(&FIB_RULE_INVERT.to_ne_bytes());
+        payload.extend(attribute(FRA_PRIORITY, &1000u32.to_ne_bytes()));
+        payload.extend(attribute(FRA_SRC, &[10, 8, 0, 0]));
+        payload.extend(attribute(FRA_FWMARK, &0x10u32.to_ne_bytes()));
+        payload.extend(attribute(FRA_FWMASK, &0xffu32.to_ne_bytes()));
+        payload.extend(attribute(FRA_IIFNAME, b"eth0\0"));
+
+        let rule = parse_rule_message(&payload, &names()).unwrap();
+        assert_eq!(
+            rule.to_string(),
+            "1000:\tnot from 10.8.0.0/24 fwmark 0x10/0xff iif eth0 lookup vpn"
+        );
+    }
+
+    #[test]
+    #[ignore = "integration test, reads the routes of the live kernel"]
+    fn test_get_netlink_routes() {
+        let route_table = get_netlink_routes(&crate::probe::RealProbe).unwrap();
+        assert!(!route_table.rules.is_empty());
+        assert!(route_table.table_names().contains(&"local".to_string()));
+        assert!(
+            route_table
+                .lookup(&RouteQuery::new("127.0.0.1".parse().unwrap()))
+                .and_then(|lookup| lookup.route)
+                .is_some_and(|route| route.table_name() == "local")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+
+use crate::probe::SystemProbe;
+use crate::route_table::bsd::parse_bsd_route_output;
+use crate::route_table::linux::parse_linux_route_output;
+use crate::route_table::mac::parse_macos_route_output;
+use crate::route_table::{IpVersion, ParseOptions, ParsedRoutes, RouteTable};
+
+/// The `netstat -rn` output format of an operating system.
+///
+/// The format is chosen at runtime from the name of the operating system, so
+/// every parser is compiled and tested on every platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetstatFlavor {
+    Linux,
+    MacOs,
+    /// FreeBSD, OpenBSD, NetBSD and DragonFly BSD.
+    Bsd,
+}
+
+impl NetstatFlavor {
+    /// Returns the format of the operating system `os`, named as in
+    /// `std::env::consts::OS`, or `None` if it is not supported.
+    pub fn from_os(os: &str) -> Option<Self> {
+        let flavor = match os {
+            "linux" => NetstatFlavor::Linux,
+            "macos" => NetstatFlavor::MacOs,
+            "freebsd" | "openbsd" | "netbsd" | "dragonfly" => NetstatFlavor::Bsd,
+            _ => return None,
+        };
+        Some(flavor)
+    }
+
+    /// Returns the `netstat` invocations that print the route table, with
+    /// the address family each prints. netstat on Linux prints IPv4 routes
+    /// only and on macOS both families at once; the BSDs are asked for each
+    /// family separately.
+    fn commands(self) -> Vec<(&'static [&'static str], IpVersion)> {
+        match self {
+            NetstatFlavor::Linux | NetstatFlavor::MacOs => vec![(&["-rn"], IpVersion::IPv4)],
+            NetstatFlavor::Bsd => vec![
+                (&["-rn", "-f", "inet"], IpVersion::IPv4),
+                (&["-rn", "-f", "inet6"], IpVersion::IPv6),
+            ],
+        }
+    }
+
+    /// Parses `netstat` output in this format. `ip_version` is the address
+    /// family that was asked for.
+    ///
+    /// # Errors
+    ///
+    /// With `strict`, the first line that is skipped is returned as a
+    /// `ParseError`.
+    pub fn parse(self, output: &str, ip_version: IpVersion, strict: bool) -> Result<ParsedRoutes> {
+        match self {
+            NetstatFlavor::Linux => parse_linux_route_output(output, strict),
+            NetstatFlavor::MacOs => parse_macos_route_output(output, strict),
+            NetstatFlavor::Bsd => parse_bsd_route_output(output, ip_version, strict),
+        }
+    }
+}
+
+/// Executes `netstat -rn` with `probe` and parses its output in the format `flavor` into
+/// a `RouteTable`.
+///
+/// On Linux, only the main table is printed. Lines that are not routes are
+/// skipped, or fail the parse with `options.strict`; with `options.verbose`,
+/// a warning is printed for each.
+///
+/// # Errors
+///
+/// If an error occurs while executing the command or parsing the output,
+/// the function returns an error.
+pub fn get_netstat_routes(
+    probe: &dyn SystemProbe,
+    flavor: NetstatFlavor,
+    options: &ParseOptions,
+) -> Result<RouteTable> {
+    let mut route_table = RouteTable::new();
+    for (args, ip_version) in flavor.commands() {
+        let output = probe.run("netstat", args)?;
+
+        if !output.success {
+            return Err(output.failure("Failed to execute netstat command"));
+        }
+
+        let routes = flavor
+            .parse(&output.stdout, ip_version, options.strict)?
+            .into_route_table(options.verbose);
+        route_table.ipv4_routes.extend(routes.ipv4_routes);
+        route_table.ipv6_routes.extend(routes.ipv6_routes);
+    }
+    Ok(route_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flavor_selection() {
+        assert_eq!(NetstatFlavor::from_os("linux"), Some(NetstatFlavor::Linux));
+        assert_eq!(NetstatFlavor::from_os("openbsd"), Some(NetstatFlavor::Bsd));
+        assert_eq!(NetstatFlavor::from_os("windows"), None);
+
+        // The BSD parser runs on any platform
+        let output = include_str!("../../tests/fixtures/netstat/openbsd-inet.txt");
+        let parsed = NetstatFlavor::from_os("openbsd")
+            .unwrap()
+            .parse(output, IpVersion::IPv4, true)
+            .unwrap();
+        assert_eq!(parsed.route_table.ipv4_routes.len(), 8);
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use crate::prefix::Prefix;
+use crate::probe::SystemProbe;
+use crate::route_table::{
+    IpVersion, ParseError, ParseOptions, ParsedRoutes, RouteEntry, RouteTable,
+};
+
+/// `RTF_*` route flags of `/proc/net/route` and the letters `route -n`
+/// prints for them.
+const IPV4_FLAGS: [(u32, char); 7] = [
+    (0x0001, 'U'),
+    (0x0002, 'G'),
+    (0x0004, 'H'),
+    (0x0008, 'R'),
+    (0x0010, 'D'),
+    (0x0020, 'M'),
+    (0x0200, '!'),
+];
+
+const RTF_UP: u32 = 0x0001;
+const RTF_GATEWAY: u32 = 0x0002;
+const RTF_REJECT: u32 = 0x0200;
+
+/// Returns the route entry for `prefix`, in the style of
+/// `get_netlink_routes`: `default` for the default route and the prefix
+/// otherwise.
+fn route_entry(prefix: Prefix, gateway: IpAddr, iface: &str, flags: String) -> RouteEntry {
+    let (ip_version, genmask) = match prefix.network() {
+        IpAddr::V4(_) => (IpVersion::IPv4, Some(prefix.netmask().to_string())),
+        IpAddr::V6(_) => (IpVersion::IPv6, None),
+    };
+    RouteEntry {
+        destination: if prefix.prefix_len() == 0 {
+            "default".to_string()
+        } else {
+            prefix.to_string()
+        },
+        gateway: gateway.to_string(),
+        flags,
+        // Routes without an interface, such as blackhole routes, show `*`
+        iface: if iface == "*" { "-" } else { iface }.to_string(),
+        ip_version,
+        genmask,
+        ..Default::default()
+    }
+}
+
+/// Parses a hex field of procfs.
+fn hex(field: &str, name: &str) -> Result<u32, String> {
+    u32::from_str_radix(field, 16).map_err(|_| format!("invalid {} '{}'", name, field))
+}
+
+/// Parses an IPv4 address of `/proc/net/route`, printed as the hex value of
+/// the address in network byte order read as a native integer.
+fn ipv4(field: &str, name: &str) -> Result<Ipv4Addr, String> {
+    Ok(Ipv4Addr::from(hex(field, name)?.to_ne_bytes()))
+}
+
+/// Parses a line of `/proc/net/route`:
+/// `Iface Destination Gateway Flags RefCnt Use Metric Mask MTU Window IRTT`.
+fn parse_ipv4_line(line: &str) -> Result<RouteEntry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 11 {
+        return Err(format!("expected 11 columns, found {}", fields.len()));
+    }
+    let destination = ipv4(fields[1], "destination")?;
+    let gateway = ipv4(fields[2], "gateway")?;
+    let flags = hex(fields[3], "flags")?;
+    let mask = ipv4(fields[7], "mask")?;
+
+    let letters = IPV4_FLAGS
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, letter)| *letter)
+        .collect();
+    let mut route = route_entry(
+        Prefix::from_netmask(IpAddr::V4(destination), IpAddr::V4(mask)),
+        IpAddr::V4(gateway),
+        fields[0],
+        letters,
+    );
+    route.refs = fields[4].parse().ok();
+    route.use_count = fields[5].parse().ok();
+    route.metric = fields[6].parse().ok();
+    // The `MTU` column holds the MSS, as `netstat -rn` shows
+    route.mss = fields[8].parse().ok();
+    route.window = fields[9].parse().ok();
+    route.irtt = fields[10].parse().ok();
+    Ok(route)
+}
+
+/// Parses an IPv6 address of `/proc/net/ipv6_route`, printed as 32 hex
+/// digits.
+fn ipv6(field: &str, name: &str) -> Result<Ipv6Addr, String> {
+    u128::from_str_radix(field, 16)
+        .ok()
+        .filter(|_| field.len() == 32)
+        .map(Ipv6Addr::from)
+        .ok_or_else(|| format!("invalid {} '{}'", name, field))
+}
+
+/// Parses a line of `/proc/net/ipv6_route`: destination, prefix length,
+/// source, source prefix length, next hop, metric, reference count, use
+/// count, flags and interface. Returns `None` for the kernel's null entry
+/// (`::/0` rejected with metric `0xffffffff`), which is not a route of any
+/// table.
+fn parse_ipv6_line(line: &str) -> Result<Option<RouteEntry>, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return Err(format!("expected 10 columns, found {}", fields.len()));
+    }
+    let destination = ipv6(fields[0], "destination")?;
+    let prefix_len = u8::from_str_radix(fields[1], 16)
+        .ok()
+        .filter(|len| *len <= 128)
+        .ok_or_else(|| format!("invalid prefix length '{}'", fields[1]))?;
+    let gateway = ipv6(fields[4], "next hop")?;
+    let metric = hex(fields[5], "metric")?;
+    let flags = hex(fields[8], "flags")?;
+    if flags & RTF_REJECT != 0 && prefix_len == 0 && metric == u32::MAX {
+        return Ok(None);
+    }
+
+    let mut letters = String::new();
+    if flags & RTF_REJECT != 0 {
+        letters.push('!');
+    } else if flags & RTF_UP != 0 {
+        letters.push('U');
+    }
+    if flags & RTF_GATEWAY != 0 {
+        letters.push('G');
+    }
+    if prefix_len == 128 {
+        letters.push('H');
+    }
+    let mut route = route_entry(
+        Prefix::new(IpAddr::V6(destination), prefix_len),
+        IpAddr::V6(gateway),
+        fields[9],
+        letters,
+    );
+    route.metric = Some(metric);
+    route.refs = hex(fields[6], "reference count").ok();
+    route.use_count = hex(fields[7], "use count").ok().map(u64::from);
+    Ok(Some(route))
+}
+
+/// Parses `/proc/net/route` (`ipv6` false) or `/proc/net/ipv6_route`
+/// (`ipv6` true) and returns the routes it contains.
+///
+/// The header line of `/proc/net/route` is ignored. Lines that cannot be
+/// parsed are skipped and recorded with their line number in
+/// `ParsedRoutes::skipped`.
+///
+/// # Errors
+///
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_procfs_routes(output: &str, ipv6: bool, strict: bool) -> Result<ParsedRoutes> {
+    let mut parsed = ParsedRoutes::default();
+    for (index, line) in output.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("Iface") {
+            continue;
+        }
+        let result = if ipv6 {
+            parse_ipv6_line(trimmed)
+        } else {
+            parse_ipv4_line(trimmed).map(Some)
+        };
+        match result {
+            Ok(Some(route)) => parsed.route_table.add_route(route),
+            Ok(None) => {}
+            Err(reason) => parsed.skip(
+                ParseError {
+                    line_number: index + 1,
+                    line: trimmed.to_string(),
+                    reason,
+                },
+                strict,
+            )?,
+        }
+    }
+    Ok(parsed)
+}
+
+/// Reads the route table from `/proc/net/route` and `/proc/net/ipv6_route`
+/// on Linux with `probe`.
+///
+/// The IPv4 routes are those of the main table; the IPv6 routes are those of
+/// all tables without telling them apart. Neither file has the routing
+/// protocol, scope or policy rules.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read, or a line cannot be parsed
+/// with `options.strict`.
+pub fn get_procfs_routes(probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+    let mut route_table = RouteTable::new();
+    for (path, ipv6) in [("/proc/net/route", false), ("/proc/net/ipv6_route", true)] {
+        let output = probe.read_file(Path::new(path))?;
+        let routes =
+            parse_procfs_routes(&output, ipv6, options.strict)?.into_route_table(options.verbose);
+        route_table.ipv4_routes.extend(routes.ipv4_routes);
+        route_table.ipv6_routes.extend(routes.ipv6_routes);
+    }
+    Ok(route_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_route() {
+        let output = include_str!("../../tests/fixtures/procfs/route");
+        let routes = parse_procfs_routes(output, false, true)
+            .unwrap()
+            .route_table
+            .ipv4_routes;
+        let described: Vec<(&str, &str, &str, &str)> = routes
+            .iter()
+            .map(|route| {
+                (
+                    route.destination.as_str(),
+                    route.gateway.as_str(),
+                    route.flags.as_str(),
+                    route.iface.as_str(),
+                )
+            })
+            .collect();
+        if cfg!(target_endian = "little") {
+            assert_eq!(
+                described,
+                [
+                    ("default", "10.8.0.1", "UG", "tun0"),
+                    ("10.0.0.5/32", "0.0.0.0", "UH", "tun0"),
+                    ("10.8.0.0/24", "0.0.0.0", "U", "tun0"),
+                    ("10.9.0.0/16", "0.0.0.0", "U", "-"),
+                ]
+            );
+            assert_eq!(routes[3].genmask.as_deref(), Some("255.255.0.0"));
+        }
+        assert_eq!(routes[0].metric, Some(100));
+    }
+
+    #[test]
+    fn test_parse_proc_net_ipv6_route() {
+        let output = include_str!("../../tests/fixtures/procfs/ipv6_route");
+        let parsed = parse_procfs_routes(output, true, true).unwrap();
+        let routes = &parsed.route_table.ipv6_routes;
+        // Without the null entry
+        assert_eq!(routes.len(), 6);
+        assert_eq!(
+            (routes[0].destination.as_str(), routes[0].flags.as_str()),
+            ("2001:db8:1::/48", "!")
+        );
+        let default = &routes[2];
+        assert_eq!(default.destination, "default");
+        assert_eq!(
+            (default.gateway.as_str(), default.flags.as_str()),
+            ("fd00:8::1", "UG")
+        );
+        assert_eq!(default.metric, Some(1024));
+        assert_eq!(routes[4].flags, "UH");
+
+        let parsed = parse_procfs_routes("fd00 40 garbage\n", true, false).unwrap();
+        assert_eq!(parsed.skipped[0].reason, "expected 10 columns, found 3");
+    }
+}
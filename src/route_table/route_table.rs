@@ -1,249 +1,275 @@
 use anyhow::{Result, anyhow};
-use colored::Colorize;
-use std::process::Command;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
 
-use crate::route_table::linux::get_linux_routes;
-use crate::route_table::mac::get_macos_routes;
-use crate::route_table::{IpVersion, RouteEntry, RouteTable};
+use crate::probe::{RealProbe, SystemProbe};
+use crate::render::{Element, Output};
+use crate::route_table::filter::RouteFilter;
+use crate::route_table::rules::{RouteQuery, read_table_names};
+use crate::route_table::source::{RouteSource, auto_sources, read_first};
+use crate::route_table::view::RouteView;
+use crate::route_table::{IpVersion, ParseOptions, RouteTable};
 
-/// Finds the maximum length of a given field in a vector of `RouteEntry`s.
-///
-/// Given a vector of `RouteEntry`s and the name of a field, this function
-/// returns the maximum length of that field in all of the `RouteEntry`s. If
-/// the field is not present in any of the `RouteEntry`s, it returns 0.
+/// Where and how route tables are collected: from the source chosen with
+/// `--route-source` or `--from-file`, parsing command output with the
+/// options of `--strict` and `--verbose`.
+#[derive(Clone, Default)]
+pub struct RouteCollector {
+    /// The source to read routes from, or `None` to auto-detect it.
+    pub source: Option<Arc<dyn RouteSource>>,
+    pub options: ParseOptions,
+}
+
+impl RouteCollector {
+    /// Collects the system's route table.
+    ///
+    /// The route table is read from `source`, or else from the first source
+    /// of `auto_sources` for the running system that is available and
+    /// succeeds. On Linux, all routing tables and the policy routing rules
+    /// are read over netlink, falling back to `ip route` (all tables but no
+    /// rules) for images without net-tools, `/proc/net` and then
+    /// `netstat -rn` (main table only). On macOS and the BSDs, the output of
+    /// `netstat -rn` is parsed, and on Windows that of `route print`.
+    ///
+    /// # Errors
+    ///
+    /// If the function encounters an error while executing the command, a
+    /// line cannot be parsed in strict mode or the operating system is not
+    /// supported, it returns an error.
+    pub fn collect(&self) -> Result<RouteTable> {
+        self.collect_from(&RealProbe)
+    }
+
+    /// Collects the route table of the system behind `probe`, as described
+    /// for `collect`.
+    ///
+    /// # Errors
+    ///
+    /// See `collect`.
+    pub fn collect_from(&self, probe: &dyn SystemProbe) -> Result<RouteTable> {
+        match &self.source {
+            Some(source) => source.read(probe, &self.options),
+            None => read_first(&auto_sources(probe.os()), probe, &self.options),
+        }
+    }
+
+    /// Returns the routing table names configured for iproute2 on the system,
+    /// to resolve the tables of the collected routes by name or ID.
+    pub fn table_names(&self) -> HashMap<u32, String> {
+        read_table_names(&RealProbe)
+    }
+}
+
+/// Writes the policy routing rules in the format of `ip rule`.
+fn write_rules(
+    output: &mut Output,
+    route_table: &RouteTable,
+    ip_version: &IpVersion,
+) -> Result<()> {
+    for rule in route_table
+        .rules
+        .iter()
+        .filter(|rule| &rule.ip_version == ip_version)
+    {
+        let text = rule.to_string();
+        match text.split_once('\t') {
+            Some((priority, rest)) => {
+                let priority = output.paint(Element::Header, priority);
+                writeln!(output, "{}\t{}", priority, rest)?
+            }
+            None => writeln!(output, "{}", text)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes which policy rule and route the kernel would choose for the
+/// traffic described by `query` to `output`, in the route table collected by
+/// `routes`.
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `routes`: The vector of `RouteEntry`s to search.
-/// * `field`: The name of the field to search for.
+/// Returns an error if the route table cannot be collected, no route
+/// matches the destination or the output cannot be written.
+pub fn display_route_lookup(
+    output: &mut Output,
+    routes: &RouteCollector,
+    query: &RouteQuery,
+) -> Result<()> {
+    let route_table = routes.collect()?;
+    let lookup = route_table
+        .lookup(query)
+        .ok_or_else(|| anyhow!("No route to {}", query.dst))?;
+
+    let title = output.paint(Element::Title, "Route lookup for");
+    let dst = output.paint(Element::Address, &query.dst.to_string());
+    writeln!(output, "{} {}", title, dst)?;
+    if let Some(rule) = lookup.rule {
+        let label = output.paint(Element::Header, "Rule: ");
+        writeln!(output, "{} {}", label, rule.to_string().replace('\t', " "))?;
+    }
+    let label = output.paint(Element::Header, "Route:");
+    match lookup.route {
+        Some(route) => writeln!(output, "{} {}", label, route.describe())?,
+        None => {
+            let rejected = output.paint(Element::Error, "rejected by rule");
+            writeln!(output, "{} {}", label, rejected)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the system's route table, as collected by `routes`, to `output`
+/// with `render_route_table`.
 ///
-/// # Return
+/// # Errors
 ///
-/// The maximum length of the field.
-fn get_max_len(routes: &Vec<RouteEntry>, field: &str) -> usize {
-    routes
-        .iter()
-        .map(|route| route.get_field(field).map(|value| value.len()).unwrap_or(0))
-        .max()
-        .unwrap_or(0)
+/// If the function encounters an error while executing the command, getting
+/// the route table or writing the output, it returns an error.
+pub fn get_route_table(
+    output: &mut Output,
+    routes: &RouteCollector,
+    protocol: &str,
+    table: &str,
+    filter: &RouteFilter,
+    view: &RouteView,
+) -> Result<()> {
+    render_route_table(
+        output,
+        &routes.collect()?,
+        protocol,
+        table,
+        &routes.table_names(),
+        filter,
+        view,
+    )
 }
 
-/// Prints the system's route table to stdout.
-///
-/// On Windows, this function simply executes the `route print` command and
-/// prints the output to stdout. On other platforms, it uses the
-/// `get_macos_routes` or `get_linux_routes` functions to get the route table and
-/// prints it to stdout.
+/// Writes `route_table` to `output`.
 ///
 /// The `protocol` argument can be either "ipv4", "ipv6", or "all". If "all" is
-/// specified, the function prints both the IPv4 and IPv6 routes. If "ipv4" or
-/// "ipv6" is specified, the function only prints the routes for that protocol.
+/// specified, the function writes both the IPv4 and IPv6 routes. If "ipv4" or
+/// "ipv6" is specified, the function only writes the routes for that protocol.
 ///
-/// The function prints the routes in a fixed-width format, with each field
-/// aligned to the maximum length of that field in the route table.
+/// The `table` argument selects the routing table to write, by name or by an
+/// ID resolved through `names`.
+/// If "all" is specified, every table is written in the order the policy
+/// routing rules reference them, followed by the rules themselves.
 ///
-/// If the function encounters an error while executing the command or getting
-/// the route table, it returns an error.
+/// Only the routes matching `filter` are written; the default gateway is
+/// shown regardless.
+///
+/// The routes are written as a table with the columns and order given by
+/// `view`. As text, each column is aligned to its widest value, and the
+/// default gateway is resolved through the policy routing rules. As
+/// Markdown, each routing table gets a heading; as CSV, the routes of all
+/// tables are written as a single table without the rules and default
+/// gateways.
 ///
 /// # Errors
 ///
-/// If the function encounters an error while executing the command or getting
-/// the route table, it returns an error.
-pub fn get_route_table(protocol: &str) -> Result<()> {
-    if cfg!(target_os = "windows") {
-        // TODO! parse not implemented
-        return match Command::new("route").args(&["print"]).output() {
-            Ok(output) => {
-                if output.status.success() {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
+/// If the output cannot be written, the function returns an error.
+pub fn render_route_table(
+    output: &mut Output,
+    route_table: &RouteTable,
+    protocol: &str,
+    table: &str,
+    names: &HashMap<u32, String>,
+    filter: &RouteFilter,
+    view: &RouteView,
+) -> Result<()> {
+    let tables = match route_table.resolve_table(table, names)?.as_str() {
+        "all" => route_table.table_names(),
+        name => vec![name.to_string()],
+    };
+    let markdown = output.format == "markdown";
+    let mut csv_routes = Vec::new();
 
-                    println!("Route table:\n{}", stdout);
-                    Ok(())
-                } else {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    Err(anyhow!("Error executing command: {}", stderr))
-                }
-            }
-            Err(e) => Err(anyhow!("Error executing command: {}", e)),
+    if output.is_text() {
+        let title = output.paint(Element::Title, "\nLocal Network Routes Table");
+        writeln!(output, "{}", title)?;
+    }
+    for (ip_version, label) in [(IpVersion::IPv4, "IPv4"), (IpVersion::IPv6, "IPv6")] {
+        let selected = match ip_version {
+            IpVersion::IPv4 => protocol == "ipv4" || protocol == "all",
+            IpVersion::IPv6 => protocol == "ipv6" || protocol == "all",
         };
-    } else {
-        let route_table: RouteTable;
-        if cfg!(target_os = "macos") {
-            route_table = get_macos_routes()?;
-        } else if cfg!(target_os = "linux") {
-            route_table = get_linux_routes()?;
-        } else {
-            return Err(anyhow!("Unsupported operating system"));
+        if !selected {
+            continue;
         }
 
-        println!("{}", "\nLocal Network Routes Table".green().bold());
-        if protocol == "ipv4" || protocol == "all" {
-            println!(
-                "{}",
-                "================ IPv4 Routes ================".green()
-            );
-            for route in &route_table.ipv4_routes {
-                println!(
-                    "{} {} {} {} {}",
-                    format!(
-                        "{:width$}",
-                        {
-                            let route = &route.destination;
-                            if route == "Destination" {
-                                route.blue().bold()
-                            } else {
-                                route.yellow()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv4_routes, "destination")
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let gateway = &route.gateway;
-                            if gateway == "Gateway" {
-                                gateway.blue().bold()
-                            } else {
-                                gateway.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv4_routes, "gateway") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let flags = &route.flags;
-                            if flags == "Flags" {
-                                flags.blue().bold()
-                            } else {
-                                flags.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv4_routes, "flags") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let iface = &route.iface;
-                            if iface == "Iface" || iface == "Netif" {
-                                iface.blue().bold()
-                            } else {
-                                iface.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv4_routes, "iface") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let expire = &route.clone().expire.unwrap_or("".to_string());
-                            if expire == "Expire" {
-                                expire.blue().bold()
-                            } else {
-                                expire.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv4_routes, "expire")
-                    )
-                );
+        for name in &tables {
+            let mut routes = route_table.table(name);
+            filter.apply(&mut routes);
+            if tables.len() > 1 && routes.routes(&ip_version).is_empty() {
+                continue;
             }
-            println!(
-                "{}",
-                "============ IPv4 Default Gateway ===========".green()
-            );
-            if let Some(ipv4_gateway) = route_table.get_default_gateway(IpVersion::IPv4) {
-                println!(
-                    "{}{} via {}\n",
-                    "IPv4 Default Gateway: ".blue().bold(),
-                    ipv4_gateway.gateway.yellow(),
-                    ipv4_gateway.iface.bold()
-                );
+            if output.format == "csv" {
+                csv_routes.extend(routes.routes(&ip_version).iter().cloned());
+                continue;
             }
+            let title = if name == "main" {
+                format!(" {} Routes ", label)
+            } else {
+                format!(" {} Routes (table {}) ", label, name)
+            };
+            if markdown {
+                writeln!(output, "##{}\n", title.trim_end())?;
+            } else {
+                let title = output.paint(Element::Separator, &format!("{:=^45}", title));
+                writeln!(output, "{}", title)?;
+            }
+            output.table(&view.table(routes.routes(&ip_version)))?;
+            if markdown {
+                writeln!(output)?;
+            }
+        }
+        if output.format == "csv" {
+            continue;
         }
 
-        if protocol == "ipv6" || protocol == "all" {
-            println!(
-                "{}",
-                "================ IPv6 Routes ================".green()
-            );
-            for route in &route_table.ipv6_routes {
-                println!(
-                    "{} {} {} {} {}",
-                    format!(
-                        "{:width$}",
-                        {
-                            let route = &route.destination;
-                            if route == "Destination" {
-                                route.blue().bold()
-                            } else {
-                                route.yellow()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv6_routes, "destination")
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let gateway = &route.gateway;
-                            if gateway == "Gateway" {
-                                gateway.blue().bold()
-                            } else {
-                                gateway.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv6_routes, "gateway") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let flags = &route.flags;
-                            if flags == "Flags" {
-                                flags.blue().bold()
-                            } else {
-                                flags.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv6_routes, "flags") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let iface = &route.iface;
-                            if iface == "Iface" || iface == "Netif" {
-                                iface.blue().bold()
-                            } else {
-                                iface.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv6_routes, "iface") + 2
-                    ),
-                    format!(
-                        "{:width$}",
-                        {
-                            let expire = &route.clone().expire.unwrap_or("".to_string());
-                            if expire == "Expire" {
-                                expire.blue().bold()
-                            } else {
-                                expire.normal()
-                            }
-                        },
-                        width = get_max_len(&route_table.ipv6_routes, "expire")
-                    )
-                );
+        if table == "all" && !route_table.rules.is_empty() {
+            if markdown {
+                writeln!(output, "## {} Rules\n\n```", label)?;
+                write_rules(output, route_table, &ip_version)?;
+                writeln!(output, "```\n")?;
+            } else {
+                let title = format!("{:=^45}", format!(" {} Rules ", label));
+                let title = output.paint(Element::Separator, &title);
+                writeln!(output, "{}", title)?;
+                write_rules(output, route_table, &ip_version)?;
             }
-            println!(
-                "{}",
-                "============ IPv6 Default Gateway ===========".green()
-            );
-            if let Some(ipv6_gateway) = route_table.get_default_gateway(IpVersion::IPv6) {
-                println!(
-                    "{}{} via {}\n",
-                    "IPv6 Default Gateway: ".blue().bold(),
-                    ipv6_gateway.gateway.yellow(),
-                    ipv6_gateway.iface.bold()
-                );
+        }
+
+        let gateway = route_table.get_default_gateway(ip_version.clone());
+        if markdown {
+            if let Some(gateway) = gateway {
+                writeln!(
+                    output,
+                    "{} default gateway: `{}` via `{}`\n",
+                    label, gateway.gateway, gateway.iface
+                )?;
             }
+            continue;
         }
+        let title = format!("{:=^45}", format!(" {} Default Gateway ", label));
+        let title = output.paint(Element::Separator, &title);
+        writeln!(output, "{}", title)?;
+        if let Some(gateway) = gateway {
+            let table_info = match gateway.table_name() {
+                "main" => String::new(),
+                name => format!(" (table {})", name),
+            };
+            let label = output.paint(Element::Header, &format!("{} Default Gateway: ", label));
+            let address = output.paint(Element::Gateway, &gateway.gateway);
+            let iface = output.paint(Element::Name, &gateway.iface);
+            writeln!(output, "{}{} via {}{}\n", label, address, iface, table_info)?;
+        }
+    }
+    if output.format == "csv" {
+        output.table(&view.table(&csv_routes))?;
     }
 
     Ok(())
@@ -252,19 +278,275 @@ pub fn get_route_table(protocol: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::render::Style;
+
+    #[test]
+    fn test_display_route_lookup() {
+        use crate::golden::fixture;
+        use crate::route_table::source::CapturedOutput;
+
+        let routes = RouteCollector {
+            source: Some(Arc::new(CapturedOutput {
+                format: "iproute2".to_string(),
+                output: fixture("iproute2/debian12-ipv4.json"),
+            })),
+            ..RouteCollector::default()
+        };
+        let mut buffer = Vec::new();
+        let mut output = Output::new(
+            &mut buffer,
+            "text",
+            Style {
+                color: false,
+                ..Style::default()
+            },
+        );
+        display_route_lookup(
+            &mut output,
+            &routes,
+            &RouteQuery::new("10.20.1.1".parse().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Route lookup for 10.20.1.1\nRoute: 10.20.0.0/16 via 10.8.0.254 dev tun0 table main proto boot\n"
+        );
+    }
 
     #[test]
-    fn test_get_v4_route_table() {
-        get_route_table("ipv4").unwrap();
+    fn test_render_route_table_golden() {
+        use crate::golden::{assert_golden, fixture, strip_colors};
+        use crate::probe::FakeProbe;
+        use crate::route_table::mac::parse_macos_route_output;
+        use crate::route_table::view::RouteColumn;
+        use crate::snapshot::Snapshot;
+
+        let debian12: Snapshot = serde_json::from_str(&fixture("snapshot/debian12.json")).unwrap();
+        let macos = parse_macos_route_output(&fixture("netstat/macos-14-rn.txt"), true)
+            .unwrap()
+            .route_table;
+        // Only the reserved tables, whatever the host configures
+        let names = read_table_names(&FakeProbe::new("linux"));
+        let detailed = RouteView {
+            columns: vec![
+                RouteColumn::Dest,
+                RouteColumn::Gw,
+                RouteColumn::Iface,
+                RouteColumn::Metric,
+                RouteColumn::Proto,
+                RouteColumn::Table,
+            ],
+            sort: Some("metric".to_string()),
+            reverse: false,
+        };
+        let cases = [
+            (
+                "debian12-ipv4.txt",
+                &debian12.routes,
+                "text",
+                "ipv4",
+                "main",
+                RouteView::default(),
+            ),
+            (
+                "debian12-all-tables.txt",
+                &debian12.routes,
+                "text",
+                "all",
+                "all",
+                RouteView::default(),
+            ),
+            (
+                "debian12-all-tables.csv",
+                &debian12.routes,
+                "csv",
+                "all",
+                "all",
+                detailed.clone(),
+            ),
+            (
+                "debian12-all-tables.md",
+                &debian12.routes,
+                "markdown",
+                "all",
+                "all",
+                detailed,
+            ),
+            (
+                "macos-14-all.txt",
+                &macos,
+                "text",
+                "all",
+                "main",
+                RouteView::default(),
+            ),
+            (
+                "macos-14-ipv6.md",
+                &macos,
+                "markdown",
+                "ipv6",
+                "main",
+                RouteView::default(),
+            ),
+        ];
+        for (name, route_table, format, protocol, table, view) in cases {
+            let mut buffer = Vec::new();
+            let mut output = Output::new(
+                &mut buffer,
+                format,
+                Style {
+                    color: false,
+                    ..Style::default()
+                },
+            );
+            render_route_table(
+                &mut output,
+                route_table,
+                protocol,
+                table,
+                &names,
+                &RouteFilter::default(),
+                &view,
+            )
+            .unwrap();
+            assert_golden(
+                &format!("routes/{}", name),
+                &String::from_utf8(buffer).unwrap(),
+            );
+        }
+
+        // Colors only add escape codes
+        let mut colored = Vec::new();
+        let mut output = Output::new(&mut colored, "text", Style::default());
+        render_route_table(
+            &mut output,
+            &debian12.routes,
+            "ipv4",
+            "main",
+            &names,
+            &RouteFilter::default(),
+            &RouteView::default(),
+        )
+        .unwrap();
+        let colored = String::from_utf8(colored).unwrap();
+        assert_golden("routes/debian12-ipv4.txt", &strip_colors(&colored));
     }
 
     #[test]
-    fn test_get_v6_route_table() {
-        get_route_table("ipv6").unwrap();
+    fn test_collect_route_table_golden() {
+        use crate::golden::{assert_golden_json, fixture};
+        use crate::probe::FakeProbe;
+
+        let debian12 = || {
+            FakeProbe::new("linux")
+                .file("/proc/net/route", &fixture("procfs/debian12-route"))
+                .file(
+                    "/proc/net/ipv6_route",
+                    &fixture("procfs/debian12-ipv6_route"),
+                )
+        };
+        let cases = [
+            (
+                "linux-debian12-iproute2",
+                debian12()
+                    .command(
+                        "ip -j -d -4 route show table all",
+                        &fixture("iproute2/debian12-ipv4.json"),
+                    )
+                    .command(
+                        "ip -j -d -6 route show table all",
+                        &fixture("iproute2/debian12-ipv6.json"),
+                    ),
+            ),
+            // iproute2 before 4.13 has no JSON output
+            (
+                "linux-iproute2-text",
+                FakeProbe::new("linux")
+                    .failing_command(
+                        "ip -j -d -4 route show table all",
+                        "Option \"-j\" is unknown, try \"ip -help\".",
+                    )
+                    .failing_command(
+                        "ip -j -d -6 route show table all",
+                        "Option \"-j\" is unknown, try \"ip -help\".",
+                    )
+                    .command(
+                        "ip -d -4 route show table all",
+                        &fixture("iproute2/route-ipv4.txt"),
+                    )
+                    .command(
+                        "ip -d -6 route show table all",
+                        &fixture("iproute2/route-ipv6.txt"),
+                    ),
+            ),
+            ("linux-debian12-procfs", debian12()),
+            (
+                "linux-debian12-netstat",
+                FakeProbe::new("linux")
+                    .command("netstat -rn", &fixture("netstat/linux-debian12-rn.txt")),
+            ),
+            (
+                "macos-10.15",
+                FakeProbe::new("macos")
+                    .command("netstat -rn", &fixture("netstat/macos-10.15-rn.txt")),
+            ),
+            (
+                "macos-14",
+                FakeProbe::new("macos").command("netstat -rn", &fixture("netstat/macos-14-rn.txt")),
+            ),
+            (
+                "windows",
+                FakeProbe::new("windows")
+                    .command("route print", &fixture("windows/route-print.txt")),
+            ),
+        ];
+        for (name, probe) in cases {
+            let route_table = RouteCollector::default().collect_from(&probe).unwrap();
+            assert_golden_json(&format!("routes/{}.json", name), &route_table);
+        }
+
+        for bsd in ["freebsd", "openbsd", "netbsd"] {
+            let probe = FakeProbe::new(bsd)
+                .command(
+                    "netstat -rn -f inet",
+                    &fixture(&format!("netstat/{}-inet.txt", bsd)),
+                )
+                .command(
+                    "netstat -rn -f inet6",
+                    &fixture(&format!("netstat/{}-inet6.txt", bsd)),
+                );
+            let route_table = RouteCollector::default().collect_from(&probe).unwrap();
+            assert_golden_json(&format!("routes/{}.json", bsd), &route_table);
+        }
     }
 
     #[test]
-    fn test_get_all_route_table() {
-        get_route_table("all").unwrap();
+    fn test_collect_route_table_without_source() {
+        use crate::error::ErrorKind;
+        use crate::probe::FakeProbe;
+
+        let error = RouteCollector::default()
+            .collect_from(&FakeProbe::new("plan9"))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported operating system: plan9");
+
+        let probe =
+            FakeProbe::new("linux").failing_command("netstat -rn", "netstat: Permission denied");
+        let error = RouteCollector::default().collect_from(&probe).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::PermissionDenied);
+        let error = error.to_string();
+        assert!(error.starts_with("No route source could read the route table"));
+        assert!(error.contains("procfs: not available"), "{}", error);
+        assert!(
+            error.contains(
+                "netstat-linux: Failed to execute netstat command: netstat: Permission denied"
+            ),
+            "{}",
+            error
+        );
+
+        let probe = FakeProbe::new("linux").failing_command("netstat -rn", "netstat: not found");
+        let error = RouteCollector::default().collect_from(&probe).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::RouteSourceUnavailable);
     }
 }
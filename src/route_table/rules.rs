@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use crate::prefix::Prefix;
+use crate::probe::SystemProbe;
+use crate::route_table::IpVersion;
+
+/// Files mapping routing table IDs to names, as used by iproute2.
+const RT_TABLES_FILES: [&str; 3] = [
+    "/usr/share/iproute2/rt_tables",
+    "/usr/lib/iproute2/rt_tables",
+    "/etc/iproute2/rt_tables",
+];
+
+/// Directories with additional `*.conf` table name files.
+const RT_TABLES_DIRS: [&str; 3] = [
+    "/usr/share/iproute2/rt_tables.d",
+    "/usr/lib/iproute2/rt_tables.d",
+    "/etc/iproute2/rt_tables.d",
+];
+
+/// What a policy routing rule does with matching traffic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "target", rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Look up the route in the named table.
+    Table(String),
+    /// Continue with the rule of the given priority.
+    Goto(u32),
+    /// Do nothing and continue with the next rule.
+    Nop,
+    /// Silently drop the traffic.
+    Blackhole,
+    /// Reject the traffic as unreachable.
+    Unreachable,
+    /// Reject the traffic as administratively prohibited.
+    Prohibit,
+}
+
+/// A policy routing rule, as listed by `ip rule`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteRule {
+    pub priority: u32,
+    pub ip_version: IpVersion,
+    /// The rule matches traffic that does *not* match its selectors.
+    pub invert: bool,
+    pub from: Option<Prefix>,
+    pub to: Option<Prefix>,
+    /// Firewall mark and mask.
+    pub fwmark: Option<(u32, u32)>,
+    pub iif: Option<String>,
+    pub oif: Option<String>,
+    #[serde(flatten)]
+    pub action: RuleAction,
+    /// Ignore routes from the table with a prefix length of this value or
+    /// less.
+    pub suppress_prefixlen: Option<u8>,
+}
+
+/// The properties of a packet that policy routing rules select on.
+#[derive(Debug, Clone)]
+pub struct RouteQuery {
+    pub dst: IpAddr,
+    /// The source address; unset for locally generated traffic before a
+    /// source address has been chosen.
+    pub src: Option<IpAddr>,
+    pub fwmark: Option<u32>,
+    /// The incoming interface; unset for locally generated traffic, which
+    /// the kernel treats as coming from `lo`.
+    pub iif: Option<String>,
+    pub oif: Option<String>,
+}
+
+impl RouteQuery {
+    pub fn new(dst: IpAddr) -> Self {
+        Self {
+            dst,
+            src: None,
+            fwmark: None,
+            iif: None,
+            oif: None,
+        }
+    }
+}
+
+impl RouteRule {
+    /// Returns `true` if the rule selects the traffic described by `query`.
+    pub fn matches(&self, query: &RouteQuery) -> bool {
+        let matches = self.selectors_match(query);
+        if self.invert { !matches } else { matches }
+    }
+
+    fn selectors_match(&self, query: &RouteQuery) -> bool {
+        if let Some(from) = &self.from {
+            let src = query.src.unwrap_or(match query.dst {
+                IpAddr::V4(_) => IpAddr::from([0u8; 4]),
+                IpAddr::V6(_) => IpAddr::from([0u8; 16]),
+            });
+            if !from.contains(src) {
+                return false;
+            }
+        }
+
+        if let Some(to) = &self.to
+            && !to.contains(query.dst)
+        {
+            return false;
+        }
+
+        if let Some((mark, mask)) = self.fwmark
+            && query.fwmark.unwrap_or(0) & mask != mark
+        {
+            return false;
+        }
+
+        if let Some(iif) = &self.iif
+            && query.iif.as_deref().unwrap_or("lo") != iif
+        {
+            return false;
+        }
+
+        if let Some(oif) = &self.oif
+            && query.oif.as_deref() != Some(oif.as_str())
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+impl fmt::Display for RouteRule {
+    /// Formats the rule the way `ip rule` prints it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:\t", self.priority)?;
+        if self.invert {
+            write!(f, "not ")?;
+        }
+        match &self.from {
+            Some(from) => write!(f, "from {}", from)?,
+            None => write!(f, "from all")?,
+        }
+        if let Some(to) = &self.to {
+            write!(f, " to {}", to)?;
+        }
+        if let Some((mark, mask)) = self.fwmark {
+            if mask == u32::MAX {
+                write!(f, " fwmark {:#x}", mark)?;
+            } else {
+                write!(f, " fwmark {:#x}/{:#x}", mark, mask)?;
+            }
+        }
+        if let Some(iif) = &self.iif {
+            write!(f, " iif {}", iif)?;
+        }
+        if let Some(oif) = &self.oif {
+            write!(f, " oif {}", oif)?;
+        }
+        match &self.action {
+            RuleAction::Table(table) => write!(f, " lookup {}", table)?,
+            RuleAction::Goto(priority) => write!(f, " goto {}", priority)?,
+            RuleAction::Nop => write!(f, " nop")?,
+            RuleAction::Blackhole => write!(f, " blackhole")?,
+            RuleAction::Unreachable => write!(f, " unreachable")?,
+            RuleAction::Prohibit => write!(f, " prohibit")?,
+        }
+        if let Some(len) = self.suppress_prefixlen {
+            write!(f, " suppress_prefixlength {}", len)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a firewall mark given in decimal or as hex with a `0x` prefix.
+pub fn parse_fwmark(value: &str) -> Result<u32, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|e| format!("invalid firewall mark '{}': {}", value, e))
+}
+
+/// Parses the content of an iproute2 `rt_tables` file into `(id, name)`
+/// pairs.
+///
+/// Comments and malformed lines are skipped.
+pub fn parse_rt_tables(content: &str) -> Vec<(u32, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.trim();
+            let mut parts = line.split_whitespace();
+            let id = parts.next()?;
+            let name = parts.next()?;
+            let id = match id.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => id.parse().ok()?,
+            };
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the routing table names configured for iproute2 on the system
+/// behind `probe`.
+///
+/// The reserved tables `local`, `main`, `default` and `unspec` are always
+/// known, even if no `rt_tables` file exists.
+pub fn read_table_names(probe: &dyn SystemProbe) -> HashMap<u32, String> {
+    let mut names: HashMap<u32, String> = [
+        (255, "local"),
+        (254, "main"),
+        (253, "default"),
+        (0, "unspec"),
+    ]
+    .into_iter()
+    .map(|(id, name)| (id, name.to_string()))
+    .collect();
+
+    let mut files: Vec<PathBuf> = RT_TABLES_FILES.iter().map(Into::into).collect();
+    for dir in RT_TABLES_DIRS {
+        if let Ok(entries) = probe.read_dir(Path::new(dir)) {
+            let mut conf: Vec<PathBuf> = entries
+                .iter()
+                .filter(|entry| entry.ends_with(".conf"))
+                .map(|entry| Path::new(dir).join(entry))
+                .collect();
+            conf.sort();
+            files.extend(conf);
+        }
+    }
+
+    for file in files {
+        if let Ok(content) = probe.read_file(&file) {
+            names.extend(parse_rt_tables(&content));
+        }
+    }
+
+    names
+}
+
+/// Returns the name of the table with the given ID, or the ID itself if the
+/// table has no configured name.
+pub fn table_name(id: u32, names: &HashMap<u32, String>) -> String {
+    names.get(&id).cloned().unwrap_or_else(|| id.to_string())
+}
+
+/// Returns the name of a table given by name or ID, e.g. "main" for "254".
+pub fn resolve_table(table: &str, names: &HashMap<u32, String>) -> String {
+    match table.parse::<u32>() {
+        Ok(id) => table_name(id, names),
+        Err(_) => table.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::FakeProbe;
+
+    fn rule(priority: u32, action: RuleAction) -> RouteRule {
+        RouteRule {
+            priority,
+            ip_version: IpVersion::IPv4,
+            invert: false,
+            from: None,
+            to: None,
+            fwmark: None,
+            iif: None,
+            oif: None,
+            action,
+            suppress_prefixlen: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_rt_tables() {
+        let content = "#\n# reserved values\n#\n255\tlocal\n254\tmain\n0x64 vpn # tunnel\n\
+                       1000 vrf-blue\nbroken line\n";
+        assert_eq!(
+            parse_rt_tables(content),
+            vec![
+                (255, "local".to_string()),
+                (254, "main".to_string()),
+                (100, "vpn".to_string()),
+                (1000, "vrf-blue".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_table_names() {
+        let probe = FakeProbe::new("linux")
+            .file(
+                "/etc/iproute2/rt_tables",
+                "255\tlocal\n254\tmain\n100\tvpn\n",
+            )
+            .file("/etc/iproute2/rt_tables.d/wg.conf", "51820 wg\n")
+            .file("/etc/iproute2/rt_tables.d/notes.txt", "7 ignored\n");
+        let names = read_table_names(&probe);
+        assert_eq!(names[&100], "vpn");
+        assert_eq!(names[&51820], "wg");
+        assert_eq!(names[&253], "default");
+        assert!(!names.contains_key(&7));
+
+        assert_eq!(read_table_names(&FakeProbe::new("linux")).len(), 4);
+    }
+
+    #[test]
+    fn test_rule_matches_selectors() {
+        let mut from_rule = rule(100, RuleAction::Table("vpn".to_string()));
+        from_rule.from = Some("10.8.0.0/24".parse().unwrap());
+
+        let mut query = RouteQuery::new("1.1.1.1".parse().unwrap());
+        assert!(!from_rule.matches(&query));
+        query.src = Some("10.8.0.2".parse().unwrap());
+        assert!(from_rule.matches(&query));
+
+        let mut mark_rule = rule(200, RuleAction::Table("51820".to_string()));
+        mark_rule.fwmark = Some((0xca6c, u32::MAX));
+        mark_rule.invert = true;
+        let mut query = RouteQuery::new("1.1.1.1".parse().unwrap());
+        assert!(mark_rule.matches(&query));
+        query.fwmark = Some(0xca6c);
+        assert!(!mark_rule.matches(&query));
+
+        let mut iif_rule = rule(300, RuleAction::Prohibit);
+        iif_rule.iif = Some("lo".to_string());
+        assert!(iif_rule.matches(&RouteQuery::new("1.1.1.1".parse().unwrap())));
+    }
+
+    #[test]
+    fn test_rule_display() {
+        let mut rule = rule(32765, RuleAction::Table("51820".to_string()));
+        rule.invert = true;
+        rule.fwmark = Some((0xca6c, u32::MAX));
+        assert_eq!(
+            rule.to_string(),
+            "32765:\tnot from all fwmark 0xca6c lookup 51820"
+        );
+
+        let mut suppress = self::rule(32764, RuleAction::Table("main".to_string()));
+        suppress.suppress_prefixlen = Some(0);
+        assert_eq!(
+            suppress.to_string(),
+            "32764:\tfrom all lookup main suppress_prefixlength 0"
+        );
+    }
+}
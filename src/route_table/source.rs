@@ -0,0 +1,477 @@
+use anyhow::{Result, anyhow};
+use std::net::Ipv6Addr;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+use crate::probe::SystemProbe;
+use crate::route_table::bsd::parse_bsd_route_output;
+use crate::route_table::iproute2::{
+    get_iproute2_routes, parse_ip_json_output, parse_ip_text_output,
+};
+use crate::route_table::linux::parse_linux_route_output;
+use crate::route_table::mac::parse_macos_route_output;
+use crate::route_table::netstat::{NetstatFlavor, get_netstat_routes};
+use crate::route_table::procfs::get_procfs_routes;
+use crate::route_table::windows::{get_windows_routes, parse_windows_route_output};
+use crate::route_table::{IpVersion, ParseOptions, ParsedRoutes, RouteTable};
+use crate::snapshot::Snapshot;
+
+/// A way of reading the system's route table.
+pub trait RouteSource: Send + Sync {
+    /// Returns the name of the source, as accepted by `--route-source` if it
+    /// can be chosen there.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the source can be tried on this system, e.g. because
+    /// its command is installed. Auto-detection skips sources that are not
+    /// available; a source chosen explicitly is always tried.
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool;
+
+    /// Reads the route table, from the system behind `probe` unless the
+    /// source has its own input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the route table cannot be read, or a line of
+    /// command output cannot be parsed with `options.strict`.
+    fn read(&self, probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable>;
+}
+
+/// The IPv4 main table and all IPv6 routes from `/proc/net/route` and
+/// `/proc/net/ipv6_route` on Linux.
+pub struct Procfs;
+
+impl RouteSource for Procfs {
+    fn name(&self) -> &'static str {
+        "procfs"
+    }
+
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool {
+        probe.read_file(Path::new("/proc/net/route")).is_ok()
+    }
+
+    fn read(&self, probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+        get_procfs_routes(probe, options)
+    }
+}
+
+/// All routing tables and policy rules over netlink on Linux.
+pub struct Netlink;
+
+impl RouteSource for Netlink {
+    fn name(&self) -> &'static str {
+        "netlink"
+    }
+
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool {
+        probe.has_netlink()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn read(&self, probe: &dyn SystemProbe, _options: &ParseOptions) -> Result<RouteTable> {
+        crate::route_table::netlink::get_netlink_routes(probe)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn read(&self, _probe: &dyn SystemProbe, _options: &ParseOptions) -> Result<RouteTable> {
+        Err(anyhow!("netlink is only available on Linux"))
+    }
+}
+
+/// The output of `netstat -rn` in the format of one operating system.
+pub struct Netstat(pub NetstatFlavor);
+
+impl RouteSource for Netstat {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            NetstatFlavor::Linux => "netstat-linux",
+            NetstatFlavor::MacOs => "netstat-macos",
+            NetstatFlavor::Bsd => "netstat-bsd",
+        }
+    }
+
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool {
+        probe.has_command("netstat")
+    }
+
+    fn read(&self, probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+        get_netstat_routes(probe, self.0, options)
+    }
+}
+
+/// All routing tables from `ip route show table all` on Linux.
+pub struct Iproute2;
+
+impl RouteSource for Iproute2 {
+    fn name(&self) -> &'static str {
+        "iproute2"
+    }
+
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool {
+        probe.has_command("ip")
+    }
+
+    fn read(&self, probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+        get_iproute2_routes(probe, options)
+    }
+}
+
+/// The output of `route print` on Windows.
+pub struct WindowsRoutePrint;
+
+impl RouteSource for WindowsRoutePrint {
+    fn name(&self) -> &'static str {
+        "windows-route-print"
+    }
+
+    fn is_available(&self, probe: &dyn SystemProbe) -> bool {
+        probe.os() == "windows"
+    }
+
+    fn read(&self, probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+        get_windows_routes(probe, options)
+    }
+}
+
+/// The route table of a snapshot saved with `ls_net snapshot save`, to
+/// replay the routes of another machine or an earlier time.
+pub struct FileReplay(pub PathBuf);
+
+impl RouteSource for FileReplay {
+    fn name(&self) -> &'static str {
+        "file-replay"
+    }
+
+    fn is_available(&self, _probe: &dyn SystemProbe) -> bool {
+        self.0.is_file()
+    }
+
+    fn read(&self, _probe: &dyn SystemProbe, _options: &ParseOptions) -> Result<RouteTable> {
+        Ok(Snapshot::load(&self.0)?.routes)
+    }
+}
+
+/// Parses route command output captured elsewhere, such as a dump pasted
+/// into a ticket, in `format`: `linux-netstat` (`netstat -rn` or
+/// `route -n`), `macos-netstat`, `bsd-netstat`, `iproute2` (`ip route`,
+/// JSON or text, either address family) or `windows` (`route print`).
+///
+/// # Errors
+///
+/// Returns an error if the format is unknown or the output cannot be parsed,
+/// or with `strict`, the first line that is skipped as a `ParseError`.
+pub fn parse_captured_output(output: &str, format: &str, strict: bool) -> Result<ParsedRoutes> {
+    match format {
+        "linux-netstat" => parse_linux_route_output(output, strict),
+        "macos-netstat" => parse_macos_route_output(output, strict),
+        "bsd-netstat" => parse_bsd_route_output(output, netstat_family(output), strict),
+        "iproute2" if output.trim_start().starts_with('[') => Ok(ParsedRoutes {
+            route_table: parse_ip_json_output(output, IpVersion::IPv4)?,
+            skipped: Vec::new(),
+        }),
+        "iproute2" => parse_ip_text_output(output, IpVersion::IPv4, strict),
+        "windows" => parse_windows_route_output(output, strict),
+        _ => Err(anyhow!("Unknown input format '{}'", format)),
+    }
+}
+
+/// Returns the address family of the routes before the first section title
+/// in `netstat -rn` output: IPv6 if one of their destinations is an IPv6
+/// address, as in `netstat -rn -f inet6` captured without its headers, else
+/// IPv4.
+fn netstat_family(output: &str) -> IpVersion {
+    let ipv6 = output
+        .lines()
+        .take_while(|line| !line.trim_start().starts_with("Internet"))
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|destination| {
+            // `fe80::%lo0/64`
+            let address = destination.split(['%', '/']).next().unwrap_or_default();
+            address.parse::<Ipv6Addr>().is_ok()
+        });
+    if ipv6 {
+        IpVersion::IPv6
+    } else {
+        IpVersion::IPv4
+    }
+}
+
+/// Route command output captured elsewhere, in a format of
+/// `parse_captured_output`.
+pub struct CapturedOutput {
+    pub format: String,
+    pub output: String,
+}
+
+impl RouteSource for CapturedOutput {
+    fn name(&self) -> &'static str {
+        "from-file"
+    }
+
+    fn is_available(&self, _probe: &dyn SystemProbe) -> bool {
+        true
+    }
+
+    fn read(&self, _probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+        Ok(
+            parse_captured_output(&self.output, &self.format, options.strict)?
+                .into_route_table(options.verbose),
+        )
+    }
+}
+
+/// Returns the source named `name`. `replay_file` is the snapshot read by
+/// `file-replay`.
+///
+/// # Errors
+///
+/// Returns an error if the name is unknown, or `file-replay` is chosen
+/// without a file.
+pub fn source_by_name(name: &str, replay_file: Option<&Path>) -> Result<Box<dyn RouteSource>> {
+    let source: Box<dyn RouteSource> = match name {
+        "procfs" => Box::new(Procfs),
+        "netlink" => Box::new(Netlink),
+        "netstat-linux" => Box::new(Netstat(NetstatFlavor::Linux)),
+        "netstat-macos" => Box::new(Netstat(NetstatFlavor::MacOs)),
+        "netstat-bsd" => Box::new(Netstat(NetstatFlavor::Bsd)),
+        "iproute2" => Box::new(Iproute2),
+        "windows-route-print" => Box::new(WindowsRoutePrint),
+        "file-replay" => {
+            let path = replay_file
+                .ok_or_else(|| anyhow!("The file-replay route source needs --replay-file"))?;
+            Box::new(FileReplay(path.to_path_buf()))
+        }
+        _ => return Err(anyhow!("Unknown route source '{}'", name)),
+    };
+    Ok(source)
+}
+
+/// Returns the sources to try on the operating system `os` (named as in
+/// `std::env::consts::OS`), best first.
+///
+/// On Linux, netlink is the only source of policy rules and all tables;
+/// iproute2 has all tables, procfs all IPv6 routes and netstat only the IPv4
+/// main table.
+pub fn auto_sources(os: &str) -> Vec<Box<dyn RouteSource>> {
+    match os {
+        "linux" => vec![
+            Box::new(Netlink),
+            Box::new(Iproute2),
+            Box::new(Procfs),
+            Box::new(Netstat(NetstatFlavor::Linux)),
+        ],
+        "windows" => vec![Box::new(WindowsRoutePrint)],
+        _ => NetstatFlavor::from_os(os)
+            .map(|flavor| vec![Box::new(Netstat(flavor)) as Box<dyn RouteSource>])
+            .unwrap_or_default(),
+    }
+}
+
+/// Reads the route table from the first of `sources` that is available on
+/// the system behind `probe` and succeeds.
+///
+/// # Errors
+///
+/// Returns an error listing why each source failed if none succeeds, of kind
+/// `PermissionDenied` if a source was not permitted to read the routes and
+/// `RouteSourceUnavailable` otherwise.
+pub fn read_first(
+    sources: &[Box<dyn RouteSource>],
+    probe: &dyn SystemProbe,
+    options: &ParseOptions,
+) -> Result<RouteTable> {
+    if sources.is_empty() {
+        return Err(Error::new(
+            ErrorKind::RouteSourceUnavailable,
+            format!("Unsupported operating system: {}", probe.os()),
+        )
+        .into());
+    }
+    let mut errors = Vec::new();
+    let mut kind = ErrorKind::RouteSourceUnavailable;
+    for source in sources {
+        if !source.is_available(probe) {
+            errors.push(format!("{}: not available", source.name()));
+            continue;
+        }
+        match source.read(probe, options) {
+            Ok(route_table) => return Ok(route_table),
+            Err(error) => {
+                if ErrorKind::of(&error) == ErrorKind::PermissionDenied {
+                    kind = ErrorKind::PermissionDenied;
+                }
+                errors.push(format!("{}: {:#}", source.name(), error));
+            }
+        }
+    }
+    Err(Error::new(
+        kind,
+        format!(
+            "No route source could read the route table ({})",
+            errors.join("; ")
+        ),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::FakeProbe;
+    use crate::route_table::{IpVersion, RouteEntry};
+
+    /// A source with fixed routes, or none if it fails.
+    struct Fake {
+        available: bool,
+        routes: Option<usize>,
+    }
+
+    impl RouteSource for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn is_available(&self, _probe: &dyn SystemProbe) -> bool {
+            self.available
+        }
+
+        fn read(&self, _probe: &dyn SystemProbe, _options: &ParseOptions) -> Result<RouteTable> {
+            let count = self.routes.ok_or_else(|| anyhow!("broken"))?;
+            let mut route_table = RouteTable::new();
+            for _ in 0..count {
+                route_table.add_route(RouteEntry {
+                    destination: "default".to_string(),
+                    ip_version: IpVersion::IPv4,
+                    ..Default::default()
+                });
+            }
+            Ok(route_table)
+        }
+    }
+
+    #[test]
+    fn test_source_names() {
+        for name in [
+            "procfs",
+            "netlink",
+            "netstat-linux",
+            "netstat-macos",
+            "netstat-bsd",
+            "iproute2",
+            "windows-route-print",
+            "file-replay",
+        ] {
+            let source = source_by_name(name, Some(Path::new("snapshot.json"))).unwrap();
+            assert_eq!(source.name(), name);
+        }
+        assert!(source_by_name("file-replay", None).is_err());
+        assert!(source_by_name("carrier-pigeon", None).is_err());
+
+        let names: Vec<&str> = auto_sources("linux").iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["netlink", "iproute2", "procfs", "netstat-linux"]);
+        assert_eq!(auto_sources("openbsd")[0].name(), "netstat-bsd");
+        assert!(auto_sources("plan9").is_empty());
+    }
+
+    #[test]
+    fn test_parse_captured_output() {
+        // A dump of both address families, as from `ip route; ip -6 route`
+        let output = "\
+default via 192.168.1.1 dev eth0 proto dhcp metric 100
+192.168.1.0/24 dev eth0 proto kernel scope link src 192.168.1.10
+default via fe80::1 dev eth0 proto ra metric 1024 pref medium
+2001:db8::/64 dev eth0 proto kernel metric 256 pref medium
+";
+        let table = parse_captured_output(output, "iproute2", true)
+            .unwrap()
+            .route_table;
+        assert_eq!((table.ipv4_routes.len(), table.ipv6_routes.len()), (2, 2));
+        assert_eq!(table.ipv6_routes[0].gateway, "fe80::1");
+
+        let output = r#"[{"dst":"default","gateway":"fe80::1","dev":"eth0","flags":[]}]"#;
+        let table = parse_captured_output(output, "iproute2", true)
+            .unwrap()
+            .route_table;
+        assert_eq!(table.ipv6_routes.len(), 1);
+
+        let output = include_str!("../../tests/fixtures/windows/route-print.txt");
+        let table = parse_captured_output(output, "windows", true)
+            .unwrap()
+            .route_table;
+        assert_eq!(table.ipv4_routes.len(), 11);
+
+        assert!(parse_captured_output("", "solaris-netstat", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_captured_ipv6_only_output() {
+        // Routes without an address, such as `default dev wg0`, take the
+        // family of the rest of the dump
+        for output in [
+            include_str!("../../tests/fixtures/iproute2/route-ipv6-only.txt"),
+            include_str!("../../tests/fixtures/iproute2/route-ipv6-only.json"),
+        ] {
+            let table = parse_captured_output(output, "iproute2", true)
+                .unwrap()
+                .route_table;
+            assert!(table.ipv4_routes.is_empty());
+            assert_eq!(table.ipv6_routes.len(), 4);
+            assert_eq!(table.ipv6_routes[0].gateway, "::");
+            assert_eq!(
+                table.get_default_gateway(IpVersion::IPv6).unwrap().iface,
+                "wg0"
+            );
+        }
+
+        // `netstat -rn -f inet6` without the section title
+        let output = include_str!("../../tests/fixtures/netstat/freebsd-inet6.txt")
+            .replace("Internet6:", "");
+        let table = parse_captured_output(&output, "bsd-netstat", true)
+            .unwrap()
+            .route_table;
+        assert!(table.ipv4_routes.is_empty());
+        assert!(!table.ipv6_routes.is_empty());
+        let output =
+            include_str!("../../tests/fixtures/netstat/freebsd-inet.txt").replace("Internet:", "");
+        let table = parse_captured_output(&output, "bsd-netstat", true)
+            .unwrap()
+            .route_table;
+        assert!(table.ipv6_routes.is_empty());
+    }
+
+    #[test]
+    fn test_fallback() {
+        let sources: Vec<Box<dyn RouteSource>> = vec![
+            Box::new(Fake {
+                available: false,
+                routes: Some(1),
+            }),
+            Box::new(Fake {
+                available: true,
+                routes: None,
+            }),
+            Box::new(Fake {
+                available: true,
+                routes: Some(2),
+            }),
+        ];
+        let options = ParseOptions::default();
+        let probe = FakeProbe::new("linux");
+        assert_eq!(
+            read_first(&sources, &probe, &options)
+                .unwrap()
+                .ipv4_routes
+                .len(),
+            2
+        );
+
+        let error = read_first(&sources[..2], &probe, &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No route source could read the route table (fake: not available; fake: broken)"
+        );
+        assert_eq!(ErrorKind::of(&error), ErrorKind::RouteSourceUnavailable);
+        let error = read_first(&[], &probe, &options).unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::RouteSourceUnavailable);
+    }
+}
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+use crate::prefix::{self, Prefix};
+use crate::render::{Element, Output};
+use crate::route_table::{IpVersion, RouteEntry, RouteTable};
+use crate::table::Table;
+
+/// The routes through one next hop, merged into the fewest prefixes covering
+/// the same destinations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NextHopSummary {
+    /// The gateway; `None` for routes directly on the link.
+    pub gateway: Option<IpAddr>,
+    pub iface: String,
+    pub routes: usize,
+    pub prefixes: Vec<Prefix>,
+}
+
+/// The number of routes with one prefix length.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrefixLenCount {
+    pub ip_version: IpVersion,
+    pub prefix_len: u8,
+    pub routes: usize,
+}
+
+/// The shape of a route table: how many routes go where and how far they
+/// can be aggregated.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouteSummary {
+    pub routes: usize,
+    /// Next hops with the most routes first.
+    pub next_hops: Vec<NextHopSummary>,
+    pub interfaces: BTreeMap<String, usize>,
+    /// IPv4 before IPv6, shortest prefixes first.
+    pub prefix_lengths: Vec<PrefixLenCount>,
+}
+
+/// Returns the gateway of `route` if it is an IP address. Direct routes and
+/// macOS routes with a link-layer gateway have none.
+fn gateway(route: &RouteEntry) -> Option<IpAddr> {
+    route
+        .gateway
+        .split('%')
+        .next()
+        .and_then(|gateway| gateway.parse::<IpAddr>().ok())
+        .filter(|ip| !ip.is_unspecified())
+}
+
+/// Summarizes the routes of `route_table`. Routes whose destination cannot
+/// be parsed are counted but not aggregated.
+pub fn summarize(route_table: &RouteTable) -> RouteSummary {
+    let routes: Vec<&RouteEntry> = route_table
+        .ipv4_routes
+        .iter()
+        .chain(&route_table.ipv6_routes)
+        .collect();
+
+    let mut next_hops: BTreeMap<(Option<IpAddr>, &str), NextHopSummary> = BTreeMap::new();
+    let mut interfaces: BTreeMap<String, usize> = BTreeMap::new();
+    let mut prefix_lengths: BTreeMap<(u8, u8), usize> = BTreeMap::new();
+    for route in &routes {
+        let next_hop = next_hops
+            .entry((gateway(route), route.iface.as_str()))
+            .or_insert_with(|| NextHopSummary {
+                gateway: gateway(route),
+                iface: route.iface.clone(),
+                routes: 0,
+                prefixes: Vec::new(),
+            });
+        next_hop.routes += 1;
+        *interfaces.entry(route.iface.clone()).or_default() += 1;
+        if let Some(prefix) = route.prefix() {
+            next_hop.prefixes.push(prefix);
+            *prefix_lengths
+                .entry((prefix.max_len(), prefix.prefix_len()))
+                .or_default() += 1;
+        }
+    }
+
+    let mut next_hops: Vec<NextHopSummary> = next_hops
+        .into_values()
+        .map(|mut next_hop| {
+            next_hop.prefixes = prefix::aggregate(&next_hop.prefixes);
+            next_hop
+        })
+        .collect();
+    next_hops.sort_by_key(|next_hop| std::cmp::Reverse(next_hop.routes));
+
+    RouteSummary {
+        routes: routes.len(),
+        next_hops,
+        interfaces,
+        prefix_lengths: prefix_lengths
+            .into_iter()
+            .map(|((max_len, prefix_len), routes)| PrefixLenCount {
+                ip_version: if max_len == 32 {
+                    IpVersion::IPv4
+                } else {
+                    IpVersion::IPv6
+                },
+                prefix_len,
+                routes,
+            })
+            .collect(),
+    }
+}
+
+/// Writes a summary returned by `summarize` to `output`.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written.
+pub fn print_summary(output: &mut Output, summary: &RouteSummary) -> io::Result<()> {
+    let title = output.paint(Element::Title, "Route Summary:");
+    writeln!(output, "\n{} {} routes", title, summary.routes)?;
+
+    let gateway_label = |next_hop: &NextHopSummary| match next_hop.gateway {
+        Some(gateway) => gateway.to_string(),
+        None => "direct".to_string(),
+    };
+    let separator = |output: &mut Output, title: &str| {
+        let line = output.paint(Element::Separator, &format!("{:=^45}", title));
+        writeln!(output, "{}", line)
+    };
+
+    separator(output, " Next Hops ")?;
+    let mut table = Table::new(["Gateway", "Iface", "Routes", "Aggregated"]);
+    for next_hop in &summary.next_hops {
+        table.push_row(vec![
+            gateway_label(next_hop),
+            next_hop.iface.clone(),
+            next_hop.routes.to_string(),
+            next_hop.prefixes.len().to_string(),
+        ]);
+    }
+    output.table(&table)?;
+
+    separator(output, " Aggregated Prefixes ")?;
+    for next_hop in &summary.next_hops {
+        let gateway = output.paint(Element::Gateway, &gateway_label(next_hop));
+        let iface = output.paint(Element::Name, &next_hop.iface);
+        writeln!(output, "{} {}", gateway, iface)?;
+        for prefix in &next_hop.prefixes {
+            writeln!(output, "  {}", prefix)?;
+        }
+    }
+
+    separator(output, " Interfaces ")?;
+    let mut table = Table::new(["Iface", "Routes"]);
+    for (iface, routes) in &summary.interfaces {
+        table.push_row(vec![iface.clone(), routes.to_string()]);
+    }
+    output.table(&table)?;
+
+    separator(output, " Prefix Lengths ")?;
+    let mut table = Table::new(["Family", "Length", "Routes"]);
+    for count in &summary.prefix_lengths {
+        let family = match count.ip_version {
+            IpVersion::IPv4 => "IPv4",
+            IpVersion::IPv6 => "IPv6",
+        };
+        table.push_row(vec![
+            family.to_string(),
+            format!("/{}", count.prefix_len),
+            count.routes.to_string(),
+        ]);
+    }
+    output.table(&table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, gateway: &str, iface: &str) -> RouteEntry {
+        let ip_version = if destination.contains(':') {
+            IpVersion::IPv6
+        } else {
+            IpVersion::IPv4
+        };
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            iface: iface.to_string(),
+            ip_version,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut route_table = RouteTable::new();
+        route_table.add_route(route("default", "192.168.1.1", "eth0"));
+        route_table.add_route(route("192.168.1.0/24", "0.0.0.0", "eth0"));
+        // Container host routes that aggregate into a /30
+        for host in 0..4 {
+            route_table.add_route(route(
+                &format!("172.17.0.{}/32", host),
+                "0.0.0.0",
+                "docker0",
+            ));
+        }
+        route_table.add_route(route("172.17.0.9/32", "0.0.0.0", "docker0"));
+        route_table.add_route(route("fd00::/64", "::", "eth0"));
+
+        let summary = summarize(&route_table);
+        assert_eq!(summary.routes, 8);
+
+        let docker = &summary.next_hops[0];
+        assert_eq!((docker.gateway, docker.routes), (None, 5));
+        assert_eq!(
+            docker.prefixes,
+            [
+                "172.17.0.0/30".parse::<Prefix>().unwrap(),
+                "172.17.0.9/32".parse().unwrap()
+            ]
+        );
+        // The direct IPv4 and IPv6 routes of eth0 share a next hop
+        let direct = summary
+            .next_hops
+            .iter()
+            .find(|next_hop| next_hop.gateway.is_none() && next_hop.iface == "eth0")
+            .unwrap();
+        assert_eq!(direct.routes, 2);
+        assert_eq!(direct.prefixes.len(), 2);
+
+        assert_eq!(summary.interfaces["docker0"], 5);
+        assert_eq!(summary.interfaces["eth0"], 3);
+        let lengths: Vec<(u8, usize)> = summary
+            .prefix_lengths
+            .iter()
+            .map(|count| (count.prefix_len, count.routes))
+            .collect();
+        assert_eq!(lengths, [(0, 1), (24, 1), (32, 5), (64, 1)]);
+        assert_eq!(summary.prefix_lengths[3].ip_version, IpVersion::IPv6);
+    }
+}
@@ -0,0 +1,294 @@
+use std::cmp::Ordering;
+
+use crate::route_table::RouteEntry;
+use crate::table::Table;
+
+/// A column of the route table output, chosen with `--columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteColumn {
+    Dest,
+    Gw,
+    Flags,
+    Iface,
+    Expire,
+    Genmask,
+    Metric,
+    Proto,
+    Scope,
+    Src,
+    Table,
+    Type,
+    Mtu,
+    Refs,
+    Use,
+    Mss,
+    Window,
+    Irtt,
+}
+
+impl RouteColumn {
+    pub const ALL: [RouteColumn; 18] = [
+        RouteColumn::Dest,
+        RouteColumn::Gw,
+        RouteColumn::Flags,
+        RouteColumn::Iface,
+        RouteColumn::Expire,
+        RouteColumn::Genmask,
+        RouteColumn::Metric,
+        RouteColumn::Proto,
+        RouteColumn::Scope,
+        RouteColumn::Src,
+        RouteColumn::Table,
+        RouteColumn::Type,
+        RouteColumn::Mtu,
+        RouteColumn::Refs,
+        RouteColumn::Use,
+        RouteColumn::Mss,
+        RouteColumn::Window,
+        RouteColumn::Irtt,
+    ];
+
+    /// The columns shown without `--columns`, in `netstat` order.
+    pub const DEFAULT: [RouteColumn; 5] = [
+        RouteColumn::Dest,
+        RouteColumn::Gw,
+        RouteColumn::Flags,
+        RouteColumn::Iface,
+        RouteColumn::Expire,
+    ];
+
+    /// Returns the name of the column on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteColumn::Dest => "dest",
+            RouteColumn::Gw => "gw",
+            RouteColumn::Flags => "flags",
+            RouteColumn::Iface => "iface",
+            RouteColumn::Expire => "expire",
+            RouteColumn::Genmask => "genmask",
+            RouteColumn::Metric => "metric",
+            RouteColumn::Proto => "proto",
+            RouteColumn::Scope => "scope",
+            RouteColumn::Src => "src",
+            RouteColumn::Table => "table",
+            RouteColumn::Type => "type",
+            RouteColumn::Mtu => "mtu",
+            RouteColumn::Refs => "refs",
+            RouteColumn::Use => "use",
+            RouteColumn::Mss => "mss",
+            RouteColumn::Window => "window",
+            RouteColumn::Irtt => "irtt",
+        }
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            RouteColumn::Dest => "Destination",
+            RouteColumn::Gw => "Gateway",
+            RouteColumn::Flags => "Flags",
+            RouteColumn::Iface => "Iface",
+            RouteColumn::Expire => "Expire",
+            RouteColumn::Genmask => "Genmask",
+            RouteColumn::Metric => "Metric",
+            RouteColumn::Proto => "Proto",
+            RouteColumn::Scope => "Scope",
+            RouteColumn::Src => "Src",
+            RouteColumn::Table => "Table",
+            RouteColumn::Type => "Type",
+            RouteColumn::Mtu => "MTU",
+            RouteColumn::Refs => "Refs",
+            RouteColumn::Use => "Use",
+            RouteColumn::Mss => "MSS",
+            RouteColumn::Window => "Window",
+            RouteColumn::Irtt => "irtt",
+        }
+    }
+
+    /// Returns the value of this column for `route`, empty if unknown.
+    pub fn value(&self, route: &RouteEntry) -> String {
+        let field = match self {
+            RouteColumn::Dest => "destination",
+            RouteColumn::Gw => "gateway",
+            RouteColumn::Table => return route.table_name().to_string(),
+            column => column.name(),
+        };
+        route.get_field(field).unwrap_or_default()
+    }
+}
+
+/// Parses a column name for `--columns`.
+pub fn parse_column(value: &str) -> Result<RouteColumn, String> {
+    RouteColumn::ALL
+        .into_iter()
+        .find(|column| column.name() == value)
+        .ok_or_else(|| {
+            let names: Vec<&str> = RouteColumn::ALL.iter().map(RouteColumn::name).collect();
+            format!(
+                "unknown column '{}', expected one of {}",
+                value,
+                names.join(", ")
+            )
+        })
+}
+
+/// Compares two routes by a `--sort` key: "metric", "dest", "iface" or
+/// "prefixlen".
+///
+/// A missing metric counts as 0, as it does for the kernel. Destinations
+/// are compared as networks, with IPv4 before IPv6 and unparsable
+/// destinations last.
+fn compare(a: &RouteEntry, b: &RouteEntry, key: &str) -> Ordering {
+    match key {
+        "metric" => a.metric.unwrap_or(0).cmp(&b.metric.unwrap_or(0)),
+        "dest" => {
+            let network = |route: &RouteEntry| (route.prefix().is_none(), route.prefix());
+            network(a)
+                .cmp(&network(b))
+                .then_with(|| a.destination.cmp(&b.destination))
+        }
+        "iface" => a.iface.cmp(&b.iface),
+        "prefixlen" => {
+            let len = |route: &RouteEntry| route.prefix().map(|prefix| prefix.prefix_len());
+            len(a).cmp(&len(b))
+        }
+        _ => Ordering::Equal,
+    }
+}
+
+/// How routes are printed as a table: the columns, their order and the
+/// order of the rows.
+#[derive(Debug, Clone, Default)]
+pub struct RouteView {
+    /// The columns to show; `RouteColumn::DEFAULT` if empty.
+    pub columns: Vec<RouteColumn>,
+    /// The `--sort` key, or `None` to keep the order of the source.
+    pub sort: Option<String>,
+    pub reverse: bool,
+}
+
+impl RouteView {
+    pub fn columns(&self) -> &[RouteColumn] {
+        if self.columns.is_empty() {
+            &RouteColumn::DEFAULT
+        } else {
+            &self.columns
+        }
+    }
+
+    /// Sorts `routes` by the sort key of this view. Routes that compare equal
+    /// keep their order, also when reversed.
+    pub fn sort(&self, routes: &mut [RouteEntry]) {
+        match &self.sort {
+            Some(key) if self.reverse => routes.sort_by(|a, b| compare(b, a, key)),
+            Some(key) => routes.sort_by(|a, b| compare(a, b, key)),
+            None if self.reverse => routes.reverse(),
+            None => {}
+        }
+    }
+
+    /// Returns the sorted `routes` as a table with the columns of this view.
+    pub fn table(&self, routes: &[RouteEntry]) -> Table {
+        let mut routes = routes.to_vec();
+        self.sort(&mut routes);
+
+        let columns = self.columns();
+        let mut table = Table::new(columns.iter().map(RouteColumn::header));
+        for route in &routes {
+            table.push_row(columns.iter().map(|column| column.value(route)).collect());
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, iface: &str, metric: Option<u32>) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: "0.0.0.0".to_string(),
+            iface: iface.to_string(),
+            metric,
+            ..Default::default()
+        }
+    }
+
+    fn destinations(routes: &[RouteEntry]) -> Vec<&str> {
+        routes
+            .iter()
+            .map(|route| route.destination.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_column() {
+        assert_eq!(parse_column("gw"), Ok(RouteColumn::Gw));
+        assert_eq!(parse_column("mtu"), Ok(RouteColumn::Mtu));
+        assert!(parse_column("gateway").unwrap_err().contains("dest, gw"));
+    }
+
+    #[test]
+    fn test_sort_routes() {
+        let routes = vec![
+            route("10.0.0.0/8", "eth1", Some(100)),
+            route("default", "eth0", Some(600)),
+            route("192.168.1.0/24", "eth0", None),
+            route("10.1.0.0/16", "wg0", Some(100)),
+        ];
+        let sorted = |sort: &str, reverse: bool| {
+            let view = RouteView {
+                sort: Some(sort.to_string()),
+                reverse,
+                ..Default::default()
+            };
+            let mut routes = routes.clone();
+            view.sort(&mut routes);
+            routes
+        };
+
+        assert_eq!(
+            destinations(&sorted("dest", false)),
+            ["default", "10.0.0.0/8", "10.1.0.0/16", "192.168.1.0/24"]
+        );
+        assert_eq!(
+            destinations(&sorted("metric", false)),
+            ["192.168.1.0/24", "10.0.0.0/8", "10.1.0.0/16", "default"]
+        );
+        // Ties keep their order when reversed
+        assert_eq!(
+            destinations(&sorted("metric", true)),
+            ["default", "10.0.0.0/8", "10.1.0.0/16", "192.168.1.0/24"]
+        );
+        assert_eq!(
+            destinations(&sorted("prefixlen", true)),
+            ["192.168.1.0/24", "10.1.0.0/16", "10.0.0.0/8", "default"]
+        );
+        assert_eq!(
+            destinations(&sorted("iface", false)),
+            ["default", "192.168.1.0/24", "10.0.0.0/8", "10.1.0.0/16"]
+        );
+    }
+
+    #[test]
+    fn test_table_columns() {
+        let view = RouteView {
+            columns: vec![RouteColumn::Iface, RouteColumn::Metric, RouteColumn::Table],
+            ..Default::default()
+        };
+        let table = view.table(&[route("default", "eth0", Some(100))]);
+        let mut expected = Table::new(["Iface", "Metric", "Table"]);
+        expected.push_row(vec![
+            "eth0".to_string(),
+            "100".to_string(),
+            "main".to_string(),
+        ]);
+        assert_eq!(table, expected);
+
+        let default = RouteView::default().table(&[]);
+        assert_eq!(
+            default,
+            Table::new(["Destination", "Gateway", "Flags", "Iface", "Expire"])
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+use std::net::IpAddr;
+
+use crate::prefix::Prefix;
+use crate::probe::SystemProbe;
+use crate::route_table::{
+    IpVersion, ParseError, ParseOptions, ParsedRoutes, RouteEntry, RouteTable,
+};
+
+/// Returns the route entry for `prefix` through `gateway`, which is
+/// `On-link` for routes directly on the link.
+fn route_entry(
+    prefix: Prefix,
+    gateway: &str,
+    iface: &str,
+    metric: &str,
+) -> Result<RouteEntry, String> {
+    let (ip_version, unspecified) = match prefix.network() {
+        IpAddr::V4(_) => (IpVersion::IPv4, "0.0.0.0"),
+        IpAddr::V6(_) => (IpVersion::IPv6, "::"),
+    };
+    let on_link = gateway.eq_ignore_ascii_case("On-link");
+    if !on_link && gateway.parse::<IpAddr>().is_err() {
+        return Err(format!("invalid gateway '{}'", gateway));
+    }
+    let mut flags = String::from("U");
+    if !on_link {
+        flags.push('G');
+    }
+    if prefix.is_host() {
+        flags.push('H');
+    }
+    Ok(RouteEntry {
+        destination: if prefix.prefix_len() == 0 {
+            "default".to_string()
+        } else {
+            prefix.to_string()
+        },
+        gateway: if on_link { unspecified } else { gateway }.to_string(),
+        flags,
+        iface: iface.to_string(),
+        genmask: match ip_version {
+            IpVersion::IPv4 => Some(prefix.netmask().to_string()),
+            IpVersion::IPv6 => None,
+        },
+        ip_version,
+        metric: Some(
+            metric
+                .parse()
+                .map_err(|_| format!("invalid metric '{}'", metric))?,
+        ),
+        ..Default::default()
+    })
+}
+
+/// Parses an IPv4 route: `Network Destination, Netmask, Gateway, Interface,
+/// Metric`.
+fn parse_ipv4_route(parts: &[&str]) -> Result<RouteEntry, String> {
+    let [destination, netmask, gateway, interface, metric] = parts else {
+        return Err(format!("expected 5 columns, found {}", parts.len()));
+    };
+    let destination: IpAddr = destination
+        .parse()
+        .map_err(|_| format!("invalid destination '{}'", destination))?;
+    let netmask: IpAddr = netmask
+        .parse()
+        .map_err(|_| format!("invalid netmask '{}'", netmask))?;
+    route_entry(
+        Prefix::from_netmask(destination, netmask),
+        gateway,
+        interface,
+        metric,
+    )
+}
+
+/// Parses an IPv6 route: `If, Metric, Network Destination, Gateway`.
+fn parse_ipv6_route(parts: &[&str]) -> Result<RouteEntry, String> {
+    let [interface, metric, destination, gateway] = parts else {
+        return Err(format!("expected 4 columns, found {}", parts.len()));
+    };
+    let prefix: Prefix = destination
+        .parse()
+        .map_err(|_| format!("invalid destination '{}'", destination))?;
+    route_entry(prefix, gateway, interface, metric)
+}
+
+/// Parses the output of `route print` on Windows and returns the routes it
+/// contains.
+///
+/// Routes are read from the "Active Routes:" lists of the "IPv4 Route Table"
+/// and "IPv6 Route Table" sections. Gateways of `On-link` routes become the
+/// unspecified address. The interface of an IPv4 route is its address and of
+/// an IPv6 route its index, as `route print` shows them. An IPv6 route whose
+/// destination is too long for its column continues with the gateway on the
+/// next line.
+///
+/// The interface list, the persistent routes (which are also active) and the
+/// separators are ignored. A line in an active route list that is not a
+/// route is skipped and recorded with its line number in
+/// `ParsedRoutes::skipped`.
+///
+/// # Errors
+///
+/// With `strict`, the first line that is skipped is returned as a
+/// `ParseError`.
+pub fn parse_windows_route_output(output: &str, strict: bool) -> Result<ParsedRoutes> {
+    let mut parsed = ParsedRoutes::default();
+    let mut section = None;
+    let mut active = false;
+    // The first columns of a wrapped IPv6 route and their line number
+    let mut wrapped: Option<(usize, String)> = None;
+
+    for (index, line) in output.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('=') {
+            continue;
+        }
+        match trimmed {
+            "IPv4 Route Table" => section = Some(IpVersion::IPv4),
+            "IPv6 Route Table" => section = Some(IpVersion::IPv6),
+            "Active Routes:" => active = true,
+            "Persistent Routes:" | "Interface List" => active = false,
+            _ => {}
+        }
+        if trimmed.ends_with(':') || trimmed.ends_with("Route Table") {
+            continue;
+        }
+        let Some(ip_version) = section.as_ref().filter(|_| active) else {
+            continue;
+        };
+        if trimmed.starts_with("Network Destination") || trimmed.starts_with("If Metric") {
+            continue;
+        }
+
+        let (line_number, text) = match wrapped.take() {
+            Some((line_number, start)) => (line_number, format!("{} {}", start, trimmed)),
+            None => (index + 1, trimmed.to_string()),
+        };
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if *ip_version == IpVersion::IPv6 && parts.len() == 3 {
+            wrapped = Some((line_number, text));
+            continue;
+        }
+        let result = match ip_version {
+            IpVersion::IPv4 => parse_ipv4_route(&parts),
+            IpVersion::IPv6 => parse_ipv6_route(&parts),
+        };
+        match result {
+            Ok(route) => parsed.route_table.add_route(route),
+            Err(reason) => parsed.skip(
+                ParseError {
+                    line_number,
+                    line: text,
+                    reason,
+                },
+                strict,
+            )?,
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Executes `route print` on Windows with `probe` and parses its output into a
+/// `RouteTable`.
+///
+/// Lines that are not routes are skipped, or fail the parse with
+/// `options.strict`; with `options.verbose`, a warning is printed for each.
+///
+/// # Errors
+///
+/// If an error occurs while executing the command or parsing the output,
+/// the function returns an error.
+pub fn get_windows_routes(probe: &dyn SystemProbe, options: &ParseOptions) -> Result<RouteTable> {
+    let output = probe.run("route", &["print"])?;
+
+    if !output.success {
+        return Err(output.failure("Error executing command"));
+    }
+
+    Ok(parse_windows_route_output(&output.stdout, options.strict)?
+        .into_route_table(options.verbose))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTE_PRINT: &str = include_str!("../../tests/fixtures/windows/route-print.txt");
+
+    #[test]
+    fn test_parse_windows_route_output() {
+        let parsed = parse_windows_route_output(ROUTE_PRINT, true).unwrap();
+        let table = &parsed.route_table;
+        assert_eq!(table.ipv4_routes.len(), 11);
+        assert_eq!(table.ipv6_routes.len(), 8);
+
+        let default = &table.ipv4_routes[0];
+        assert_eq!(default.destination, "default");
+        assert_eq!(
+            (default.gateway.as_str(), default.flags.as_str()),
+            ("192.168.1.1", "UG")
+        );
+        assert_eq!(
+            (default.iface.as_str(), default.metric),
+            ("192.168.1.20", Some(25))
+        );
+        let host = &table.ipv4_routes[3];
+        assert_eq!(
+            (host.destination.as_str(), host.gateway.as_str()),
+            ("127.0.0.1/32", "0.0.0.0")
+        );
+        assert_eq!(host.flags, "UH");
+
+        let default = &table.ipv6_routes[0];
+        assert_eq!(
+            (default.gateway.as_str(), default.iface.as_str()),
+            ("fe80::1", "12")
+        );
+        // Wrapped onto two lines
+        let wrapped = &table.ipv6_routes[3];
+        assert_eq!(wrapped.destination, "2001:db8:1:0:1c2d:3e4f:5a6b:7c8d/128");
+        assert_eq!(
+            (wrapped.gateway.as_str(), wrapped.flags.as_str()),
+            ("::", "UH")
+        );
+        assert_eq!(table.ipv6_routes[4].destination, "fe80::/64");
+    }
+
+    #[test]
+    fn test_skipped_lines() {
+        let output = "\
+IPv4 Route Table
+Active Routes:
+Network Destination        Netmask          Gateway       Interface  Metric
+          0.0.0.0          0.0.0.0      192.168.1.1     192.168.1.20     25
+        10.0.0.0       255.0.0.0        somewhere     192.168.1.20     25
+";
+        let parsed = parse_windows_route_output(output, false).unwrap();
+        assert_eq!(parsed.route_table.ipv4_routes.len(), 1);
+        assert_eq!(parsed.skipped[0].line_number, 5);
+        assert_eq!(parsed.skipped[0].reason, "invalid gateway 'somewhere'");
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::ErrorReport;
+use crate::ip_interfaces::{InterfaceAddr, get_ip_interfaces};
+use crate::machine_main_ip::get_local_ip;
+use crate::route_table::RouteTable;
+use crate::route_table::route_table::RouteCollector;
+
+/// The network state of the host at one point in time: the main IP address,
+/// all interface addresses and the full route table including policy rules.
+///
+/// Snapshots are stored as JSON, and the same schema is printed by
+/// `--format json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    pub main_ip: Option<String>,
+    pub interfaces: Vec<InterfaceAddr>,
+    pub routes: RouteTable,
+    /// The parts of the state that could not be collected, by
+    /// `collect_partial`.
+    pub errors: Vec<ErrorReport>,
+}
+
+impl Snapshot {
+    /// The number of parts collected independently: the main IP address,
+    /// the interfaces and the route table.
+    pub const PARTS: usize = 3;
+
+    /// Collects the current network state of the host, with the route table
+    /// from `routes`.
+    ///
+    /// A missing main IP address (e.g. without any route to the internet) is
+    /// not an error and leaves `main_ip` unset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interfaces or the route table cannot be
+    /// collected.
+    pub fn collect(routes: &RouteCollector) -> Result<Self> {
+        Ok(Self {
+            main_ip: get_local_ip().ok(),
+            interfaces: get_ip_interfaces()?,
+            routes: routes.collect()?,
+            errors: Vec::new(),
+        })
+    }
+
+    /// Collects the current network state of the host like `collect`, but
+    /// leaves the main IP address, the interfaces or the route table empty if
+    /// they cannot be collected and records why in `errors` instead.
+    pub fn collect_partial(routes: &RouteCollector) -> Self {
+        let mut errors = Vec::new();
+        let main_ip = get_local_ip()
+            .context("Error getting IP address")
+            .map_err(|error| errors.push(ErrorReport::from(&error)))
+            .ok();
+        let interfaces = get_ip_interfaces().unwrap_or_else(|error| {
+            errors.push(ErrorReport::from(&error));
+            Vec::new()
+        });
+        let routes = routes.collect().unwrap_or_else(|error| {
+            errors.push(ErrorReport::from(&error));
+            RouteTable::default()
+        });
+        Self {
+            main_ip,
+            interfaces,
+            routes,
+            errors,
+        }
+    }
+
+    /// Loads a snapshot previously written with `save`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid
+    /// snapshot.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid snapshot file {}", path.display()))
+    }
+
+    /// Writes the snapshot to `path` as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write snapshot {}", path.display()))
+    }
+
+    /// Returns a copy of the snapshot with only the addresses and routes of
+    /// the given protocol ("ipv4", "ipv6", or "all").
+    pub fn filter_protocol(&self, protocol: &str) -> Self {
+        let mut snapshot = self.clone();
+        match protocol {
+            "ipv4" => snapshot.interfaces.retain(|interface| interface.is_ipv4()),
+            "ipv6" => snapshot.interfaces.retain(|interface| interface.is_ipv6()),
+            _ => {}
+        }
+        snapshot.routes.retain_protocol(protocol);
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route_table::RouteEntry;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mut snapshot = Snapshot {
+            main_ip: Some("192.168.1.10".to_string()),
+            interfaces: vec![InterfaceAddr {
+                name: "eth0".to_string(),
+                ip: "192.168.1.10".parse().unwrap(),
+                netmask: "255.255.255.0".parse().unwrap(),
+                prefix_len: 24,
+            }],
+            routes: RouteTable::new(),
+            errors: Vec::new(),
+        };
+        snapshot.routes.add_route(RouteEntry {
+            destination: "default".to_string(),
+            gateway: "192.168.1.1".to_string(),
+            flags: "UG".to_string(),
+            iface: "eth0".to_string(),
+            table: Some("main".to_string()),
+            metric: Some(100),
+            ..Default::default()
+        });
+
+        let path =
+            std::env::temp_dir().join(format!("ls_net_snapshot_{}.json", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.main_ip, snapshot.main_ip);
+        assert_eq!(loaded.interfaces, snapshot.interfaces);
+        assert_eq!(loaded.routes.ipv4_routes, snapshot.routes.ipv4_routes);
+        assert!(loaded.filter_protocol("ipv6").interfaces.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+
+use crate::probe::{RealProbe, SystemProbe};
+
+/// Socket tables of the Linux procfs and the protocol they describe.
+const PROC_NET_FILES: [(&str, &str); 4] = [
+    ("/proc/net/tcp", "tcp"),
+    ("/proc/net/tcp6", "tcp6"),
+    ("/proc/net/udp", "udp"),
+    ("/proc/net/udp6", "udp6"),
+];
+
+/// An open TCP or UDP socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Socket {
+    /// `tcp`, `tcp6`, `udp` or `udp6`.
+    pub proto: String,
+    pub local: SocketAddr,
+    /// The peer address; unspecified for listening and unconnected sockets.
+    pub remote: SocketAddr,
+    /// The TCP state, e.g. `LISTEN` or `ESTABLISHED`; `UNCONN` for
+    /// unconnected UDP sockets.
+    pub state: String,
+}
+
+impl Socket {
+    pub fn is_ipv4(&self) -> bool {
+        self.local.is_ipv4()
+    }
+}
+
+/// Gets all open TCP and UDP sockets of the host.
+///
+/// On Linux the sockets are read from `/proc/net/{tcp,udp}[6]`; other
+/// platforms use `netstat -an`.
+///
+/// # Errors
+///
+/// Returns an error if the socket tables cannot be read.
+pub fn get_sockets() -> Result<Vec<Socket>> {
+    get_sockets_from(&RealProbe)
+}
+
+/// Gets the sockets of the system behind `probe`, as described for
+/// `get_sockets`.
+///
+/// # Errors
+///
+/// Returns an error if the socket tables cannot be read.
+pub fn get_sockets_from(probe: &dyn SystemProbe) -> Result<Vec<Socket>> {
+    if probe.os() == "linux" {
+        let mut sockets = Vec::new();
+        for (path, proto) in PROC_NET_FILES {
+            // IPv6 may be disabled, leaving the tcp6/udp6 files missing
+            if let Ok(content) = probe.read_file(Path::new(path)) {
+                sockets.extend(parse_proc_net(&content, proto));
+            }
+        }
+        Ok(sockets)
+    } else {
+        let output = probe.run("netstat", &["-an"])?;
+        if !output.success {
+            return Err(anyhow!("Failed to execute netstat command"));
+        }
+        Ok(parse_netstat_sockets(&output.stdout))
+    }
+}
+
+/// Parses a socket table of the Linux procfs such as `/proc/net/tcp`.
+///
+/// The header line and malformed lines are skipped.
+pub fn parse_proc_net(content: &str, proto: &str) -> Vec<Socket> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let local = parse_proc_address(parts.get(1)?)?;
+            let remote = parse_proc_address(parts.get(2)?)?;
+            let state = u8::from_str_radix(parts.get(3)?, 16).ok()?;
+            Some(Socket {
+                proto: proto.to_string(),
+                local,
+                remote,
+                state: tcp_state_name(state, proto.starts_with("udp")),
+            })
+        })
+        .collect()
+}
+
+/// Parses an `ADDRESS:PORT` pair of the procfs socket tables, where the
+/// address is hex in host byte order per 32-bit word.
+fn parse_proc_address(value: &str) -> Option<SocketAddr> {
+    let (addr, port) = value.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let ip = match addr.len() {
+        8 => {
+            let word = u32::from_str_radix(addr, 16).ok()?;
+            IpAddr::V4(Ipv4Addr::from(word.to_ne_bytes()))
+        }
+        32 => {
+            let mut octets = [0u8; 16];
+            for (i, chunk) in octets.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&addr[i * 8..i * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+fn tcp_state_name(state: u8, udp: bool) -> String {
+    let name = match state {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECV",
+        0x04 => "FIN_WAIT1",
+        0x05 => "FIN_WAIT2",
+        0x06 => "TIME_WAIT",
+        0x07 if udp => "UNCONN",
+        0x07 => "CLOSE",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTEN",
+        0x0B => "CLOSING",
+        _ => return format!("{:#x}", state),
+    };
+    name.to_string()
+}
+
+/// Parses the TCP and UDP lines of BSD/macOS `netstat -an`, e.g.
+/// `tcp4  0  0  127.0.0.1.631  *.*  LISTEN`.
+///
+/// Addresses use a dot before the port, and `*` for unspecified parts.
+pub fn parse_netstat_sockets(output: &str) -> Vec<Socket> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let proto = match *parts.first()? {
+                "tcp4" | "tcp" => "tcp",
+                "tcp6" | "tcp46" => "tcp6",
+                "udp4" | "udp" => "udp",
+                "udp6" | "udp46" => "udp6",
+                _ => return None,
+            };
+            let ipv6 = proto.ends_with('6');
+            let local = parse_netstat_address(parts.get(3)?, ipv6)?;
+            let remote = parse_netstat_address(parts.get(4)?, ipv6)?;
+            let state = match parts.get(5) {
+                Some(state) => state.to_string(),
+                None => "UNCONN".to_string(),
+            };
+            Some(Socket {
+                proto: proto.to_string(),
+                local,
+                remote,
+                state,
+            })
+        })
+        .collect()
+}
+
+fn parse_netstat_address(value: &str, ipv6: bool) -> Option<SocketAddr> {
+    let (addr, port) = value.rsplit_once('.')?;
+    let port = if port == "*" { 0 } else { port.parse().ok()? };
+    let ip = match addr {
+        "*" if ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        "*" => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        // Strip a scope suffix, e.g. `fe80::1%lo0`
+        _ => addr.split('%').next()?.parse().ok()?,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+                   \x20  0: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1234 1\n\
+                   \x20  1: 0202000A:C350 0102000A:01BB 01 00000000:00000000 00:00000000 00000000  1000        0 5678 1\n";
+        let sockets = parse_proc_net(tcp, "tcp");
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].local.to_string(), "127.0.0.1:53");
+        assert_eq!(sockets[0].state, "LISTEN");
+        assert_eq!(sockets[1].remote.to_string(), "10.0.2.1:443");
+        assert_eq!(sockets[1].state, "ESTABLISHED");
+
+        let udp6 = "  sl  local_address                         remote_address                        st\n\
+                    \x20  0: 00000000000000000000000001000000:0223 00000000000000000000000000000000:0000 07\n";
+        let sockets = parse_proc_net(udp6, "udp6");
+        assert_eq!(sockets[0].local.to_string(), "[::1]:547");
+        assert_eq!(sockets[0].state, "UNCONN");
+    }
+
+    #[test]
+    fn test_parse_netstat_sockets() {
+        let output = "Active Internet connections (including servers)\n\
+                      Proto Recv-Q Send-Q  Local Address          Foreign Address        (state)\n\
+                      tcp4       0      0  192.168.1.10.52311     140.82.112.4.443       ESTABLISHED\n\
+                      tcp6       0      0  *.22                   *.*                    LISTEN\n\
+                      udp4       0      0  *.5353                 *.*\n\
+                      udp6       0      0  fe80::1%lo0.123        *.*\n";
+        let sockets = parse_netstat_sockets(output);
+        assert_eq!(sockets.len(), 4);
+        assert_eq!(sockets[0].local.to_string(), "192.168.1.10:52311");
+        assert_eq!(sockets[0].remote.to_string(), "140.82.112.4:443");
+        assert_eq!(sockets[1].local.to_string(), "[::]:22");
+        assert_eq!(sockets[2].state, "UNCONN");
+        assert_eq!(sockets[3].local.to_string(), "[fe80::1]:123");
+    }
+
+    #[test]
+    fn test_get_sockets_golden() {
+        use crate::golden::{assert_golden_json, fixture};
+        use crate::probe::FakeProbe;
+
+        let linux = FakeProbe::new("linux")
+            .file("/proc/net/tcp", &fixture("procfs/tcp"))
+            .file("/proc/net/tcp6", &fixture("procfs/tcp6"))
+            .file("/proc/net/udp", &fixture("procfs/udp"))
+            .file("/proc/net/udp6", &fixture("procfs/udp6"));
+        assert_golden_json(
+            "sockets/linux-procfs.json",
+            &get_sockets_from(&linux).unwrap(),
+        );
+
+        let macos =
+            FakeProbe::new("macos").command("netstat -an", &fixture("netstat/macos-14-an.txt"));
+        assert_golden_json("sockets/macos-14.json", &get_sockets_from(&macos).unwrap());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::render::{Element, Style};
+
+/// Separator between two columns of a table.
+const COLUMN_GAP: &str = "  ";
+
+/// A text table with a header row, printed with every column aligned to its
+/// widest cell.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new<S: Into<String>>(headers: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends a row. Missing cells are left empty and extra cells are
+    /// dropped.
+    pub fn push_row(&mut self, mut cells: Vec<String>) {
+        cells.resize(self.headers.len(), String::new());
+        self.rows.push(cells);
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        (0..self.headers.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .chain([self.headers[i].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// Renders the table with the header row styled as `Element::Header` and
+    /// the first column, the key of each row, as `Element::Address`.
+    pub fn render(&self, style: &Style) -> String {
+        let widths = self.widths();
+        let line = |cells: &[String], style: &dyn Fn(usize, String) -> String| {
+            let cells: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    // Pad before coloring, the escape codes have no width
+                    let cell = if i + 1 == cells.len() {
+                        cell.clone()
+                    } else {
+                        format!("{:w$}", cell, w = widths[i])
+                    };
+                    style(i, cell)
+                })
+                .collect();
+            cells.join(COLUMN_GAP).trim_end().to_string() + "\n"
+        };
+
+        let mut out = line(&self.headers, &|_, cell| {
+            style.paint(Element::Header, &cell)
+        });
+        for row in &self.rows {
+            out.push_str(&line(row, &|i, cell| match i {
+                0 => style.paint(Element::Address, &cell),
+                _ => cell,
+            }));
+        }
+        out
+    }
+
+    /// Renders the table as CSV (RFC 4180), quoting cells that contain a
+    /// comma, a quote or a line break.
+    pub fn render_csv(&self) -> String {
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect();
+            cells.join(",") + "\n"
+        };
+        std::iter::once(&self.headers)
+            .chain(&self.rows)
+            .map(|row| line(row))
+            .collect()
+    }
+
+    /// Renders the table as a GitHub Flavored Markdown table, with the
+    /// columns aligned for reading as text. Pipes in cells are escaped.
+    pub fn render_markdown(&self) -> String {
+        let escape = |cell: &String| cell.replace('|', "\\|");
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|i| {
+                std::iter::once(&self.headers)
+                    .chain(&self.rows)
+                    .map(|row| escape(&row[i]).chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect();
+        let line = |cells: Vec<String>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:w$}", cell, w = width))
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        let mut out = line(self.headers.iter().map(escape).collect());
+        out.push_str(&line(
+            widths.iter().map(|width| "-".repeat(*width)).collect(),
+        ));
+        for row in &self.rows {
+            out.push_str(&line(row.iter().map(escape).collect()));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::strip_colors;
+
+    #[test]
+    fn test_render_aligns_columns() {
+        let mut table = Table::new(["Destination", "Gateway", "Iface"]);
+        table.push_row(vec![
+            "192.168.100.0/24".to_string(),
+            "0.0.0.0".to_string(),
+            "eth0".to_string(),
+        ]);
+        table.push_row(vec!["default".to_string(), "192.168.100.1".to_string()]);
+
+        assert_eq!(
+            strip_colors(&table.render(&Style::default())),
+            "\
+Destination       Gateway        Iface
+192.168.100.0/24  0.0.0.0        eth0
+default           192.168.100.1
+"
+        );
+    }
+
+    #[test]
+    fn test_render_csv_and_markdown() {
+        let mut table = Table::new(["Destination", "Gateway"]);
+        table.push_row(vec!["default".to_string(), "a,\"b\"".to_string()]);
+        table.push_row(vec!["10.0.0.0/8".to_string(), "x|y".to_string()]);
+
+        assert_eq!(
+            table.render_csv(),
+            "Destination,Gateway\ndefault,\"a,\"\"b\"\"\"\n10.0.0.0/8,x|y\n"
+        );
+        assert_eq!(
+            table.render_markdown(),
+            "\
+| Destination | Gateway |
+| ----------- | ------- |
+| default     | a,\"b\"   |
+| 10.0.0.0/8  | x\\|y    |
+"
+        );
+        assert_eq!(
+            table.render(&Style {
+                color: false,
+                ..Style::default()
+            }),
+            strip_colors(&table.render(&Style::default()))
+        );
+    }
+}
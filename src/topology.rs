@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+use crate::ip_interfaces::InterfaceAddr;
+use crate::prefix::Prefix;
+use crate::route_table::{IpVersion, RouteEntry, RouteTable};
+
+/// The kind of a node in the topology graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeKind {
+    Interface,
+    Subnet,
+    Gateway,
+}
+
+/// The kind of an edge in the topology graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EdgeKind {
+    /// An interface address attaching the interface to a subnet.
+    Address,
+    /// A default route leaving through an interface towards a gateway.
+    DefaultRoute,
+    /// A static route leaving through an interface towards a next hop.
+    StaticRoute,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Node {
+    kind: NodeKind,
+    name: String,
+}
+
+impl Node {
+    fn id(&self) -> String {
+        let prefix = match self.kind {
+            NodeKind::Interface => "iface",
+            NodeKind::Subnet => "net",
+            NodeKind::Gateway => "gw",
+        };
+        format!("{}:{}", prefix, self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Edge {
+    kind: EdgeKind,
+    from: Node,
+    to: Node,
+    label: String,
+}
+
+/// A graph of the host's network topology.
+///
+/// Interfaces are connected to the subnets their addresses attach to, to the
+/// gateways of all default routes and to the next hops of static routes. The
+/// graph is built from already collected data, so it can be rendered from any
+/// source.
+#[derive(Debug, Default)]
+pub struct Topology {
+    nodes: BTreeSet<Node>,
+    edges: BTreeSet<Edge>,
+}
+
+impl Topology {
+    /// Builds the topology graph from the interface addresses and the route
+    /// table.
+    ///
+    /// The `protocol` argument can be either "ipv4", "ipv6", or "all" and
+    /// filters both the addresses and the routes that end up in the graph.
+    pub fn build(interfaces: &[InterfaceAddr], route_table: &RouteTable, protocol: &str) -> Self {
+        let mut topology = Topology::default();
+
+        for interface in interfaces {
+            if !protocol_matches(protocol, interface.ip) {
+                continue;
+            }
+            let iface = topology.add_node(NodeKind::Interface, &interface.name);
+            let subnet = Prefix::new(interface.ip, interface.prefix_len);
+            let subnet = topology.add_node(NodeKind::Subnet, &subnet.to_string());
+            topology.add_edge(EdgeKind::Address, iface, subnet, interface.ip.to_string());
+        }
+
+        let versions: &[IpVersion] = match protocol {
+            "ipv4" => &[IpVersion::IPv4],
+            "ipv6" => &[IpVersion::IPv6],
+            _ => &[IpVersion::IPv4, IpVersion::IPv6],
+        };
+
+        for version in versions {
+            let routes = match version {
+                IpVersion::IPv4 => &route_table.ipv4_routes,
+                IpVersion::IPv6 => &route_table.ipv6_routes,
+            };
+            for route in routes {
+                let Some(gateway) = next_hop(route) else {
+                    continue;
+                };
+                if route.is_default() {
+                    topology.add_route(EdgeKind::DefaultRoute, route, gateway, "default");
+                } else {
+                    topology.add_route(EdgeKind::StaticRoute, route, gateway, &route.destination);
+                }
+            }
+        }
+
+        topology
+    }
+
+    fn add_node(&mut self, kind: NodeKind, name: &str) -> Node {
+        let node = Node {
+            kind,
+            name: name.to_string(),
+        };
+        self.nodes.insert(node.clone());
+        node
+    }
+
+    fn add_edge(&mut self, kind: EdgeKind, from: Node, to: Node, label: String) {
+        self.edges.insert(Edge {
+            kind,
+            from,
+            to,
+            label,
+        });
+    }
+
+    fn add_route(&mut self, kind: EdgeKind, route: &RouteEntry, gateway: IpAddr, label: &str) {
+        let iface = self.add_node(NodeKind::Interface, &route.iface);
+        let gateway = self.add_node(NodeKind::Gateway, &gateway.to_string());
+        self.add_edge(kind, iface, gateway, label.to_string());
+    }
+
+    /// Renders the topology as a Graphviz `dot` digraph.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ls_net {\n");
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [fontname=\"Helvetica\"];\n");
+        out.push_str("    edge [fontname=\"Helvetica\", fontsize=10];\n");
+
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Interface => "box",
+                NodeKind::Subnet => "ellipse",
+                NodeKind::Gateway => "diamond",
+            };
+            out.push_str(&format!(
+                "    {} [label={}, shape={}];\n",
+                dot_quote(&node.id()),
+                dot_quote(&node.name),
+                shape
+            ));
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Address => "solid",
+                EdgeKind::DefaultRoute => "bold",
+                EdgeKind::StaticRoute => "dashed",
+            };
+            out.push_str(&format!(
+                "    {} -> {} [label={}, style={}];\n",
+                dot_quote(&edge.from.id()),
+                dot_quote(&edge.to.id()),
+                dot_quote(&edge.label),
+                style
+            ));
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the topology as a Mermaid flowchart.
+    ///
+    /// Mermaid IDs allow only a few characters, so nodes are numbered instead
+    /// of named after their addresses, which would collide once escaped.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("graph LR\n");
+        let ids: BTreeMap<&Node, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node, format!("n{}", index)))
+            .collect();
+
+        for node in &self.nodes {
+            let id = &ids[node];
+            let label = mermaid_quote(&node.name);
+            let shape = match node.kind {
+                NodeKind::Interface => format!("[{}]", label),
+                NodeKind::Subnet => format!("([{}])", label),
+                NodeKind::Gateway => format!("{{{}}}", label),
+            };
+            out.push_str(&format!("    {}{}\n", id, shape));
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Address => "-->",
+                EdgeKind::DefaultRoute => "==>",
+                EdgeKind::StaticRoute => "-.->",
+            };
+            out.push_str(&format!(
+                "    {} {}|{}| {}\n",
+                ids[&edge.from],
+                arrow,
+                mermaid_quote(&edge.label),
+                ids[&edge.to]
+            ));
+        }
+
+        out
+    }
+}
+
+/// Returns `true` if `ip` belongs to the address family selected by
+/// `protocol` ("ipv4", "ipv6", or "all").
+fn protocol_matches(protocol: &str, ip: IpAddr) -> bool {
+    match protocol {
+        "ipv4" => ip.is_ipv4(),
+        "ipv6" => ip.is_ipv6(),
+        _ => true,
+    }
+}
+
+/// Returns the next hop of a route if it is a real IP address.
+///
+/// Directly connected routes have an unspecified gateway (`0.0.0.0`, `::`) or
+/// a link-layer one (`link#4`, a MAC address) and have no next hop. A scope
+/// suffix such as `fe80::1%en0` is stripped.
+fn next_hop(route: &RouteEntry) -> Option<IpAddr> {
+    let gateway = route.gateway.split('%').next()?;
+    let ip: IpAddr = gateway.parse().ok()?;
+    if ip.is_unspecified() { None } else { Some(ip) }
+}
+
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn mermaid_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "#quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface(name: &str, ip: &str, prefix_len: u8) -> InterfaceAddr {
+        let ip: IpAddr = ip.parse().unwrap();
+        InterfaceAddr {
+            name: name.to_string(),
+            ip,
+            netmask: ip,
+            prefix_len,
+        }
+    }
+
+    fn route(destination: &str, gateway: &str, iface: &str, ip_version: IpVersion) -> RouteEntry {
+        RouteEntry {
+            destination: destination.to_string(),
+            gateway: gateway.to_string(),
+            flags: "UG".to_string(),
+            iface: iface.to_string(),
+            ip_version,
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> (Vec<InterfaceAddr>, RouteTable) {
+        let interfaces = vec![
+            interface("eth0", "192.168.1.10", 24),
+            interface("eth0", "fd00::10", 64),
+            interface("wg0", "10.8.0.2", 24),
+        ];
+        let mut route_table = RouteTable::new();
+        route_table.add_route(route("0.0.0.0", "192.168.1.1", "eth0", IpVersion::IPv4));
+        route_table.add_route(route("192.168.1.0", "0.0.0.0", "eth0", IpVersion::IPv4));
+        route_table.add_route(route("10.0.0.0/8", "10.8.0.1", "wg0", IpVersion::IPv4));
+        route_table.add_route(route("::/0", "fe80::1%eth0", "eth0", IpVersion::IPv6));
+        (interfaces, route_table)
+    }
+
+    #[test]
+    fn test_dot_contains_addresses_and_routes() {
+        let (interfaces, route_table) = sample();
+        let dot = Topology::build(&interfaces, &route_table, "ipv4").to_dot();
+
+        assert!(dot.starts_with("digraph ls_net {"));
+        assert!(dot.contains(
+            "\"iface:eth0\" -> \"net:192.168.1.0/24\" [label=\"192.168.1.10\", style=solid];"
+        ));
+        assert!(
+            dot.contains("\"iface:eth0\" -> \"gw:192.168.1.1\" [label=\"default\", style=bold];")
+        );
+        assert!(
+            dot.contains("\"iface:wg0\" -> \"gw:10.8.0.1\" [label=\"10.0.0.0/8\", style=dashed];")
+        );
+        assert!(!dot.contains("fd00"));
+        assert!(!dot.contains("gw:0.0.0.0"));
+    }
+
+    #[test]
+    fn test_mermaid_all_protocols() {
+        let (interfaces, route_table) = sample();
+        let mermaid = Topology::build(&interfaces, &route_table, "all").to_mermaid();
+
+        assert!(mermaid.starts_with("graph LR\n"));
+        assert!(mermaid.contains("    n4([\"fd00::/64\"])\n"));
+        assert!(mermaid.contains("    n7{\"fe80::1\"}\n"));
+        assert!(mermaid.contains("    n0 ==>|\"default\"| n7\n"));
+        assert!(mermaid.contains("    n1 -.->|\"10.0.0.0/8\"| n5\n"));
+    }
+
+    #[test]
+    fn test_mermaid_ids_do_not_collide() {
+        let interfaces = vec![
+            interface("eth0.1", "10.0.1.2", 24),
+            interface("eth0_1", "10.0.2.2", 24),
+        ];
+        let mermaid = Topology::build(&interfaces, &RouteTable::new(), "all").to_mermaid();
+
+        assert_eq!(
+            mermaid,
+            "\
+graph LR
+    n0[\"eth0.1\"]
+    n1[\"eth0_1\"]
+    n2([\"10.0.1.0/24\"])
+    n3([\"10.0.2.0/24\"])
+    n0 -->|\"10.0.1.2\"| n2
+    n1 -->|\"10.0.2.2\"| n3
+"
+        );
+    }
+
+    #[test]
+    fn test_every_default_route() {
+        let (interfaces, mut route_table) = sample();
+        let mut backup = route("default", "10.8.0.1", "wg0", IpVersion::IPv4);
+        backup.metric = Some(600);
+        route_table.add_route(backup);
+        let dot = Topology::build(&interfaces, &route_table, "ipv4").to_dot();
+
+        assert!(
+            dot.contains("\"iface:eth0\" -> \"gw:192.168.1.1\" [label=\"default\", style=bold];")
+        );
+        assert!(dot.contains("\"iface:wg0\" -> \"gw:10.8.0.1\" [label=\"default\", style=bold];"));
+    }
+}
@@ -0,0 +1,563 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::cmp::Ordering;
+use std::net::SocketAddr;
+use std::time::SystemTime;
+
+use crate::ip_interfaces::{InterfaceAddr, get_ip_interfaces};
+use crate::monitor::format_timestamp;
+use crate::neighbors::{Neighbor, get_neighbors};
+use crate::prefix::Prefix;
+use crate::route_table::route_table::RouteCollector;
+use crate::route_table::{RouteEntry, RouteTable};
+use crate::sockets::{Socket, get_sockets};
+
+/// Number of rows a page up/down key moves the selection.
+const PAGE_SIZE: usize = 10;
+
+/// Route fields shown in the detail pane, with their labels.
+const ROUTE_DETAIL_FIELDS: [(&str, &str); 12] = [
+    ("Destination", "destination"),
+    ("Gateway", "gateway"),
+    ("Interface", "iface"),
+    ("Flags", "flags"),
+    ("Genmask", "genmask"),
+    ("Table", "table"),
+    ("Metric", "metric"),
+    ("Protocol", "proto"),
+    ("Scope", "scope"),
+    ("Source", "src"),
+    ("Type", "type"),
+    ("Expire", "expire"),
+];
+
+/// The tabs of the dashboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tab {
+    Interfaces,
+    Routes,
+    Neighbors,
+    Sockets,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 4] = [Tab::Interfaces, Tab::Routes, Tab::Neighbors, Tab::Sockets];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Tab::Interfaces => "Interfaces",
+            Tab::Routes => "Routes",
+            Tab::Neighbors => "Neighbors",
+            Tab::Sockets => "Sockets",
+        }
+    }
+
+    pub fn headers(&self) -> &'static [&'static str] {
+        match self {
+            Tab::Interfaces => &["Name", "Address", "Prefix", "Netmask"],
+            Tab::Routes => &[
+                "Destination",
+                "Gateway",
+                "Iface",
+                "Metric",
+                "Proto",
+                "Table",
+                "Flags",
+            ],
+            Tab::Neighbors => &["Address", "MAC", "Iface", "State"],
+            Tab::Sockets => &["Proto", "Local", "Remote", "State"],
+        }
+    }
+}
+
+/// A row of a tab, with the lines of its detail pane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub cells: Vec<String>,
+    pub detail: Vec<(String, String)>,
+}
+
+/// The network state shown by the dashboard, gathered with the same
+/// collectors as the command line output.
+#[derive(Debug, Default)]
+pub struct Data {
+    pub interfaces: Vec<InterfaceAddr>,
+    pub routes: RouteTable,
+    pub neighbors: Vec<Neighbor>,
+    pub sockets: Vec<Socket>,
+    /// Collectors that failed, with their error message.
+    pub errors: Vec<String>,
+    pub updated: String,
+}
+
+impl Data {
+    /// Collects the current network state, with the route table from
+    /// `route_collector`. A failing collector leaves its part empty and
+    /// records the error instead of failing as a whole.
+    pub fn collect(route_collector: &RouteCollector) -> Self {
+        let mut errors = Vec::new();
+        let mut record =
+            |name: &str, error: anyhow::Error| errors.push(format!("{}: {}", name, error));
+
+        let interfaces = get_ip_interfaces().unwrap_or_else(|e| {
+            record("interfaces", e);
+            Vec::new()
+        });
+        let routes = route_collector.collect().unwrap_or_else(|e| {
+            record("routes", e);
+            RouteTable::new()
+        });
+        let neighbors = get_neighbors().unwrap_or_else(|e| {
+            record("neighbors", e);
+            Vec::new()
+        });
+        let sockets = get_sockets().unwrap_or_else(|e| {
+            record("sockets", e);
+            Vec::new()
+        });
+
+        Self {
+            interfaces,
+            routes,
+            neighbors,
+            sockets,
+            errors,
+            updated: format_timestamp(SystemTime::now()),
+        }
+    }
+}
+
+/// What the event loop should do after a key press.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    None,
+    Refresh,
+    Quit,
+}
+
+/// The state of the dashboard: the collected data turned into rows per tab,
+/// and the navigation, search and sort state.
+pub struct App {
+    protocol: String,
+    pub tab: usize,
+    items: Vec<Vec<Item>>,
+    pub selected: usize,
+    pub search: String,
+    pub searching: bool,
+    /// Sort column and whether it is reversed, per tab.
+    sort: Vec<(Option<usize>, bool)>,
+    pub show_detail: bool,
+    pub errors: Vec<String>,
+    pub updated: String,
+    /// Draws with ASCII characters only, for legacy consoles.
+    pub ascii: bool,
+}
+
+impl App {
+    pub fn new(protocol: &str, data: Data) -> Self {
+        let mut app = Self {
+            protocol: protocol.to_string(),
+            tab: 0,
+            items: Vec::new(),
+            selected: 0,
+            search: String::new(),
+            searching: false,
+            sort: vec![(None, false); Tab::ALL.len()],
+            show_detail: false,
+            errors: Vec::new(),
+            updated: String::new(),
+            ascii: false,
+        };
+        app.set_data(data);
+        app
+    }
+
+    pub fn current_tab(&self) -> Tab {
+        Tab::ALL[self.tab]
+    }
+
+    /// Returns the sort column of the current tab and whether the order is
+    /// reversed.
+    pub fn sort(&self) -> (Option<usize>, bool) {
+        self.sort[self.tab]
+    }
+
+    /// Replaces the shown data, e.g. after a refresh. The selection is kept
+    /// where possible.
+    pub fn set_data(&mut self, data: Data) {
+        let keep = |address: bool| match self.protocol.as_str() {
+            "ipv4" => address,
+            "ipv6" => !address,
+            _ => true,
+        };
+
+        let interfaces = data
+            .interfaces
+            .iter()
+            .filter(|interface| keep(interface.is_ipv4()))
+            .map(|interface| interface_item(interface, &data))
+            .collect();
+        let routes = data
+            .routes
+            .ipv4_routes
+            .iter()
+            .filter(|_| keep(true))
+            .chain(data.routes.ipv6_routes.iter().filter(|_| keep(false)))
+            .map(route_item)
+            .collect();
+        let neighbors = data
+            .neighbors
+            .iter()
+            .filter(|neighbor| keep(neighbor.ip.is_ipv4()))
+            .map(neighbor_item)
+            .collect();
+        let sockets = data
+            .sockets
+            .iter()
+            .filter(|socket| keep(socket.is_ipv4()))
+            .map(socket_item)
+            .collect();
+
+        self.items = vec![interfaces, routes, neighbors, sockets];
+        self.errors = data.errors;
+        self.updated = data.updated;
+        self.clamp_selection();
+    }
+
+    /// Returns the rows of the current tab that match the search, in sort
+    /// order.
+    pub fn visible_items(&self) -> Vec<&Item> {
+        let query = self.search.to_lowercase();
+        let mut items: Vec<&Item> = self.items[self.tab]
+            .iter()
+            .filter(|item| {
+                query.is_empty()
+                    || item
+                        .cells
+                        .iter()
+                        .any(|cell| cell.to_lowercase().contains(&query))
+            })
+            .collect();
+
+        let (column, reverse) = self.sort();
+        if let Some(column) = column {
+            items.sort_by(|a, b| compare_cells(&a.cells[column], &b.cells[column]));
+        }
+        if reverse {
+            items.reverse();
+        }
+        items
+    }
+
+    /// Returns the selected row, if any row is visible.
+    pub fn selected_item(&self) -> Option<&Item> {
+        self.visible_items().get(self.selected).copied()
+    }
+
+    /// Updates the state for a key press.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        if self.searching {
+            match key.code {
+                KeyCode::Char(c) => self.search.push(c),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                }
+                _ => {}
+            }
+            self.selected = 0;
+            return Action::None;
+        }
+
+        let count = self.visible_items().len();
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.search.is_empty() && !self.show_detail => return Action::Quit,
+            KeyCode::Esc => {
+                self.search.clear();
+                self.show_detail = false;
+            }
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                self.select_tab((self.tab + 1) % Tab::ALL.len())
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                self.select_tab((self.tab + Tab::ALL.len() - 1) % Tab::ALL.len())
+            }
+            KeyCode::Char(c @ '1'..='4') => self.select_tab(c as usize - '1' as usize),
+            KeyCode::Down | KeyCode::Char('j') => self.selected += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::PageDown => self.selected += PAGE_SIZE,
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(PAGE_SIZE),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = count.saturating_sub(1),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => {
+                let columns = self.current_tab().headers().len();
+                let (column, _) = &mut self.sort[self.tab];
+                *column = match *column {
+                    None => Some(0),
+                    Some(i) if i + 1 < columns => Some(i + 1),
+                    Some(_) => None,
+                };
+            }
+            KeyCode::Char('r') => self.sort[self.tab].1 = !self.sort[self.tab].1,
+            KeyCode::Enter | KeyCode::Char('d') => self.show_detail = !self.show_detail,
+            KeyCode::Char('R') | KeyCode::F(5) => return Action::Refresh,
+            _ => {}
+        }
+        self.clamp_selection();
+        Action::None
+    }
+
+    fn select_tab(&mut self, tab: usize) {
+        self.tab = tab;
+        self.selected = 0;
+        self.search.clear();
+    }
+
+    fn clamp_selection(&mut self) {
+        let count = self.visible_items().len();
+        self.selected = self.selected.min(count.saturating_sub(1));
+    }
+}
+
+/// Compares two cells by their value: numbers numerically, addresses and
+/// prefixes by address, anything else case-insensitively as text.
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    if let (Ok(a), Ok(b)) = (a.parse::<u64>(), b.parse::<u64>()) {
+        return a.cmp(&b);
+    }
+    if let (Ok(a), Ok(b)) = (a.parse::<Prefix>(), b.parse::<Prefix>()) {
+        return a.cmp(&b);
+    }
+    if let (Ok(a), Ok(b)) = (a.parse::<SocketAddr>(), b.parse::<SocketAddr>()) {
+        return a.cmp(&b);
+    }
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+fn interface_item(interface: &InterfaceAddr, data: &Data) -> Item {
+    let mut detail = vec![
+        ("Interface".to_string(), interface.name.clone()),
+        ("Address".to_string(), interface.ip.to_string()),
+        ("Netmask".to_string(), interface.netmask.to_string()),
+        (
+            "Network".to_string(),
+            Prefix::new(interface.ip, interface.prefix_len).to_string(),
+        ),
+    ];
+    for route in data
+        .routes
+        .ipv4_routes
+        .iter()
+        .chain(&data.routes.ipv6_routes)
+        .filter(|route| route.iface == interface.name)
+    {
+        detail.push(("Route".to_string(), route.describe()));
+    }
+    for neighbor in data
+        .neighbors
+        .iter()
+        .filter(|neighbor| neighbor.iface == interface.name)
+    {
+        detail.push((
+            "Neighbor".to_string(),
+            format!(
+                "{} {} {}",
+                neighbor.ip,
+                neighbor.mac.as_deref().unwrap_or("-"),
+                neighbor.state
+            ),
+        ));
+    }
+
+    Item {
+        cells: vec![
+            interface.name.clone(),
+            interface.ip.to_string(),
+            interface.prefix_len.to_string(),
+            interface.netmask.to_string(),
+        ],
+        detail,
+    }
+}
+
+fn route_item(route: &RouteEntry) -> Item {
+    let field = |name: &str| route.get_field(name).unwrap_or_default();
+    Item {
+        cells: vec![
+            route.destination.clone(),
+            route.gateway.clone(),
+            route.iface.clone(),
+            field("metric"),
+            field("proto"),
+            route.table_name().to_string(),
+            route.flags.clone(),
+        ],
+        detail: ROUTE_DETAIL_FIELDS
+            .iter()
+            .filter_map(|(label, name)| Some((label.to_string(), route.get_field(name)?)))
+            .chain([("Summary".to_string(), route.describe())])
+            .collect(),
+    }
+}
+
+fn neighbor_item(neighbor: &Neighbor) -> Item {
+    let cells = vec![
+        neighbor.ip.to_string(),
+        neighbor.mac.clone().unwrap_or_else(|| "-".to_string()),
+        neighbor.iface.clone(),
+        neighbor.state.clone(),
+    ];
+    Item {
+        detail: Tab::Neighbors
+            .headers()
+            .iter()
+            .zip(&cells)
+            .map(|(header, cell)| (header.to_string(), cell.clone()))
+            .collect(),
+        cells,
+    }
+}
+
+fn socket_item(socket: &Socket) -> Item {
+    let cells = vec![
+        socket.proto.clone(),
+        socket.local.to_string(),
+        socket.remote.to_string(),
+        socket.state.clone(),
+    ];
+    Item {
+        detail: Tab::Sockets
+            .headers()
+            .iter()
+            .zip(&cells)
+            .map(|(header, cell)| (header.to_string(), cell.clone()))
+            .collect(),
+        cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn sample_data() -> Data {
+        let mut routes = RouteTable::new();
+        for (destination, gateway, iface, metric) in [
+            ("default", "192.168.1.1", "eth0", 100),
+            ("192.168.1.0/24", "0.0.0.0", "eth0", 100),
+            ("10.8.0.0/24", "0.0.0.0", "wg0", 50),
+        ] {
+            routes.add_route(RouteEntry {
+                destination: destination.to_string(),
+                gateway: gateway.to_string(),
+                iface: iface.to_string(),
+                metric: Some(metric),
+                ..Default::default()
+            });
+        }
+        let address = |name: &str, ip: &str| InterfaceAddr {
+            name: name.to_string(),
+            ip: ip.parse().unwrap(),
+            netmask: "255.255.255.0".parse().unwrap(),
+            prefix_len: 24,
+        };
+
+        Data {
+            interfaces: vec![address("eth0", "192.168.1.10"), address("wg0", "10.8.0.2")],
+            routes,
+            neighbors: vec![Neighbor {
+                ip: "192.168.1.1".parse().unwrap(),
+                mac: Some("52:54:00:12:34:56".to_string()),
+                iface: "eth0".to_string(),
+                state: "reachable".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_filters_rows() {
+        let mut app = App::new("all", sample_data());
+        assert_eq!(app.handle_key(key(KeyCode::Char('2'))), Action::None);
+        assert_eq!(app.current_tab(), Tab::Routes);
+        assert_eq!(app.visible_items().len(), 3);
+
+        for code in [KeyCode::Char('/'), KeyCode::Char('W'), KeyCode::Char('g')] {
+            app.handle_key(key(code));
+        }
+        assert_eq!(app.visible_items().len(), 1);
+        assert_eq!(app.visible_items()[0].cells[0], "10.8.0.0/24");
+
+        app.handle_key(key(KeyCode::Esc));
+        assert!(!app.searching);
+        assert_eq!(app.visible_items().len(), 3);
+    }
+
+    #[test]
+    fn test_sort_by_column() {
+        let mut app = App::new("all", sample_data());
+        app.handle_key(key(KeyCode::Tab));
+
+        // Sort by the metric column
+        for _ in 0..4 {
+            app.handle_key(key(KeyCode::Char('s')));
+        }
+        assert_eq!(app.sort(), (Some(3), false));
+        let ifaces: Vec<&str> = app
+            .visible_items()
+            .iter()
+            .map(|item| item.cells[2].as_str())
+            .collect();
+        assert_eq!(ifaces, ["wg0", "eth0", "eth0"]);
+
+        app.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(app.visible_items()[2].cells[2], "wg0");
+    }
+
+    #[test]
+    fn test_navigation_and_detail() {
+        let mut app = App::new("all", sample_data());
+        app.handle_key(key(KeyCode::End));
+        assert_eq!(app.selected, 1);
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.selected, 1);
+
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.show_detail);
+        let detail = &app.selected_item().unwrap().detail;
+        assert!(detail.contains(&("Network".to_string(), "192.168.1.0/24".to_string())));
+        assert!(detail.iter().any(|(label, _)| label == "Neighbor"));
+        assert_eq!(
+            detail.iter().filter(|(label, _)| label == "Route").count(),
+            2
+        );
+
+        assert_eq!(app.handle_key(key(KeyCode::Char('R'))), Action::Refresh);
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), Action::Quit);
+    }
+
+    #[test]
+    fn test_compare_cells() {
+        assert_eq!(compare_cells("9", "10"), Ordering::Less);
+        assert_eq!(compare_cells("10.0.0.9", "10.0.0.10"), Ordering::Less);
+        assert_eq!(
+            compare_cells("192.168.1.1:80", "192.168.1.1:443"),
+            Ordering::Less
+        );
+        assert_eq!(compare_cells("eth0", "WG0"), Ordering::Less);
+    }
+}
@@ -0,0 +1,64 @@
+mod app;
+mod ui;
+
+use anyhow::Result;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use std::time::{Duration, Instant};
+
+use crate::route_table::route_table::RouteCollector;
+use app::{Action, App, Data};
+
+/// Runs the full-screen dashboard until the user quits.
+///
+/// The network state is collected again every `interval`, or on demand with
+/// `R`. Only addresses, routes, neighbors and sockets of the given protocol
+/// are shown, with the route table collected by `routes`. With `ascii`,
+/// borders and markers are drawn with ASCII characters only.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be set up or read from.
+pub fn run_tui(
+    protocol: &str,
+    interval: Duration,
+    ascii: bool,
+    routes: &RouteCollector,
+) -> Result<()> {
+    let mut app = App::new(protocol, Data::collect(routes));
+    app.ascii = ascii;
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app, interval, routes);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    interval: Duration,
+    routes: &RouteCollector,
+) -> Result<()> {
+    let mut last_refresh = Instant::now();
+    loop {
+        let mut refresh = false;
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        let timeout = interval.saturating_sub(last_refresh.elapsed());
+        if event::poll(timeout)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match app.handle_key(key) {
+                Action::Quit => return Ok(()),
+                Action::Refresh => refresh = true,
+                Action::None => {}
+            }
+        }
+
+        if refresh || last_refresh.elapsed() >= interval {
+            app.set_data(Data::collect(routes));
+            last_refresh = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::border;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState, Tabs, Wrap};
+
+use super::app::{App, Tab};
+
+/// Upper bound for the width of a table column.
+const MAX_COLUMN_WIDTH: usize = 45;
+
+/// Borders drawn with ASCII characters only.
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// Returns a bordered block, with ASCII borders if the app asks for them.
+fn bordered<'a>(app: &App) -> Block<'a> {
+    if app.ascii {
+        Block::bordered().border_set(ASCII_BORDER)
+    } else {
+        Block::bordered()
+    }
+}
+
+/// Draws the whole dashboard: the tab bar, the table of the current tab with
+/// an optional detail pane, and the status line.
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [tabs_area, main_area, status_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let tabs = Tabs::new(
+        Tab::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| format!("{} {}", i + 1, tab.title())),
+    )
+    .select(app.tab)
+    .highlight_style(Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    .block(bordered(app).title(" ls_net "));
+    frame.render_widget(tabs, tabs_area);
+
+    if app.show_detail {
+        let [table_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main_area);
+        draw_table(frame, app, table_area);
+        draw_detail(frame, app, detail_area);
+    } else {
+        draw_table(frame, app, main_area);
+    }
+
+    draw_status(frame, app, status_area);
+}
+
+fn draw_table(frame: &mut Frame, app: &App, area: Rect) {
+    let tab = app.current_tab();
+    let items = app.visible_items();
+    let (sort_column, reverse) = app.sort();
+
+    let header = Row::new(tab.headers().iter().enumerate().map(|(i, header)| {
+        let marker = match (sort_column == Some(i), reverse, app.ascii) {
+            (true, false, false) => " ▲",
+            (true, true, false) => " ▼",
+            (true, false, true) => " ^",
+            (true, true, true) => " v",
+            (false, _, _) => "",
+        };
+        Cell::from(format!("{}{}", header, marker))
+    }))
+    .style(Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD));
+
+    let widths: Vec<Constraint> = tab
+        .headers()
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            let width = items
+                .iter()
+                .map(|item| item.cells[i].chars().count())
+                .chain([header.len() + 2])
+                .max()
+                .unwrap_or(0)
+                .min(MAX_COLUMN_WIDTH);
+            Constraint::Length(width as u16)
+        })
+        .collect();
+
+    let rows = items.iter().map(|item| {
+        Row::new(item.cells.iter().enumerate().map(|(i, cell)| {
+            // The first column is the key of the row, as in the text output
+            let style = if i == 0 {
+                Style::new().fg(Color::Yellow)
+            } else {
+                Style::new()
+            };
+            Cell::from(cell.as_str()).style(style)
+        }))
+    });
+
+    let title = format!(" {} ({}) ", tab.title(), items.len());
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(bordered(app).title(title))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+
+    let mut state =
+        TableState::default().with_selected((!items.is_empty()).then_some(app.selected));
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_detail(frame: &mut Frame, app: &App, area: Rect) {
+    let lines: Vec<Line> = match app.selected_item() {
+        Some(item) => item
+            .detail
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(format!("{}: ", label), Style::new().fg(Color::Blue)),
+                    Span::raw(value.as_str()),
+                ])
+            })
+            .collect(),
+        None => vec![Line::from("Nothing selected")],
+    };
+
+    let detail = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(bordered(app).title(" Details "));
+    frame.render_widget(detail, area);
+}
+
+fn draw_status(frame: &mut Frame, app: &App, area: Rect) {
+    let line = if app.searching {
+        Line::from(vec![
+            Span::styled("/", Style::new().fg(Color::Yellow)),
+            Span::raw(app.search.as_str()),
+            Span::styled("_", Style::new().add_modifier(Modifier::SLOW_BLINK)),
+        ])
+    } else if let Some(error) = app.errors.first() {
+        Line::from(Span::styled(error.as_str(), Style::new().fg(Color::Red)))
+    } else {
+        let arrows = if app.ascii { "<-/->" } else { "←/→" };
+        let mut spans = vec![Span::styled(
+            format!(
+                "q quit  {} tabs  / search  s sort  r reverse  enter details  R refresh",
+                arrows
+            ),
+            Style::new().fg(Color::DarkGray),
+        )];
+        if !app.search.is_empty() {
+            spans.push(Span::styled(
+                format!("  filter: {}", app.search),
+                Style::new().fg(Color::Yellow),
+            ));
+        }
+        spans.push(Span::raw(format!("  updated {}", app.updated)));
+        Line::from(spans)
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ip_interfaces::InterfaceAddr;
+    use crate::tui::app::Data;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_table_and_detail() {
+        let data = Data {
+            interfaces: vec![InterfaceAddr {
+                name: "eth0".to_string(),
+                ip: "192.168.1.10".parse().unwrap(),
+                netmask: "255.255.255.0".parse().unwrap(),
+                prefix_len: 24,
+            }],
+            ..Default::default()
+        };
+        let mut app = App::new("all", data);
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let content = screen(&terminal);
+        assert!(content.contains("1 Interfaces"));
+        assert!(content.contains("192.168.1.10"));
+        assert!(!content.contains("Details"));
+
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let content = screen(&terminal);
+        assert!(content.contains("Details"));
+        assert!(content.contains("Network: 192.168.1.0/24"));
+    }
+}